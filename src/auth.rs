@@ -0,0 +1,160 @@
+use crate::client::ClientError;
+use crate::http::TimeoutError;
+use crate::store;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Lifetime assumed for access tokens issued without `expires_in`.
+const DEFAULT_TOKEN_LIFETIME: i64 = 300;
+/// Tokens are refreshed this many seconds before they expire.
+const TOKEN_EXPIRY_MARGIN: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AuthMode {
+    #[serde(rename = "basic")]
+    Basic,
+    #[serde(rename = "bearer")]
+    Bearer,
+    #[serde(rename = "oauth2")]
+    OAuth2,
+}
+
+impl FromStr for AuthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "basic" => Ok(AuthMode::Basic),
+            "bearer" => Ok(AuthMode::Bearer),
+            "oauth2" => Ok(AuthMode::OAuth2),
+            _ => Err(format!(
+                "invalid auth mode {}, expected basic, bearer or oauth2",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AuthMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthMode::Basic => write!(f, "basic"),
+            AuthMode::Bearer => write!(f, "bearer"),
+            AuthMode::OAuth2 => write!(f, "oauth2"),
+        }
+    }
+}
+
+/// OAuth2 client-credentials grant parameters.
+#[derive(Debug, Clone)]
+pub struct ClientCredentials {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>,
+}
+
+/// Bearer tokens for a [`Client`](crate::Client), asked for before each request.
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// A token that is still valid, or a newly issued one with `refresh` after
+    /// the broker rejected the previous one.
+    async fn token(&self, refresh: bool) -> Result<String, Box<dyn Error>>;
+}
+
+/// Tokens of the client-credentials grant, cached in the local store.
+pub struct ClientCredentialsProvider {
+    pub client: reqwest::Client,
+    pub credentials: ClientCredentials,
+}
+
+#[async_trait]
+impl TokenProvider for ClientCredentialsProvider {
+    async fn token(&self, refresh: bool) -> Result<String, Box<dyn Error>> {
+        match refresh {
+            true => request_token(&self.client, &self.credentials).await,
+            false => access_token(&self.client, &self.credentials).await,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<i64>,
+}
+
+/// Returns an access token for the given client and scope, reusing the one
+/// cached in the local store until it is about to expire.
+pub async fn access_token(
+    client: &reqwest::Client,
+    credentials: &ClientCredentials,
+) -> Result<String, Box<dyn Error>> {
+    if let Some((token, expires_at)) = store::token_get(
+        &credentials.token_url,
+        &credentials.client_id,
+        credentials.scope.as_deref(),
+    )? {
+        if expires_at > Utc::now().naive_utc() + Duration::seconds(TOKEN_EXPIRY_MARGIN) {
+            return Ok(token);
+        }
+    }
+    request_token(client, credentials).await
+}
+
+/// Requests a new access token and caches it in the local store.
+async fn request_token(
+    client: &reqwest::Client,
+    credentials: &ClientCredentials,
+) -> Result<String, Box<dyn Error>> {
+    let mut form = vec![("grant_type", "client_credentials")];
+    if let Some(scope) = &credentials.scope {
+        form.push(("scope", scope));
+    }
+
+    // timeouts exit like timed out broker requests
+    let timeout = |source: reqwest::Error| -> Box<dyn Error> {
+        match source.is_timeout() {
+            true => Box::new(ClientError::Timeout(TimeoutError {
+                context: format!("token request to {}", credentials.token_url),
+                source: Box::new(source),
+            })),
+            false => Box::new(source),
+        }
+    };
+
+    let response = client
+        .post(&credentials.token_url)
+        .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
+        .form(&form)
+        .send()
+        .await
+        .map_err(timeout)?;
+
+    let status = response.status();
+    let content = response.text().await.map_err(timeout)?;
+    if !status.is_success() {
+        return Err(format!(
+            "token request to {} failed: status code {}: {}",
+            credentials.token_url, status, content
+        )
+        .into());
+    }
+
+    let token: TokenResponse = serde_json::from_str(&content)?;
+    let expires_at = Utc::now().naive_utc()
+        + Duration::seconds(token.expires_in.unwrap_or(DEFAULT_TOKEN_LIFETIME));
+
+    store::token_save(
+        credentials.token_url.clone(),
+        credentials.client_id.clone(),
+        credentials.scope.clone(),
+        token.access_token.clone(),
+        expires_at,
+    )?;
+
+    Ok(token.access_token)
+}
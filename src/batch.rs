@@ -0,0 +1,131 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+/// One line of a `rocs batch` file, selected by its `operation` field.
+///
+/// Instances and bindings are referred to by ID, like the single operation
+/// commands. IDs of new instances and bindings are generated when absent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BatchOperation {
+    Provision {
+        /// Service offering name or ID.
+        service: String,
+        /// Plan name or ID.
+        plan: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        instance_id: Option<String>,
+        /// Recorded in the local store, as manifests do.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parameters: Option<Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Value>,
+    },
+    Update {
+        instance_id: String,
+        /// Needed for instances missing from the local store.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        service: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        plan: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parameters: Option<Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Value>,
+    },
+    Bind {
+        instance_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        binding_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parameters: Option<Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<Value>,
+    },
+    Unbind {
+        instance_id: String,
+        binding_id: String,
+    },
+    Deprovision {
+        instance_id: String,
+    },
+}
+
+impl BatchOperation {
+    /// Value of the `operation` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BatchOperation::Provision { .. } => "provision",
+            BatchOperation::Update { .. } => "update",
+            BatchOperation::Bind { .. } => "bind",
+            BatchOperation::Unbind { .. } => "unbind",
+            BatchOperation::Deprovision { .. } => "deprovision",
+        }
+    }
+
+    /// Instance the operation acts on, unknown before a provision without `instance_id`.
+    pub fn instance_id(&self) -> Option<&str> {
+        match self {
+            BatchOperation::Provision { instance_id, .. } => instance_id.as_deref(),
+            BatchOperation::Update { instance_id, .. }
+            | BatchOperation::Bind { instance_id, .. }
+            | BatchOperation::Unbind { instance_id, .. }
+            | BatchOperation::Deprovision { instance_id } => Some(instance_id),
+        }
+    }
+}
+
+/// Operations of a JSONL batch with their line numbers, blank lines are skipped.
+///
+/// Every line is checked before anything runs, the first invalid one fails
+/// the whole batch.
+pub fn parse(content: &str) -> Result<Vec<(usize, BatchOperation)>, Box<dyn Error>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map(|operation| (i + 1, operation))
+                .map_err(|e| format!("line {}: {}", i + 1, e).into())
+        })
+        .collect()
+}
+
+/// Splits operations into queues run one after the other, keeping the file order.
+///
+/// Operations on the same instance share a queue, so a bind starts once the
+/// provisioning before it finished. Provisions without an instance ID, which
+/// nothing can refer to, get a queue of their own.
+pub fn queues(operations: Vec<(usize, BatchOperation)>) -> Vec<Vec<(usize, BatchOperation)>> {
+    let mut queues: Vec<Vec<(usize, BatchOperation)>> = Vec::new();
+    let mut instances: HashMap<String, usize> = HashMap::new();
+
+    for (line, operation) in operations {
+        let queue = match operation.instance_id() {
+            Some(instance_id) => *instances
+                .entry(instance_id.to_string())
+                .or_insert(queues.len()),
+            None => queues.len(),
+        };
+        match queues.get_mut(queue) {
+            Some(queue) => queue.push((line, operation)),
+            None => queues.push(vec![(line, operation)]),
+        }
+    }
+
+    queues
+}
+
+/// Reads and parses a batch file, see [`parse`].
+pub fn load(path: &str) -> Result<Vec<(usize, BatchOperation)>, Box<dyn Error>> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    parse(&content)
+}
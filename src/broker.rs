@@ -0,0 +1,364 @@
+use crate::client::ClientError;
+
+use async_trait::async_trait;
+use reqwest::{header, StatusCode};
+use rocl::apis::{
+    catalog_api::CatalogGetError,
+    configuration::Configuration,
+    service_bindings_api::{
+        service_binding_binding, service_binding_get, service_binding_last_operation_get,
+        service_binding_unbinding,
+    },
+    service_instances_api::{
+        service_instance_deprovision, service_instance_get, service_instance_last_operation_get,
+        service_instance_provision, service_instance_update,
+    },
+    Error as ApiError, ResponseContent,
+};
+use rocl::models::{
+    AsyncOperation, LastOperationResource, ServiceBindingRequest, ServiceBindingResource,
+    ServiceBindingResponse, ServiceInstanceProvisionRequestBody, ServiceInstanceProvisionResponse,
+    ServiceInstanceResource, ServiceInstanceUpdateRequestBody,
+};
+use serde_json::Value;
+
+/// The OSB operations [`Client`](crate::Client) sends to a broker.
+///
+/// [`RoclBroker`] talks to a broker over HTTP, [`FakeBroker`](crate::fake::FakeBroker)
+/// answers with scripted responses so commands can be tested without one.
+#[async_trait]
+pub trait Broker: Send + Sync {
+    /// Catalog document as served, [`CatalogDocument::NotModified`] when it still matches `etag`.
+    async fn catalog(&self, etag: Option<&str>) -> Result<CatalogDocument, ClientError>;
+
+    async fn provision(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceProvisionRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceInstanceProvisionResponse, ClientError>;
+
+    async fn update(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceUpdateRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError>;
+
+    async fn deprovision(
+        &self,
+        instance_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError>;
+
+    async fn bind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        body: ServiceBindingRequest,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceBindingResponse, ClientError>;
+
+    async fn unbind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError>;
+
+    async fn fetch_instance(
+        &self,
+        instance_id: &str,
+    ) -> Result<ServiceInstanceResource, ClientError>;
+
+    async fn fetch_binding(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+    ) -> Result<ServiceBindingResource, ClientError>;
+
+    async fn last_operation(
+        &self,
+        query: &LastOperationQuery,
+    ) -> Result<LastOperationResource, ClientError>;
+}
+
+/// Answer to a catalog request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogDocument {
+    /// The catalog did not change since the ETag sent with the request.
+    NotModified,
+    /// JSON document as served, catalogs breaking the OSB models are kept as is.
+    Modified {
+        content: String,
+        etag: Option<String>,
+    },
+}
+
+/// Selects the last operation of an instance, or of one of its bindings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LastOperationQuery {
+    pub instance_id: String,
+    pub binding_id: Option<String>,
+    pub service_id: Option<String>,
+    pub plan_id: Option<String>,
+    /// Operation token the broker returned when the operation started.
+    pub operation: Option<String>,
+}
+
+/// Sends broker operations over HTTP with rocl.
+#[derive(Debug, Clone)]
+pub struct RoclBroker {
+    config: Configuration,
+    api_version: String,
+    originating_identity: Option<String>,
+}
+
+impl RoclBroker {
+    /// Wraps a rocl configuration that already carries the broker URL, HTTP client and credentials.
+    pub fn new(
+        config: Configuration,
+        api_version: &str,
+        originating_identity: Option<&str>,
+    ) -> RoclBroker {
+        RoclBroker {
+            config,
+            api_version: api_version.to_string(),
+            originating_identity: originating_identity.map(str::to_owned),
+        }
+    }
+
+    pub fn configuration(&self) -> &Configuration {
+        &self.config
+    }
+
+    fn identity(&self) -> Option<&str> {
+        self.originating_identity.as_deref()
+    }
+}
+
+#[async_trait]
+impl Broker for RoclBroker {
+    async fn catalog(&self, etag: Option<&str>) -> Result<CatalogDocument, ClientError> {
+        // rocl's catalog_get hides response headers, the request is made here to read the ETag
+        let config = &self.config;
+        let mut request = config
+            .client
+            .get(format!("{}/v2/catalog", config.base_path))
+            .header("X-Broker-API-Version", &self.api_version);
+        if let Some(user_agent) = &config.user_agent {
+            request = request.header(header::USER_AGENT, user_agent);
+        }
+        if let Some((username, password)) = &config.basic_auth {
+            request = request.basic_auth(username, password.as_ref());
+        }
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        let catalog_error =
+            |e: ApiError<CatalogGetError>| ClientError::new("failed to get catalog", e);
+        let response = request
+            .send()
+            .await
+            .map_err(|e| catalog_error(ApiError::Reqwest(e)))?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_MODIFIED && etag.is_some() {
+            return Ok(CatalogDocument::NotModified);
+        }
+
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let content = response
+            .text()
+            .await
+            .map_err(|e| catalog_error(ApiError::Reqwest(e)))?;
+
+        if !status.is_success() {
+            return Err(catalog_error(ApiError::ResponseError(ResponseContent {
+                status,
+                content,
+                entity: None,
+            })));
+        }
+
+        Ok(CatalogDocument::Modified { content, etag })
+    }
+
+    async fn provision(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceProvisionRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceInstanceProvisionResponse, ClientError> {
+        service_instance_provision(
+            &self.config,
+            &self.api_version,
+            instance_id,
+            body,
+            self.identity(),
+            Some(accepts_incomplete),
+        )
+        .await
+        .map_err(|e| ClientError::new("provision request failed", e))
+    }
+
+    async fn update(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceUpdateRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        service_instance_update(
+            &self.config,
+            &self.api_version,
+            instance_id,
+            body,
+            self.identity(),
+            Some(accepts_incomplete),
+        )
+        .await
+        .map(async_operation)
+        .map_err(|e| ClientError::new("update request failed", e))
+    }
+
+    async fn deprovision(
+        &self,
+        instance_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        service_instance_deprovision(
+            &self.config,
+            &self.api_version,
+            instance_id,
+            service_id,
+            plan_id,
+            self.identity(),
+            Some(accepts_incomplete),
+        )
+        .await
+        .map(async_operation)
+        .map_err(|e| ClientError::new("deprovisioning request failed", e))
+    }
+
+    async fn bind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        body: ServiceBindingRequest,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceBindingResponse, ClientError> {
+        service_binding_binding(
+            &self.config,
+            &self.api_version,
+            instance_id,
+            binding_id,
+            body,
+            self.identity(),
+            Some(accepts_incomplete),
+        )
+        .await
+        .map_err(|e| ClientError::new("binding failed", e))
+    }
+
+    async fn unbind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        service_binding_unbinding(
+            &self.config,
+            &self.api_version,
+            instance_id,
+            binding_id,
+            service_id,
+            plan_id,
+            self.identity(),
+            Some(accepts_incomplete),
+        )
+        .await
+        .map(async_operation)
+        .map_err(|e| ClientError::new("service binding unbind failed", e))
+    }
+
+    async fn fetch_instance(
+        &self,
+        instance_id: &str,
+    ) -> Result<ServiceInstanceResource, ClientError> {
+        service_instance_get(
+            &self.config,
+            &self.api_version,
+            instance_id,
+            self.identity(),
+            None,
+            None,
+        )
+        .await
+        .map_err(|e| ClientError::new("failed to fetch service instance", e))
+    }
+
+    async fn fetch_binding(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+    ) -> Result<ServiceBindingResource, ClientError> {
+        service_binding_get(
+            &self.config,
+            &self.api_version,
+            instance_id,
+            binding_id,
+            self.identity(),
+            None,
+            None,
+        )
+        .await
+        .map_err(|e| ClientError::new("failed to fetch service binding", e))
+    }
+
+    async fn last_operation(
+        &self,
+        query: &LastOperationQuery,
+    ) -> Result<LastOperationResource, ClientError> {
+        match &query.binding_id {
+            Some(binding_id) => service_binding_last_operation_get(
+                &self.config,
+                &self.api_version,
+                &query.instance_id,
+                binding_id,
+                query.service_id.as_deref(),
+                query.plan_id.as_deref(),
+                query.operation.as_deref(),
+            )
+            .await
+            .map_err(|e| ClientError::new("failed to get binding last operation", e)),
+            None => service_instance_last_operation_get(
+                &self.config,
+                &self.api_version,
+                &query.instance_id,
+                query.service_id.as_deref(),
+                query.plan_id.as_deref(),
+                query.operation.as_deref(),
+            )
+            .await
+            .map_err(|e| ClientError::new("failed to fetch last operation", e)),
+        }
+    }
+}
+
+// brokers answer {} when they completed synchronously
+fn async_operation(body: Value) -> AsyncOperation {
+    serde_json::from_value(body).unwrap_or_else(|_| AsyncOperation::new())
+}
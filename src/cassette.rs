@@ -0,0 +1,153 @@
+use crate::trace::Exchange;
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// A request and the response it produced, one per line of a cassette file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub method: String,
+    /// Path and query, relative to the broker URL.
+    pub path: String,
+    pub request_headers: BTreeMap<String, String>,
+    /// Parsed when the body is JSON, a string otherwise.
+    #[serde(default)]
+    pub request_body: Value,
+    pub status: u16,
+    pub response_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub response_body: Value,
+}
+
+impl Interaction {
+    fn new(exchange: &Exchange) -> Interaction {
+        let exchange = exchange.redacted();
+        let path = match Url::parse(&exchange.url) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+            Err(_) => exchange.url.clone(),
+        };
+
+        Interaction {
+            method: exchange.method,
+            path,
+            request_headers: exchange.request_headers.into_iter().collect(),
+            request_body: body_value(&exchange.request_body),
+            status: exchange.status,
+            response_headers: exchange.response_headers.into_iter().collect(),
+            response_body: body_value(&exchange.response_body),
+        }
+    }
+
+    /// Response body as sent by the broker.
+    pub fn response_text(&self) -> String {
+        match &self.response_body {
+            Value::Null => String::new(),
+            Value::String(text) => text.clone(),
+            body => body.to_string(),
+        }
+    }
+}
+
+fn body_value(body: &str) -> Value {
+    match body.is_empty() {
+        true => Value::Null,
+        false => serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string())),
+    }
+}
+
+/// Appends every exchange with the broker, secrets redacted, to a JSONL cassette.
+pub struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Creates the cassette, replacing any previous recording.
+    pub fn new(path: &str) -> Result<Recorder, Box<dyn Error>> {
+        let file = File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?;
+        Ok(Recorder {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, exchange: &Exchange) -> Result<(), Box<dyn Error>> {
+        let line = serde_json::to_string(&Interaction::new(exchange))?;
+        let mut file = self.file.lock().unwrap();
+        writeln!(file, "{}", line)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Answers requests with the responses of a cassette instead of the broker.
+///
+/// Interactions are matched by method and path in recorded order. IDs rocs
+/// generates are random, so UUIDs in paths match any UUID. Once every
+/// matching interaction was used the last one is repeated, which keeps
+/// polling loops going until the recorded final state.
+pub struct Player {
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Player {
+    pub fn new(path: &str) -> Result<Player, Box<dyn Error>> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        let interactions = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                serde_json::from_str(line)
+                    .map(|interaction| (interaction, false))
+                    .map_err(|e| format!("invalid interaction at {}:{}: {}", path, number + 1, e))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Player {
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    pub fn play(&self, method: &str, path: &str) -> Option<Interaction> {
+        let key = template(path);
+        let mut interactions = self.interactions.lock().unwrap();
+        let mut matching = interactions
+            .iter_mut()
+            .filter(|(i, _)| i.method == method && template(&i.path) == key)
+            .peekable();
+
+        let mut last = None;
+        while let Some((interaction, used)) = matching.next() {
+            if !*used {
+                *used = true;
+                return Some(interaction.clone());
+            }
+            if matching.peek().is_none() {
+                last = Some(interaction.clone());
+            }
+        }
+        last
+    }
+}
+
+// replaces UUIDs so requests for resources created during the replay match the recording
+fn template(path: &str) -> String {
+    let delimiter = |c: char| matches!(c, '/' | '?' | '&' | '=');
+    path.split_inclusive(delimiter)
+        .map(|part| {
+            let token = part.trim_end_matches(delimiter);
+            match Uuid::parse_str(token) {
+                Ok(_) => format!("{{id}}{}", &part[token.len()..]),
+                Err(_) => part.to_string(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,864 @@
+use crate::cli::output::OutputFormat;
+use crate::cli::{parse_age, CONFORMANCE_CHECKS};
+
+use clap::{crate_authors, crate_version, Arg, Command};
+
+/// Command line definition of rocs.
+pub fn app() -> Command<'static> {
+    Command::new("rocs")
+        .version(crate_version!())
+        .author(crate_authors!())
+        .about("Rust OSB Client 'Super'")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("broker_url")
+                .short('b')
+                .long("broker")
+                .env("ROCS_BROKER_URL")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("broker_user")
+                .short('u')
+                .long("username")
+                .env("ROCS_BROKER_USERNAME")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("broker_pass")
+                .short('a')
+                .long("password")
+                .env("ROCS_BROKER_PASSWORD")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("profile")
+                .help("Named broker profile from ~/.config/rocs/profiles.yaml")
+                .long("profile")
+                .env("ROCS_PROFILE")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("auth")
+                .help("Authentication mode, defaults to basic")
+                .long("auth")
+                .env("ROCS_AUTH")
+                .takes_value(true)
+                .possible_values(["basic", "bearer", "oauth2"])
+                .required(false),
+        )
+        .arg(
+            Arg::new("token")
+                .help("Static bearer token")
+                .long("token")
+                .env("ROCS_BROKER_TOKEN")
+                .hide_env_values(true)
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("token_url")
+                .help("OAuth2 token endpoint for the client-credentials grant")
+                .long("token-url")
+                .env("ROCS_TOKEN_URL")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("client_id")
+                .help("OAuth2 client ID")
+                .long("client-id")
+                .env("ROCS_CLIENT_ID")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("client_secret")
+                .help("OAuth2 client secret")
+                .long("client-secret")
+                .env("ROCS_CLIENT_SECRET")
+                .hide_env_values(true)
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("scope")
+                .help("OAuth2 scope to request")
+                .long("scope")
+                .env("ROCS_SCOPE")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("cacert")
+                .help("PEM bundle of certificate authorities to trust")
+                .long("cacert")
+                .env("ROCS_CACERT")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("cert")
+                .help("PEM client certificate for mutual TLS")
+                .long("cert")
+                .env("ROCS_CERT")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("key")
+                .help("PEM private key for the client certificate")
+                .long("key")
+                .env("ROCS_KEY")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("insecure_skip_verify")
+                .help("Disables TLS certificate verification, never use in production")
+                .long("insecure-skip-verify")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("timeout")
+                .help("Seconds allowed for each broker request")
+                .long("timeout")
+                .env("ROCS_TIMEOUT")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u64))
+                .required(false),
+        )
+        .arg(
+            Arg::new("connect_timeout")
+                .help("Seconds allowed to establish a connection")
+                .long("connect-timeout")
+                .env("ROCS_CONNECT_TIMEOUT")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u64))
+                .required(false),
+        )
+        .arg(
+            Arg::new("proxy")
+                .help("Proxy URL used for broker requests")
+                .long("proxy")
+                .env("ROCS_PROXY")
+                .takes_value(true)
+                .conflicts_with("no_proxy")
+                .required(false),
+        )
+        .arg(
+            Arg::new("no_proxy")
+                .help("Ignores proxies configured in the environment")
+                .long("no-proxy")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("catalog_ttl")
+                .help("Seconds the cached catalog is used before revalidating it with the broker [default: 300]")
+                .long("catalog-ttl")
+                .env("ROCS_CATALOG_TTL")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u64))
+                .required(false),
+        )
+        .arg(
+            Arg::new("refresh_catalog")
+                .help("Downloads the catalog even if the cached one is fresh")
+                .long("refresh-catalog")
+                .takes_value(false)
+                .conflicts_with("offline")
+                .required(false),
+        )
+        .arg(
+            Arg::new("offline")
+                .help("Uses the cached catalog only, without contacting the broker for it")
+                .long("offline")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("verbose")
+                .help("Logs broker requests and responses, repeat for headers (-vv) and bodies (-vvv)")
+                .short('v')
+                .multiple_occurrences(true)
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("trace_file")
+                .help("Writes broker requests and responses to a HAR file")
+                .long("trace-file")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("record")
+                .help("Records broker requests and responses, secrets redacted, to a JSONL cassette")
+                .long("record")
+                .takes_value(true)
+                .conflicts_with("replay")
+                .required(false),
+        )
+        .arg(
+            Arg::new("replay")
+                .help("Answers broker requests from a JSONL cassette instead of the network")
+                .long("replay")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new("output")
+                .help("Output format: table, wide, json, yaml, jsonpath=<expr> or template=<file>")
+                .short('o')
+                .long("output")
+                .env("ROCS_OUTPUT")
+                .takes_value(true)
+                .default_value("table")
+                .value_parser(clap::value_parser!(OutputFormat))
+                .required(false),
+        )
+        .arg(
+            Arg::new("json")
+                .help("Prints result in JSON format, same as -o json")
+                .long("json")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("sync")
+                .help("Execute provisioning and binding synchronously")
+                .long("sync")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("curl")
+                .help("Prints cURL command")
+                .long("curl")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::new("request_id")
+                .help("Request identity sent to the broker, generated when absent")
+                .long("request-id")
+                .env("ROCS_REQUEST_ID")
+                .takes_value(true)
+                .required(false),
+        )
+        .subcommand(
+            Command::new("provision")
+                .about("Service Instance provisioning")
+                .arg(
+                    Arg::new("service")
+                        .short('s')
+                        .long("service")
+                        .takes_value(true)
+                        .required(true)
+                        .help("service offering to use for provision"),
+                )
+                .arg(
+                    Arg::new("plan")
+                        .short('p')
+                        .long("plan")
+                        .takes_value(true)
+                        .required(true)
+                        .help("service plan to use for provision"),
+                )
+                .arg(
+                    Arg::new("parameters")
+                        .short('P')
+                        .long("params")
+                        .multiple_values(true)
+                        .takes_value(true)
+                        .help("parameters to provision service instances. ex: region=us-east-1 other=value"),
+                )
+                .arg(
+                    Arg::new("context")
+                        .short('C')
+                        .long("context")
+                        .multiple_values(true)
+                        .takes_value(true)
+                        .help("context to provision service instances. ex: account_id=123 other=value"),
+                )
+                .arg(
+                    Arg::new("wait")
+                        .short('w')
+                        .long("wait")
+                        .takes_value(false)
+                        .help("wait service instance provisioning to finish"),
+                ),
+        )
+        .subcommand(
+            Command::new("deprovision")
+                .about("Service Instance deprovisioning")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .long("instance")
+                        .takes_value(true)
+                        .required_unless_present("all")
+                        .conflicts_with("all")
+                        .help("service instance id to deprovision"),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .takes_value(false)
+                        .help("deprovision every instance of the broker in the local store matching the filters"),
+                )
+                .arg(
+                    Arg::new("service")
+                        .long("service")
+                        .takes_value(true)
+                        .requires("all")
+                        .help("only deprovision instances of this service name or ID"),
+                )
+                .arg(
+                    Arg::new("plan")
+                        .long("plan")
+                        .takes_value(true)
+                        .requires("all")
+                        .help("only deprovision instances of this plan name or ID"),
+                )
+                .arg(
+                    Arg::new("older_than")
+                        .long("older-than")
+                        .takes_value(true)
+                        .requires("all")
+                        .value_parser(parse_age)
+                        .help("only deprovision instances created longer ago than this, such as 7d, 12h or 30m"),
+                )
+                .arg(
+                    Arg::new("cascade")
+                        .long("cascade")
+                        .takes_value(false)
+                        .requires("all")
+                        .help("unbind the bindings of each instance first"),
+                )
+                .arg(
+                    Arg::new("concurrency")
+                        .short('c')
+                        .long("concurrency")
+                        .takes_value(true)
+                        .requires("all")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("number of instances deprovisioned at the same time [default: 4]"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .takes_value(false)
+                        .requires("all")
+                        .help("do not ask for confirmation"),
+                ),
+        )
+        .subcommand(
+            Command::new("bind")
+                .about("Service Binding request")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .long("instance")
+                        .takes_value(true)
+                        .help("instance ID or name to bind")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("binding")
+                        .short('b')
+                        .long("binding")
+                        .takes_value(true)
+                        .help("binding ID to fetch if bindings are fetchable")
+                )
+                .arg(
+                    Arg::new("parameters")
+                        .short('P')
+                        .long("params")
+                        .multiple_values(true)
+                        .takes_value(true)
+                        .help("parameters to provision service bindings. ex: param1=value1 param2=value2"),
+                )
+                .arg(
+                    Arg::new("context")
+                        .short('C')
+                        .long("context")
+                        .multiple_values(true)
+                        .takes_value(true)
+                        .help("context to provision service bindings. ex: account_id=123 other=value"),
+                )
+                .arg(
+                    Arg::new("wait")
+                        .short('w')
+                        .long("wait")
+                        .takes_value(false)
+                        .help("wait service binding provisioning to finish")
+                )
+        )
+        .subcommand(
+            Command::new("unbind")
+                .about("Service Binding removal")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .long("instance")
+                        .takes_value(true)
+                        .help("instance ID or name to bind")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("binding")
+                        .short('b')
+                        .long("binding")
+                        .takes_value(true)
+                        .help("Binding ID to unbind")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("catalog")
+                .about("Catalog request")
+                .alias("cat")
+                .args_conflicts_with_subcommands(true)
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .help("only services with this tag, repeat to require several"),
+                )
+                .arg(
+                    Arg::new("requires")
+                        .long("requires")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_parser(["syslog_drain", "route_forwarding", "volume_mount"])
+                        .help("only services requiring this permission"),
+                )
+                .arg(
+                    Arg::new("bindable")
+                        .long("bindable")
+                        .takes_value(false)
+                        .help("only bindable plans"),
+                )
+                .arg(
+                    Arg::new("free")
+                        .long("free")
+                        .takes_value(false)
+                        .help("only free plans"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .takes_value(true)
+                        .help("only services or plans whose name matches this glob, ex: 'postgres*'"),
+                )
+                .arg(
+                    Arg::new("search")
+                        .long("search")
+                        .takes_value(true)
+                        .help("only services or plans mentioning this text in names, descriptions or metadata"),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Show every detail of a service or one of its plans")
+                        .arg(
+                            Arg::new("service")
+                                .takes_value(true)
+                                .required(true)
+                                .help("service name or ID"),
+                        )
+                        .arg(
+                            Arg::new("plan")
+                                .takes_value(true)
+                                .help("plan name or ID"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("params")
+                        .about("Document the parameters a plan accepts from its schemas")
+                        .arg(
+                            Arg::new("service")
+                                .takes_value(true)
+                                .required(true)
+                                .help("service name or ID"),
+                        )
+                        .arg(
+                            Arg::new("plan")
+                                .takes_value(true)
+                                .required(true)
+                                .help("plan name or ID"),
+                        )
+                        .arg(
+                            Arg::new("for")
+                                .long("for")
+                                .takes_value(true)
+                                .value_parser(["create", "update", "bind"])
+                                .default_value("create")
+                                .help("operation whose parameters are documented"),
+                        )
+                        .arg(
+                            Arg::new("markdown")
+                                .long("markdown")
+                                .takes_value(false)
+                                .help("print a Markdown reference instead of the output format"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("diff")
+                        .about("Compare two catalogs and report breaking changes")
+                        .arg(
+                            Arg::new("from")
+                                .long("from")
+                                .takes_value(true)
+                                .help("catalog file (JSON or YAML) or profile to compare from [default: cached catalog]"),
+                        )
+                        .arg(
+                            Arg::new("to")
+                                .long("to")
+                                .takes_value(true)
+                                .help("catalog file (JSON or YAML) or profile to compare to [default: broker's catalog]"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("lint")
+                        .about("Check a catalog against the OSB rules for broker authors")
+                        .arg(
+                            Arg::new("file")
+                                .long("file")
+                                .short('f')
+                                .takes_value(true)
+                                .help("catalog file (JSON or YAML) [default: broker's catalog]"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("conformance")
+                .about("Certify a broker by driving a whole lifecycle and checking its answers")
+                .arg(
+                    Arg::new("service")
+                        .short('s')
+                        .long("service")
+                        .takes_value(true)
+                        .help("service name or ID to test [default: the first bindable one]"),
+                )
+                .arg(
+                    Arg::new("plan")
+                        .short('p')
+                        .long("plan")
+                        .takes_value(true)
+                        .help("plan name or ID to test [default: the first bindable one]"),
+                )
+                .arg(
+                    Arg::new("parameters")
+                        .short('P')
+                        .long("params")
+                        .multiple_values(true)
+                        .takes_value(true)
+                        .help("parameters sent when provisioning, updating and binding. ex: region=us-east-1"),
+                )
+                .arg(
+                    Arg::new("skip")
+                        .long("skip")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_parser(CONFORMANCE_CHECKS)
+                        .help("check to leave out, can be repeated"),
+                )
+                .arg(
+                    Arg::new("operation_timeout")
+                        .long("operation-timeout")
+                        .takes_value(true)
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("300")
+                        .help("seconds to wait for asynchronous operations to finish"),
+                )
+                .arg(
+                    Arg::new("junit")
+                        .long("junit")
+                        .takes_value(true)
+                        .help("also write the report as JUnit XML to this file"),
+                ),
+        )
+        .subcommand(
+            Command::new("mock-broker")
+                .about("Serve a catalog as an in-memory broker for local development and tests")
+                .arg(
+                    Arg::new("catalog")
+                        .long("catalog")
+                        .takes_value(true)
+                        .required(true)
+                        .help("catalog file (JSON or YAML) to serve"),
+                )
+                .arg(
+                    Arg::new("listen")
+                        .long("listen")
+                        .takes_value(true)
+                        .default_value("127.0.0.1:8080")
+                        .help("address to listen on"),
+                )
+                .arg(
+                    Arg::new("delay")
+                        .long("delay")
+                        .takes_value(true)
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("0")
+                        .help("seconds asynchronous operations stay in progress"),
+                )
+                .arg(
+                    Arg::new("failure_rate")
+                        .long("failure-rate")
+                        .takes_value(true)
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("0")
+                        .help("probability between 0 and 1 that an operation fails"),
+                ),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Fetch Service Instances information")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .long("instance")
+                        .takes_value(true)
+                        .help("instance ID to fetch information")
+                        .required(true),
+                )
+        )
+        .subcommand(
+            Command::new("describe")
+                .about("Describe a Service Instance with catalog names, last operation and bindings")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .long("instance")
+                        .takes_value(true)
+                        .help("instance ID to describe")
+                        .required(true),
+                )
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Last operation state of a Service Instance or Service Binding")
+                .alias("last-operation")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .long("instance")
+                        .takes_value(true)
+                        .help("instance ID to check")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("binding")
+                        .short('b')
+                        .long("binding")
+                        .takes_value(true)
+                        .help("binding ID to check instead of the instance"),
+                )
+                .arg(
+                    Arg::new("operation")
+                        .long("operation")
+                        .takes_value(true)
+                        .help("operation token returned by the broker"),
+                )
+                .arg(
+                    Arg::new("watch")
+                        .short('w')
+                        .long("watch")
+                        .takes_value(false)
+                        .help("poll until the operation finishes, printing every change"),
+                ),
+        )
+        .subcommand(
+            Command::new("instances")
+                .about("List service instances provisioned with rocs")
+                .arg(
+                    Arg::new("service")
+                        .long("service")
+                        .takes_value(true)
+                        .help("only show instances of this service name or ID"),
+                )
+                .arg(
+                    Arg::new("plan")
+                        .long("plan")
+                        .takes_value(true)
+                        .help("only show instances of this plan name or ID"),
+                )
+                .arg(
+                    Arg::new("state")
+                        .long("state")
+                        .takes_value(true)
+                        .value_parser(["in progress", "succeeded", "failed"])
+                        .help("only show instances whose last operation is in this state"),
+                )
+                .arg(
+                    Arg::new("sort_by")
+                        .long("sort-by")
+                        .takes_value(true)
+                        .value_parser(["id", "service", "plan", "state", "created"])
+                        .default_value("created")
+                        .help("field used to sort the list"),
+                ),
+        )
+        .subcommand(
+            Command::new("bindings")
+                .about("List service bindings created with rocs")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .long("instance")
+                        .takes_value(true)
+                        .help("only show bindings of this instance ID"),
+                )
+                .arg(
+                    Arg::new("service")
+                        .long("service")
+                        .takes_value(true)
+                        .help("only show bindings of this service name or ID"),
+                )
+                .arg(
+                    Arg::new("plan")
+                        .long("plan")
+                        .takes_value(true)
+                        .help("only show bindings of this plan name or ID"),
+                )
+                .arg(
+                    Arg::new("state")
+                        .long("state")
+                        .takes_value(true)
+                        .value_parser(["in progress", "succeeded", "failed"])
+                        .help("only show bindings whose last operation is in this state"),
+                )
+                .arg(
+                    Arg::new("sort_by")
+                        .long("sort-by")
+                        .takes_value(true)
+                        .value_parser(["id", "instance", "state", "created"])
+                        .default_value("created")
+                        .help("field used to sort the list"),
+                ),
+        )
+        .subcommand(
+            Command::new("plan")
+                .about("Show the changes apply would make to converge to a manifest")
+                .arg(
+                    Arg::new("file")
+                        .short('f')
+                        .long("file")
+                        .takes_value(true)
+                        .required(true)
+                        .help("YAML manifest listing the instances and bindings to converge to"),
+                )
+                .arg(
+                    Arg::new("prune")
+                        .long("prune")
+                        .takes_value(false)
+                        .help("include the removal of instances and bindings missing from the manifest"),
+                ),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Provision, update, bind, unbind and deprovision to converge to a manifest")
+                .arg(
+                    Arg::new("file")
+                        .short('f')
+                        .long("file")
+                        .takes_value(true)
+                        .required(true)
+                        .help("YAML manifest listing the instances and bindings to converge to"),
+                )
+                .arg(
+                    Arg::new("prune")
+                        .long("prune")
+                        .takes_value(false)
+                        .help("unbind and deprovision instances and bindings missing from the manifest"),
+                ),
+        )
+        .subcommand(
+            Command::new("batch")
+                .about("Run the provision, update, bind, unbind and deprovision operations of a JSONL file")
+                .arg(
+                    Arg::new("file")
+                        .takes_value(true)
+                        .required(true)
+                        .help("JSONL file with one operation per line"),
+                )
+                .arg(
+                    Arg::new("concurrency")
+                        .short('c')
+                        .long("concurrency")
+                        .takes_value(true)
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("4")
+                        .help("number of operations running at the same time"),
+                )
+                .arg(
+                    Arg::new("results")
+                        .short('r')
+                        .long("results")
+                        .takes_value(true)
+                        .help("JSONL file receiving one result per line, defaults to stdout"),
+                )
+                .arg(
+                    Arg::new("continue_on_error")
+                        .long("continue-on-error")
+                        .takes_value(false)
+                        .help("keep starting operations after one failed instead of skipping the rest"),
+                ),
+        )
+        .subcommand(
+            Command::new("extension")
+                .about("Extension interaction")
+                .alias("ext")
+                .arg(
+                    Arg::new("instance")
+                        .short('i')
+                        .help("Instance ID to access extension")
+                        .takes_value(true)
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("id")
+                        .short('I')
+                        .help("Extension ID")
+                        .takes_value(true)
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("operation")
+                        .short('o')
+                        .help("Operation to perform")
+                        .takes_value(true)
+                        .required_unless_present("list")
+                )
+                .arg(
+                    Arg::new("parameters")
+                        .short('P')
+                        .help("All parameters required for this action including path parameters")
+                        .takes_value(true)
+                        .multiple_values(true)
+                )
+                .arg(
+                    Arg::new("body")
+                        .short('B')
+                        .help("Request body with required fields")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("list")
+                        .short('l')
+                        .help("List operations and parameters available for an extension")
+                        .takes_value(false)
+                )
+        )
+}
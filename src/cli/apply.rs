@@ -0,0 +1,259 @@
+use crate::cli::{
+    create_binding, fetch_catalog, find_plan, find_service, forget_instance, is_gone, output,
+    provision_instance, remove_binding, remove_instance, store_warning, update_instance,
+    ExitStatus, NewBinding, NewInstance, Options,
+};
+use crate::client::Client;
+use crate::manifest::{Change, Manifest};
+use crate::models::{ChangeSetOutput, ResourceChange};
+use crate::store;
+
+use clap::ArgMatches;
+use rocl::models::Service;
+use serde_json::{json, Value};
+use std::error::Error;
+use uuid::Uuid;
+
+pub async fn plan(
+    matches: &ArgMatches,
+    client: Client,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    let (changes, _services) = changes(matches, &client, &options).await?;
+
+    let changes = changes.iter().map(describe).collect();
+    output::print(&ChangeSetOutput::new(false, changes), &options.output)?;
+
+    Ok(())
+}
+
+pub async fn apply(
+    matches: &ArgMatches,
+    client: Client,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    let (changes, services) = changes(matches, &client, &options).await?;
+
+    let mut results = Vec::new();
+    let mut failed = false;
+
+    // changes depend on each other, the first failure skips the rest
+    for change in &changes {
+        let mut result = describe(change);
+        if failed {
+            result.state = Some("skipped".into());
+            results.push(result);
+            continue;
+        }
+
+        eprintln!("[INFO] {} {}", result.action, result.name);
+        match converge(change, &client, &options, &services, &mut result).await {
+            Ok(()) => result.state = Some("succeeded".into()),
+            Err(e) => {
+                result.state = Some("failed".into());
+                result.error = Some(e.to_string());
+                failed = true;
+            }
+        }
+        results.push(result);
+    }
+
+    output::print(&ChangeSetOutput::new(true, results), &options.output)?;
+
+    match failed {
+        true => Err(Box::new(ExitStatus {
+            code: 1,
+            reason: "manifest was not fully applied".into(),
+        })),
+        false => Ok(()),
+    }
+}
+
+/// Changes needed by the manifest, with the catalog they were checked against.
+async fn changes(
+    matches: &ArgMatches,
+    client: &Client,
+    options: &Options,
+) -> Result<(Vec<Change>, Vec<Service>), Box<dyn Error>> {
+    if options.curl_output {
+        return Err("apply sends several dependent requests and cannot be printed as curl".into());
+    }
+
+    let manifest = Manifest::load(matches.value_of("file").unwrap())?;
+    // only this broker's instances are compared and pruned
+    let instances: Vec<_> = store::instance_list()?
+        .into_iter()
+        .filter(|i| i.broker_url == options.broker_url)
+        .collect();
+    let bindings: Vec<_> = store::binding_list(None)?
+        .into_iter()
+        .filter(|b| instances.iter().any(|i| i.instance_id == b.instance_id))
+        .collect();
+    let changes = manifest.changes(&instances, &bindings, matches.is_present("prune"))?;
+
+    // unknown services and plans are reported before anything is changed
+    let services = fetch_catalog(client, options).await?;
+    for instance in &manifest.instances {
+        let service = find_service(services.clone(), &instance.service)?;
+        find_plan(&service, &instance.plan)?;
+    }
+
+    Ok((changes, services))
+}
+
+fn describe(change: &Change) -> ResourceChange {
+    let mut resource = ResourceChange {
+        action: String::new(),
+        name: String::new(),
+        instance: None,
+        service: None,
+        plan: None,
+        fields: Vec::new(),
+        service_instance_id: None,
+        service_binding_id: None,
+        state: None,
+        error: None,
+    };
+
+    match change {
+        Change::Provision(instance) => {
+            resource.action = "provision".into();
+            resource.name = instance.name.clone();
+            resource.service = Some(instance.service.clone());
+            resource.plan = Some(instance.plan.clone());
+        }
+        Change::Update {
+            instance_id,
+            instance,
+            fields,
+        } => {
+            resource.action = "update".into();
+            resource.name = instance.name.clone();
+            resource.service = Some(instance.service.clone());
+            resource.plan = Some(instance.plan.clone());
+            resource.fields = fields.clone();
+            resource.service_instance_id = Some(instance_id.clone());
+        }
+        Change::Unbind {
+            binding_id,
+            instance_id,
+            name,
+        } => {
+            resource.action = "unbind".into();
+            resource.name = name.clone();
+            resource.service_instance_id = Some(instance_id.clone());
+            resource.service_binding_id = Some(binding_id.clone());
+        }
+        Change::Bind(binding) => {
+            resource.action = "bind".into();
+            resource.name = binding.name.clone();
+            resource.instance = Some(binding.instance.clone());
+        }
+        Change::Deprovision { instance_id, name } => {
+            resource.action = "deprovision".into();
+            resource.name = name.clone();
+            resource.service_instance_id = Some(instance_id.clone());
+        }
+    }
+
+    resource
+}
+
+async fn converge(
+    change: &Change,
+    client: &Client,
+    options: &Options,
+    services: &[Service],
+    result: &mut ResourceChange,
+) -> Result<(), Box<dyn Error>> {
+    let accepts_incomplete = !options.synchronous;
+
+    match change {
+        Change::Provision(instance) => {
+            let service = find_service(services.to_vec(), &instance.service)?;
+            let plan = find_plan(&service, &instance.plan)?;
+            let instance_id = Uuid::new_v4().as_hyphenated().to_string();
+            result.service_instance_id = Some(instance_id.clone());
+
+            let new_instance = NewInstance {
+                instance_id,
+                service,
+                plan,
+                parameters: parameters(&instance.parameters),
+                context: instance.context.as_ref(),
+                name: Some(instance.name.clone()),
+                broker_url: &options.broker_url,
+            };
+            provision_instance(client, new_instance, accepts_incomplete, true).await?;
+        }
+        Change::Update {
+            instance_id,
+            instance,
+            ..
+        } => {
+            let stored = store::instance_get(instance_id)?
+                .ok_or_else(|| format!("instance {} is not in the local store", instance_id))?;
+            let service = find_service(services.to_vec(), &stored.service_id)?;
+            let plan = find_plan(&service, &instance.plan)?;
+
+            update_instance(
+                client,
+                instance_id,
+                &service,
+                Some(plan),
+                Some(parameters(&instance.parameters)),
+                instance.context.as_ref(),
+                accepts_incomplete,
+            )
+            .await?;
+        }
+        Change::Bind(binding) => {
+            let instance = named_instance(&binding.instance, options)?;
+            let binding_id = Uuid::new_v4().as_hyphenated().to_string();
+            result.service_instance_id = Some(instance.instance_id.clone());
+            result.service_binding_id = Some(binding_id.clone());
+
+            let new_binding = NewBinding {
+                instance_id: &instance.instance_id,
+                binding_id: &binding_id,
+                parameters: parameters(&binding.parameters),
+                context: binding.context.as_ref(),
+                name: Some(binding.name.clone()),
+            };
+            create_binding(client, new_binding, accepts_incomplete, true).await?;
+        }
+        Change::Unbind {
+            binding_id,
+            instance_id,
+            ..
+        } => match remove_binding(client, instance_id, binding_id, accepts_incomplete).await {
+            // failed bindings may not exist on the broker
+            Err(e) if is_gone(&*e) => {
+                store_warning(store::binding_delete(binding_id.clone()));
+            }
+            result => result?,
+        },
+        Change::Deprovision { instance_id, .. } => {
+            match remove_instance(client, instance_id, accepts_incomplete).await {
+                // failed instances may not exist on the broker
+                Err(e) if is_gone(&*e) => forget_instance(instance_id),
+                result => result?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn named_instance(name: &str, options: &Options) -> Result<store::Instance, Box<dyn Error>> {
+    store::instance_list()?
+        .into_iter()
+        .filter(|i| i.broker_url == options.broker_url)
+        .find(|i| i.name.as_deref() == Some(name))
+        .ok_or_else(|| format!("instance {} is not in the local store", name).into())
+}
+
+// parameters are always sent so the stored ones compare with the manifest
+fn parameters(value: &Option<Value>) -> Value {
+    value.clone().unwrap_or_else(|| json!({}))
+}
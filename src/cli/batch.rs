@@ -0,0 +1,213 @@
+use crate::batch::{self, BatchOperation};
+use crate::cli::{
+    create_binding, fetch_catalog, find_plan, find_service, provision_instance, remove_binding,
+    remove_instance, update_instance, ExitStatus, NewBinding, NewInstance, Options,
+};
+use crate::client::{Client, ClientError};
+use crate::models::BatchResultOutput;
+use crate::store;
+
+use clap::ArgMatches;
+use futures_util::{stream, StreamExt};
+use rocl::models::Service;
+use serde_json::json;
+use std::cell::Cell;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+use uuid::Uuid;
+
+pub async fn batch(
+    matches: &ArgMatches,
+    client: Client,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    if options.curl_output {
+        return Err("batch sends many requests and cannot be printed as curl".into());
+    }
+
+    let operations = batch::load(matches.value_of("file").unwrap())?;
+    let concurrency = *matches.get_one::<usize>("concurrency").unwrap();
+    let continue_on_error = matches.is_present("continue_on_error");
+
+    let mut results: Box<dyn Write> = match matches.value_of("results") {
+        Some(path) => {
+            Box::new(File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?)
+        }
+        None => Box::new(io::stdout()),
+    };
+
+    // provisions and updates name services and plans, the catalog resolves them
+    let services = match operations.iter().any(|(_, operation)| {
+        matches!(
+            operation,
+            BatchOperation::Provision { .. } | BatchOperation::Update { .. }
+        )
+    }) {
+        true => fetch_catalog(&client, &options).await?,
+        false => Vec::new(),
+    };
+
+    // operations already started finish after a failure, the others are skipped;
+    // lines on the same instance run in file order, see batch::queues
+    let failed = Cell::new(false);
+    let mut records = stream::iter(batch::queues(operations))
+        .map(|queue| {
+            let (client, options, services, failed) = (&client, &options, &services, &failed);
+            stream::iter(queue)
+                .then(move |(line, operation)| async move {
+                    let mut result = BatchResultOutput::new(line, operation.name().into());
+                    result.service_instance_id = operation.instance_id().map(String::from);
+                    if failed.get() && !continue_on_error {
+                        result.state = "skipped".into();
+                        return result;
+                    }
+
+                    eprintln!("[INFO] line {}: {}", line, operation.name());
+                    match execute(&operation, client, options, services, &mut result).await {
+                        Ok(()) => result.state = "succeeded".into(),
+                        Err(e) => {
+                            result.state = "failed".into();
+                            result.status = e
+                                .downcast_ref::<ClientError>()
+                                .and_then(ClientError::status);
+                            result.error = Some(e.to_string());
+                            failed.set(true);
+                        }
+                    }
+                    result
+                })
+                .boxed_local()
+        })
+        .flatten_unordered(concurrency.max(1));
+
+    let (mut succeeded, mut errors, mut skipped) = (0, 0, 0);
+    while let Some(record) = records.next().await {
+        match record.state.as_str() {
+            "succeeded" => succeeded += 1,
+            "failed" => errors += 1,
+            _ => skipped += 1,
+        }
+        writeln!(results, "{}", serde_json::to_string(&record)?)?;
+        results.flush()?;
+    }
+    eprintln!(
+        "[INFO] {} succeeded, {} failed, {} skipped",
+        succeeded, errors, skipped
+    );
+
+    match errors {
+        0 => Ok(()),
+        _ => Err(Box::new(ExitStatus {
+            code: 1,
+            reason: "batch was not fully executed".into(),
+        })),
+    }
+}
+
+async fn execute(
+    operation: &BatchOperation,
+    client: &Client,
+    options: &Options,
+    services: &[Service],
+    result: &mut BatchResultOutput,
+) -> Result<(), Box<dyn Error>> {
+    let accepts_incomplete = !options.synchronous;
+
+    match operation {
+        BatchOperation::Provision {
+            service,
+            plan,
+            instance_id,
+            name,
+            parameters,
+            context,
+        } => {
+            let service = find_service(services.to_vec(), service)?;
+            let plan = find_plan(&service, plan)?;
+            let instance_id = instance_id
+                .clone()
+                .unwrap_or_else(|| Uuid::new_v4().as_hyphenated().to_string());
+            result.service_instance_id = Some(instance_id.clone());
+
+            let instance = NewInstance {
+                instance_id,
+                service,
+                plan,
+                parameters: parameters.clone().unwrap_or_else(|| json!({})),
+                context: context.as_ref(),
+                name: name.clone(),
+                broker_url: &options.broker_url,
+            };
+            provision_instance(client, instance, accepts_incomplete, true).await?;
+        }
+        BatchOperation::Update {
+            instance_id,
+            service,
+            plan,
+            parameters,
+            context,
+        } => {
+            let stored = store::instance_get(instance_id)?;
+            let service = match (service, &stored) {
+                (Some(service), _) => find_service(services.to_vec(), service)?,
+                (None, Some(stored)) => find_service(services.to_vec(), &stored.service_id)?,
+                (None, None) => {
+                    return Err(format!(
+                        "instance {} is not in the local store, its service is required",
+                        instance_id
+                    )
+                    .into())
+                }
+            };
+            let plan = plan
+                .as_deref()
+                .map(|plan| find_plan(&service, plan))
+                .transpose()?;
+
+            update_instance(
+                client,
+                instance_id,
+                &service,
+                plan,
+                parameters.clone(),
+                context.as_ref(),
+                accepts_incomplete,
+            )
+            .await?;
+        }
+        BatchOperation::Bind {
+            instance_id,
+            binding_id,
+            name,
+            parameters,
+            context,
+        } => {
+            let binding_id = binding_id
+                .clone()
+                .unwrap_or_else(|| Uuid::new_v4().as_hyphenated().to_string());
+            result.service_binding_id = Some(binding_id.clone());
+
+            let binding = NewBinding {
+                instance_id,
+                binding_id: &binding_id,
+                parameters: parameters.clone().unwrap_or_else(|| json!({})),
+                context: context.as_ref(),
+                name: name.clone(),
+            };
+            create_binding(client, binding, accepts_incomplete, true).await?;
+        }
+        BatchOperation::Unbind {
+            instance_id,
+            binding_id,
+        } => {
+            result.service_binding_id = Some(binding_id.clone());
+            remove_binding(client, instance_id, binding_id, accepts_incomplete).await?;
+        }
+        BatchOperation::Deprovision { instance_id } => {
+            remove_instance(client, instance_id, accepts_incomplete).await?
+        }
+    }
+
+    Ok(())
+}
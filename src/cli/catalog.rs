@@ -1,18 +1,69 @@
-use crate::cli::{generate_curl_command, Options};
+use crate::auth::AuthMode;
+use crate::broker::CatalogDocument;
+use crate::cli::{diff, generate_curl_command, lint, output, store_warning, Options};
+use crate::client::Client;
+use crate::models::{CatalogOutput, Parameter, ParametersOutput, PlanOutput, ServiceOutput};
+use crate::profile::Profile;
+use crate::store;
+use chrono::Utc;
 use clap::ArgMatches;
-use prettytable::{format, Table};
-use rocl::apis::{catalog_api::catalog_get, configuration::Configuration};
+use glob::Pattern;
+use ring::digest;
+use rocl::models::{Catalog, Plan, Service};
+use serde_json::Value;
 use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use valico::json_schema::Scope;
+
+/// How the catalog cached in the local store is used.
+#[derive(Debug, Clone)]
+pub struct CatalogSettings {
+    /// Age after which the cached catalog is revalidated with the broker.
+    pub ttl: Duration,
+    /// Downloads the catalog even when the cached one is fresh.
+    pub refresh: bool,
+    /// Only uses the cached catalog, whatever its age.
+    pub offline: bool,
+    /// Entry of the local cache, see [`catalog_cache_key`].
+    pub cache_key: String,
+}
+
+/// Local cache entry of a broker's catalog. Brokers may serve clients
+/// different catalogs, so the identity used to authenticate is part of it.
+pub fn catalog_cache_key(profile: &Profile) -> String {
+    let identity = match profile.auth_mode() {
+        AuthMode::Basic => format!("basic:{}", profile.username.as_deref().unwrap_or_default()),
+        AuthMode::Bearer => {
+            // tokens are secrets, only their SHA-256 digest is stored
+            let token = profile.token.as_deref().unwrap_or_default();
+            let digest = digest::digest(&digest::SHA256, token.as_bytes());
+            let hex: String = digest
+                .as_ref()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            format!("bearer:{}", hex)
+        }
+        AuthMode::OAuth2 => format!(
+            "oauth2:{}:{}",
+            profile.client_id.as_deref().unwrap_or_default(),
+            profile.scope.as_deref().unwrap_or_default()
+        ),
+    };
+    format!(
+        "{} {}",
+        profile.broker_url.as_deref().unwrap_or_default(),
+        identity
+    )
+}
 
 pub async fn catalog(
-    _: &ArgMatches,
-    config: Configuration,
+    matches: &ArgMatches,
+    client: Client,
     options: Options,
 ) -> Result<(), Box<dyn Error>> {
-    let catalog = catalog_get(&config, crate::cli::DEFAULT_API_VERSION)
-        .await
-        .expect("failed to get catalog");
-
     if options.curl_output {
         println!(
             "{}",
@@ -22,50 +73,450 @@ pub async fn catalog(
                 "".to_owned(),
                 false,
                 "".to_owned(),
-                "".to_owned()
+                "".to_owned(),
+                &options,
             )
         );
         return Ok(());
     }
 
-    match options.json_output {
-        false => {
-            let mut services_table = Table::new();
+    match matches.subcommand() {
+        Some(("diff", diff_matches)) => return diff(diff_matches, Some(&client), options).await,
+        Some(("lint", lint_matches)) => return lint(lint_matches, Some(&client), options).await,
+        _ => {}
+    }
 
-            services_table.add_row(row!["Service", "Description", "Plans", "Extensions"]);
+    let services = fetch_catalog(&client, &options).await?;
 
-            for s in catalog.services.unwrap().iter() {
-                let mut plans_table = Table::new();
-                // let mut extensions_table = Table::new();
+    match matches.subcommand() {
+        Some(("show", show_matches)) => show(show_matches, services, options),
+        Some(("params", params_matches)) => params(params_matches, services, options),
+        _ => {
+            let filter = CatalogFilter::from_matches(matches)?;
+            let catalog_output = CatalogOutput::new(filter.apply(services));
+            output::print(&catalog_output, &options.output)?;
+            Ok(())
+        }
+    }
+}
 
-                plans_table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+/// `catalog` subcommands not using the configured broker, see [`is_local`](crate::cli::is_local).
+pub async fn catalog_local(matches: &ArgMatches, options: Options) -> Result<(), Box<dyn Error>> {
+    match matches.subcommand() {
+        Some(("diff", diff_matches)) => diff(diff_matches, None, options).await,
+        Some(("lint", lint_matches)) => lint(lint_matches, None, options).await,
+        _ => Err(Box::from("unknown command")),
+    }
+}
 
-                for p in s.plans.iter() {
-                    plans_table.add_row(row![p.name]);
-                }
+/// Services of the broker's catalog, served from the local store while it is
+/// fresh and revalidated with `If-None-Match` once it expires.
+pub async fn fetch_catalog(
+    client: &Client,
+    options: &Options,
+) -> Result<Vec<Service>, Box<dyn Error>> {
+    parse_catalog(&fetch_catalog_content(client, options, options.catalog.refresh).await?)
+}
+
+/// JSON document of the broker's catalog as served, with the same caching as
+/// [`fetch_catalog`]. `refresh` downloads it even when the cached one is fresh.
+pub async fn fetch_catalog_content(
+    client: &Client,
+    options: &Options,
+    refresh: bool,
+) -> Result<String, Box<dyn Error>> {
+    let settings = &options.catalog;
+    let cached = store::catalog_cache_get(&settings.cache_key).unwrap_or_else(|e| {
+        eprintln!("[WARN] failed to read local store: {}", e);
+        None
+    });
+
+    if settings.offline {
+        return match cached {
+            Some(cached) => Ok(cached.data),
+            None => Err(format!(
+                "no cached catalog for {}, run once without --offline",
+                options.broker_url
+            )
+            .into()),
+        };
+    }
+
+    if let Some(cached) = &cached {
+        let age = Utc::now().naive_utc() - cached.fetched_at;
+        if !refresh && age.to_std().map_or(true, |age| age < settings.ttl) {
+            return Ok(cached.data.clone());
+        }
+    }
+
+    let etag = match refresh {
+        true => None,
+        false => cached.as_ref().and_then(|c| c.etag.as_deref()),
+    };
+    let (content, etag) = match client.broker().catalog(etag).await? {
+        CatalogDocument::Modified { content, etag } => (content, etag),
+        CatalogDocument::NotModified => {
+            store_warning(store::catalog_cache_touch(&settings.cache_key));
+            return Ok(cached.map(|c| c.data).unwrap_or_default());
+        }
+    };
 
-                /*
-                if let Some(extensions) = &s.extensions {
-                    extensions_table.add_row(row!["ID", "Path"]);
+    // catalogs breaking the OSB models are still cached, `catalog lint` reports on them
+    serde_json::from_str::<Value>(&content).map_err(|e| format!("invalid catalog: {}", e))?;
+    store_warning(store::catalog_cache_save(
+        &settings.cache_key,
+        etag,
+        content.clone(),
+    ));
 
-                    for p in extensions.iter() {
-                        extensions_table.add_row(row![p.id, p.path]);
-                    }
+    Ok(content)
+}
+
+fn parse_catalog(data: &str) -> Result<Vec<Service>, Box<dyn Error>> {
+    let catalog: Catalog =
+        serde_json::from_str(data).map_err(|e| format!("invalid catalog: {}", e))?;
+    Ok(catalog.services.unwrap_or_default())
+}
+
+/// Narrows the catalog listing. Service-level criteria drop whole services,
+/// plan-level ones drop plans, and services left without plans are hidden.
+pub struct CatalogFilter {
+    tags: Vec<String>,
+    requires: Vec<String>,
+    bindable: bool,
+    free: bool,
+    name: Option<Pattern>,
+    search: Option<String>,
+}
+
+impl CatalogFilter {
+    /// Filter given with the options of the `catalog` command.
+    pub fn from_matches(matches: &ArgMatches) -> Result<CatalogFilter, Box<dyn Error>> {
+        let values = |name| {
+            matches
+                .values_of(name)
+                .map(|v| v.map(String::from).collect())
+                .unwrap_or_default()
+        };
+
+        Ok(CatalogFilter {
+            tags: values("tag"),
+            requires: values("requires"),
+            bindable: matches.is_present("bindable"),
+            free: matches.is_present("free"),
+            name: match matches.value_of("name") {
+                Some(name) => Some(
+                    Pattern::new(name).map_err(|e| format!("invalid pattern {}: {}", name, e))?,
+                ),
+                None => None,
+            },
+            search: matches.value_of("search").map(|s| s.to_lowercase()),
+        })
+    }
+
+    pub fn apply(&self, services: Vec<Service>) -> Vec<Service> {
+        services
+            .into_iter()
+            .filter(|s| self.service_matches(s))
+            .filter_map(|mut s| {
+                let plans = std::mem::take(&mut s.plans);
+                s.plans = plans
+                    .into_iter()
+                    .filter(|p| self.plan_matches(&s, p))
+                    .collect();
+
+                match s.plans.is_empty() {
+                    true => None,
+                    false => Some(s),
                 }
-                 */
+            })
+            .collect()
+    }
+
+    // a service matching by name or text keeps all of its plans
+    fn plan_matches(&self, service: &Service, plan: &Plan) -> bool {
+        let name = match &self.name {
+            Some(pattern) => pattern.matches(&service.name) || pattern.matches(&plan.name),
+            None => true,
+        };
+        let text = match &self.search {
+            Some(text) => service_contains(service, text) || plan_contains(plan, text),
+            None => true,
+        };
+
+        name && text
+            && (!self.bindable || plan.bindable.unwrap_or(service.bindable))
+            && (!self.free || plan.free.unwrap_or(true))
+    }
+
+    fn service_matches(&self, service: &Service) -> bool {
+        let tags = service.tags.clone().unwrap_or_default();
+        let requires: Vec<String> = service
+            .requires
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|r| serde_json::to_value(r).ok())
+            .filter_map(|r| r.as_str().map(String::from))
+            .collect();
+
+        self.tags.iter().all(|t| tags.contains(t))
+            && self.requires.iter().all(|r| requires.contains(r))
+    }
+}
+
+fn service_contains(service: &Service, text: &str) -> bool {
+    [
+        service.name.clone(),
+        service.description.clone(),
+        service.tags.clone().unwrap_or_default().join(" "),
+        metadata_text(&service.metadata),
+    ]
+    .iter()
+    .any(|field| field.to_lowercase().contains(text))
+}
+
+fn plan_contains(plan: &Plan, text: &str) -> bool {
+    [
+        plan.name.clone(),
+        plan.description.clone(),
+        metadata_text(&plan.metadata),
+    ]
+    .iter()
+    .any(|field| field.to_lowercase().contains(text))
+}
+
+// string values anywhere in the metadata, so keys do not produce matches
+fn metadata_text(metadata: &Option<Value>) -> String {
+    fn collect(value: &Value, text: &mut Vec<String>) {
+        match value {
+            Value::String(s) => text.push(s.clone()),
+            Value::Array(items) => items.iter().for_each(|i| collect(i, text)),
+            Value::Object(map) => map.values().for_each(|v| collect(v, text)),
+            _ => {}
+        }
+    }
+
+    let mut text = Vec::new();
+    if let Some(metadata) = metadata {
+        collect(metadata, &mut text);
+    }
+    text.join(" ")
+}
 
-                services_table.add_row(row![s.name, s.description, plans_table]);
-            }
+fn show(
+    matches: &ArgMatches,
+    services: Vec<Service>,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    let service = find_service(services, matches.value_of("service").unwrap())?;
 
-            services_table.printstd();
+    match matches.value_of("plan") {
+        Some(plan) => {
+            let plan = find_plan(&service, plan)?;
+            let bindable = plan.bindable.unwrap_or(service.bindable);
+            let plan_output = PlanOutput::new(service.id, service.name, bindable, plan);
+            output::print(&plan_output, &options.output)?;
         }
-        true => {
-            println!(
-                "{}",
-                serde_json::to_string(&catalog.services.unwrap()).unwrap()
+        None => output::print(&ServiceOutput::new(service), &options.output)?,
+    }
+
+    Ok(())
+}
+
+fn params(
+    matches: &ArgMatches,
+    services: Vec<Service>,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    let service = find_service(services, matches.value_of("service").unwrap())?;
+    let plan = find_plan(&service, matches.value_of("plan").unwrap())?;
+    let action: SchemaAction = matches.value_of("for").unwrap_or("create").parse()?;
+
+    let parameters = match parameters_schema(&plan, action) {
+        Some(schema) => schema_parameters(schema),
+        None => {
+            eprintln!(
+                "[INFO] plan {} publishes no parameters schema for {}",
+                plan.name, action
             );
+            Vec::new()
         }
     };
 
+    let params_output =
+        ParametersOutput::new(service.name, plan.name, action.to_string(), parameters);
+
+    match matches.is_present("markdown") {
+        true => print!("{}", output::markdown(&params_output)),
+        false => output::print(&params_output, &options.output)?,
+    }
+
     Ok(())
 }
+
+/// Parameters described by an object schema, nested properties being flattened to dotted names.
+pub fn schema_parameters(schema: &Value) -> Vec<Parameter> {
+    let mut parameters = Vec::new();
+    collect_parameters(schema, "", &mut parameters);
+    parameters
+}
+
+// flattens the properties of an object schema, nested objects become dotted names
+fn collect_parameters(schema: &Value, prefix: &str, parameters: &mut Vec<Parameter>) {
+    let required: Vec<&str> = match schema.get("required") {
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let properties = match schema.get("properties") {
+        Some(Value::Object(properties)) => properties,
+        _ => return,
+    };
+
+    for (key, property) in properties {
+        let name = format!("{}{}", prefix, key);
+
+        if property.get("properties").is_some() {
+            collect_parameters(property, &format!("{}.", name), parameters);
+            continue;
+        }
+
+        let mut parameter = Parameter::new(name, required.contains(&key.as_str()));
+        parameter._type = match property.get("type") {
+            Some(Value::String(t)) => Some(t.clone()),
+            Some(Value::Array(types)) => Some(
+                types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("|"),
+            ),
+            _ => None,
+        };
+        parameter.description = property
+            .get("description")
+            .or_else(|| property.get("title"))
+            .and_then(Value::as_str)
+            .map(String::from);
+        parameter.default = property.get("default").cloned();
+        parameter._enum = property.get("enum").and_then(Value::as_array).cloned();
+        parameter.minimum = property.get("minimum").cloned();
+        parameter.maximum = property.get("maximum").cloned();
+        parameter.min_length = property.get("minLength").and_then(Value::as_u64);
+        parameter.max_length = property.get("maxLength").and_then(Value::as_u64);
+        parameter.pattern = property
+            .get("pattern")
+            .and_then(Value::as_str)
+            .map(String::from);
+
+        parameters.push(parameter);
+    }
+}
+
+/// Operation a plan's parameter schema applies to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaAction {
+    Create,
+    Update,
+    Bind,
+}
+
+impl FromStr for SchemaAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(SchemaAction::Create),
+            "update" => Ok(SchemaAction::Update),
+            "bind" => Ok(SchemaAction::Bind),
+            _ => Err(format!(
+                "invalid schema action {}, expected create, update or bind",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SchemaAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaAction::Create => write!(f, "create"),
+            SchemaAction::Update => write!(f, "update"),
+            SchemaAction::Bind => write!(f, "bind"),
+        }
+    }
+}
+
+/// JSON schema of the parameters accepted by `action` on `plan`, when the broker publishes one.
+pub fn parameters_schema(plan: &Plan, action: SchemaAction) -> Option<&serde_json::Value> {
+    let schemas = plan.schemas.as_ref()?;
+    let schema = match action {
+        SchemaAction::Create => schemas.service_instance.as_ref()?.create.as_ref(),
+        SchemaAction::Update => schemas.service_instance.as_ref()?.update.as_ref(),
+        SchemaAction::Bind => schemas.service_binding.as_ref()?.create.as_ref(),
+    };
+    schema?.parameters.as_ref()
+}
+
+/// Checks parameters against the plan's schema for `action` before they are
+/// sent, plans without a schema accept any parameters.
+pub fn validate_parameters(
+    plan: &Plan,
+    action: SchemaAction,
+    parameters: &Value,
+) -> Result<(), Box<dyn Error>> {
+    let schema = match parameters_schema(plan, action) {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+
+    let mut scope = Scope::new();
+    let compiled = scope
+        .compile_and_return(schema.clone(), false)
+        .map_err(|e| {
+            format!(
+                "the {} schema of plan {} is invalid: {}",
+                action, plan.name, e
+            )
+        })?;
+    let validation = compiled.validate(parameters);
+    if validation.is_valid() {
+        return Ok(());
+    }
+
+    let errors: Vec<String> = validation
+        .errors
+        .iter()
+        .map(|e| {
+            format!(
+                "{} {}",
+                e.get_path(),
+                e.get_detail().unwrap_or(e.get_title())
+            )
+        })
+        .collect();
+    Err(format!(
+        "invalid parameters for plan {}: {}",
+        plan.name,
+        errors.join(", ")
+    )
+    .into())
+}
+
+/// Finds a catalog service by name or ID.
+pub fn find_service(services: Vec<Service>, service: &str) -> Result<Service, Box<dyn Error>> {
+    services
+        .into_iter()
+        .find(|s| s.name == service || s.id == service)
+        .ok_or_else(|| format!("service {} not found in catalog", service).into())
+}
+
+/// Finds a plan of `service` by name or ID.
+pub fn find_plan(service: &Service, plan: &str) -> Result<Plan, Box<dyn Error>> {
+    service
+        .plans
+        .iter()
+        .find(|p| p.name == plan || p.id == plan)
+        .cloned()
+        .ok_or_else(|| format!("plan {} not found in service {}", plan, service.name).into())
+}
@@ -0,0 +1,324 @@
+use crate::cli::{
+    connect, fetch_catalog_content, output, parameters_schema, schema_parameters, ExitStatus,
+    Options, SchemaAction,
+};
+use crate::client::{Client, ClientError};
+use crate::http::BrokerError;
+use crate::models::{CatalogChange, CatalogDiffOutput, Parameter};
+use crate::profile;
+use crate::store;
+
+use clap::ArgMatches;
+use rocl::models::{Catalog, Plan, Service};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Without a client both `--from` and `--to` must be given.
+pub async fn diff(
+    matches: &ArgMatches,
+    client: Option<&Client>,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    // by default the cached catalog is compared with the one the broker serves now
+    let from = match matches.value_of("from") {
+        Some(source) => load(source, &options).await?,
+        None => {
+            let cached =
+                store::catalog_cache_get(&options.catalog.cache_key)?.ok_or_else(|| {
+                    format!(
+                        "no cached catalog for {}, use --from <file|profile>",
+                        options.broker_url
+                    )
+                })?;
+            serde_json::from_str::<Catalog>(&cached.data)?
+                .services
+                .unwrap_or_default()
+        }
+    };
+    let to = match matches.value_of("to") {
+        Some(source) => load(source, &options).await?,
+        None => {
+            let client = client.ok_or("catalog diff requires --to or a broker")?;
+            let refresh = !options.catalog.offline;
+            let content = fetch_catalog_content(client, &options, refresh).await?;
+            serde_json::from_str::<Catalog>(&content)?
+                .services
+                .unwrap_or_default()
+        }
+    };
+
+    let diff_output = CatalogDiffOutput::new(compare_catalogs(&from, &to));
+    output::print(&diff_output, &options.output)?;
+
+    match diff_output.breaking {
+        true => Err(Box::new(ExitStatus {
+            code: 1,
+            reason: "catalog has breaking changes".into(),
+        })),
+        false => Ok(()),
+    }
+}
+
+/// Reads a catalog from a JSON or YAML file, or fetches it from the broker of a profile.
+async fn load(source: &str, options: &Options) -> Result<Vec<Service>, Box<dyn Error>> {
+    if Path::new(source).is_file() {
+        let content =
+            fs::read_to_string(source).map_err(|e| format!("failed to read {}: {}", source, e))?;
+        // YAML is a superset of JSON, both formats go through the same parser
+        let catalog: Catalog = serde_yaml::from_str(&content)
+            .map_err(|e| format!("invalid catalog in {}: {}", source, e))?;
+        return Ok(catalog.services.unwrap_or_default());
+    }
+
+    let profile = profile::load(source)
+        .map_err(|e| format!("{} is neither a catalog file nor a profile: {}", source, e))?;
+    if profile.broker_url.is_none() {
+        return Err(format!("profile {} has no broker_url", source).into());
+    }
+    let client = connect(&profile, options, None, None).await?;
+
+    let catalog = client.catalog().await.map_err(|e| match e {
+        ClientError::Broker(e) => ClientError::Broker(BrokerError {
+            context: format!("failed to get catalog of {}", source),
+            ..e
+        }),
+        e => e,
+    })?;
+
+    Ok(catalog.services.unwrap_or_default())
+}
+
+/// Changes between two catalogs, services and plans being matched by ID.
+pub fn compare_catalogs(from: &[Service], to: &[Service]) -> Vec<CatalogChange> {
+    let mut changes = Vec::new();
+
+    for old in from {
+        match to.iter().find(|s| s.id == old.id) {
+            Some(new) => compare_services(old, new, &mut changes),
+            None => changes.push(service_change("removed", old, true)),
+        }
+    }
+    for new in to {
+        if !from.iter().any(|s| s.id == new.id) {
+            changes.push(service_change("added", new, false));
+        }
+    }
+
+    changes
+}
+
+fn compare_services(old: &Service, new: &Service, changes: &mut Vec<CatalogChange>) {
+    let mut field = |name: &str, from: Value, to: Value, breaking: bool| {
+        if from != to {
+            changes.push(CatalogChange {
+                field: Some(name.to_string()),
+                from: Some(from),
+                to: Some(to),
+                ..service_change("modified", new, breaking)
+            });
+        }
+    };
+
+    field("name", json!(old.name), json!(new.name), true);
+    field(
+        "description",
+        json!(old.description),
+        json!(new.description),
+        false,
+    );
+    field("tags", json!(old.tags), json!(new.tags), false);
+    // new permissions must be granted by the platform before the service works
+    let requires_added = new
+        .requires
+        .iter()
+        .flatten()
+        .any(|r| !old.requires.iter().flatten().any(|o| o == r));
+    field(
+        "requires",
+        json!(old.requires),
+        json!(new.requires),
+        requires_added,
+    );
+    field(
+        "bindable",
+        json!(old.bindable),
+        json!(new.bindable),
+        old.bindable && !new.bindable,
+    );
+    field(
+        "plan_updateable",
+        json!(old.plan_updateable),
+        json!(new.plan_updateable),
+        old.plan_updateable == Some(true) && new.plan_updateable != Some(true),
+    );
+    field(
+        "binding_rotatable",
+        json!(old.binding_rotatable),
+        json!(new.binding_rotatable),
+        false,
+    );
+    field(
+        "dashboard_client",
+        json!(old.dashboard_client),
+        json!(new.dashboard_client),
+        false,
+    );
+    field("metadata", json!(old.metadata), json!(new.metadata), false);
+
+    for old_plan in &old.plans {
+        match new.plans.iter().find(|p| p.id == old_plan.id) {
+            Some(new_plan) => compare_plans(old, new, old_plan, new_plan, changes),
+            None => changes.push(plan_change("removed", new, old_plan, true)),
+        }
+    }
+    for new_plan in &new.plans {
+        if !old.plans.iter().any(|p| p.id == new_plan.id) {
+            changes.push(plan_change("added", new, new_plan, false));
+        }
+    }
+}
+
+fn compare_plans(
+    old_service: &Service,
+    service: &Service,
+    old: &Plan,
+    new: &Plan,
+    changes: &mut Vec<CatalogChange>,
+) {
+    let mut field = |name: &str, from: Value, to: Value, breaking: bool| {
+        if from != to {
+            changes.push(CatalogChange {
+                field: Some(name.to_string()),
+                from: Some(from),
+                to: Some(to),
+                ..plan_change("modified", service, new, breaking)
+            });
+        }
+    };
+
+    let old_bindable = old.bindable.unwrap_or(old_service.bindable);
+    let new_bindable = new.bindable.unwrap_or(service.bindable);
+
+    field("name", json!(old.name), json!(new.name), true);
+    field(
+        "description",
+        json!(old.description),
+        json!(new.description),
+        false,
+    );
+    // plans are free unless stated otherwise
+    field(
+        "free",
+        json!(old.free.unwrap_or(true)),
+        json!(new.free.unwrap_or(true)),
+        old.free.unwrap_or(true) && !new.free.unwrap_or(true),
+    );
+    field(
+        "bindable",
+        json!(old_bindable),
+        json!(new_bindable),
+        old_bindable && !new_bindable,
+    );
+    field(
+        "maintenance_info",
+        json!(old.maintenance_info),
+        json!(new.maintenance_info),
+        false,
+    );
+    field("metadata", json!(old.metadata), json!(new.metadata), false);
+
+    for action in [
+        SchemaAction::Create,
+        SchemaAction::Update,
+        SchemaAction::Bind,
+    ] {
+        compare_parameters(service, action, old, new, changes);
+    }
+}
+
+fn compare_parameters(
+    service: &Service,
+    action: SchemaAction,
+    old_plan: &Plan,
+    new_plan: &Plan,
+    changes: &mut Vec<CatalogChange>,
+) {
+    let parameters = |p: &Plan| {
+        parameters_schema(p, action)
+            .map(schema_parameters)
+            .unwrap_or_default()
+    };
+    let old = parameters(old_plan);
+    let new = parameters(new_plan);
+
+    let mut change = |kind: &str,
+                      name: &str,
+                      from: Option<&Parameter>,
+                      to: Option<&Parameter>,
+                      breaking: bool| {
+        changes.push(CatalogChange {
+            object: "parameter".to_string(),
+            field: Some(format!("{}.{}", action, name)),
+            from: from.map(to_value),
+            to: to.map(to_value),
+            ..plan_change(kind, service, new_plan, breaking)
+        });
+    };
+
+    for o in &old {
+        match new.iter().find(|n| n.name == o.name) {
+            Some(n) if n != o => {
+                // values accepted before must still be accepted
+                let enum_narrowed = match (&o._enum, &n._enum) {
+                    (Some(before), Some(after)) => before.iter().any(|v| !after.contains(v)),
+                    (None, Some(_)) => true,
+                    _ => false,
+                };
+                let breaking = (n.required && !o.required)
+                    || n._type != o._type
+                    || enum_narrowed
+                    || n.pattern != o.pattern && n.pattern.is_some();
+                change("modified", &n.name, Some(o), Some(n), breaking);
+            }
+            Some(_) => {}
+            // brokers reject parameters they no longer know about
+            None => change("removed", &o.name, Some(o), None, true),
+        }
+    }
+    for n in &new {
+        if !old.iter().any(|o| o.name == n.name) {
+            change("added", &n.name, None, Some(n), n.required);
+        }
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn service_change(change: &str, service: &Service, breaking: bool) -> CatalogChange {
+    CatalogChange {
+        change: change.to_string(),
+        object: "service".to_string(),
+        service_id: service.id.clone(),
+        service_name: service.name.clone(),
+        plan_id: None,
+        plan_name: None,
+        field: None,
+        from: None,
+        to: None,
+        breaking,
+    }
+}
+
+fn plan_change(change: &str, service: &Service, plan: &Plan, breaking: bool) -> CatalogChange {
+    CatalogChange {
+        object: "plan".to_string(),
+        plan_id: Some(plan.id.clone()),
+        plan_name: Some(plan.name.clone()),
+        ..service_change(change, service, breaking)
+    }
+}
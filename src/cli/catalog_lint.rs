@@ -0,0 +1,524 @@
+use crate::cli::{fetch_catalog_content, output, ExitStatus, Options};
+use crate::client::Client;
+use crate::models::{CatalogLintOutput, LintFinding};
+use crate::operation::POOL_INTERVAL;
+
+use clap::ArgMatches;
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use valico::json_schema::Scope;
+
+const DRAFT_04: [&str; 2] = [
+    "http://json-schema.org/draft-04/schema#",
+    "http://json-schema.org/draft-04/schema",
+];
+
+/// Longest `maximum_polling_duration` not reported, one week.
+const MAX_POLLING_DURATION: u64 = 7 * 24 * 60 * 60;
+
+/// Without a client the catalog must come from `--file`.
+pub async fn lint(
+    matches: &ArgMatches,
+    client: Option<&Client>,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    // the raw document is checked, rocl's models drop fields such as maximum_polling_duration
+    let catalog: Value = match (matches.value_of("file"), client) {
+        (Some(file), _) => {
+            let content =
+                fs::read_to_string(file).map_err(|e| format!("failed to read {}: {}", file, e))?;
+            serde_yaml::from_str(&content)
+                .map_err(|e| format!("invalid catalog in {}: {}", file, e))?
+        }
+        (None, Some(client)) => serde_json::from_str(
+            &fetch_catalog_content(client, &options, options.catalog.refresh).await?,
+        )?,
+        (None, None) => return Err("catalog lint requires --file or a broker".into()),
+    };
+
+    let lint_output = CatalogLintOutput::new(lint_catalog(&catalog));
+    output::print(&lint_output, &options.output)?;
+
+    match lint_output.errors {
+        0 => Ok(()),
+        errors => Err(Box::new(ExitStatus {
+            code: 1,
+            reason: format!("catalog has {} errors", errors),
+        })),
+    }
+}
+
+/// Findings of the OSB rules on a raw catalog document, in document order.
+pub fn lint_catalog(catalog: &Value) -> Vec<LintFinding> {
+    let mut linter = Linter::default();
+    linter.catalog(catalog);
+    linter.findings
+}
+
+#[derive(Default)]
+struct Linter {
+    findings: Vec<LintFinding>,
+    /// First path of each service ID, service name and plan ID, they are unique catalog-wide.
+    service_ids: HashMap<String, String>,
+    service_names: HashMap<String, String>,
+    plan_ids: HashMap<String, String>,
+}
+
+impl Linter {
+    fn error(&mut self, rule: &str, path: &str, message: String) {
+        self.finding("error", rule, path, message);
+    }
+
+    fn warning(&mut self, rule: &str, path: &str, message: String) {
+        self.finding("warning", rule, path, message);
+    }
+
+    fn finding(&mut self, severity: &str, rule: &str, path: &str, message: String) {
+        self.findings.push(LintFinding {
+            severity: severity.to_string(),
+            rule: rule.to_string(),
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn catalog(&mut self, catalog: &Value) {
+        let services = match catalog.get("services") {
+            Some(Value::Array(services)) => services,
+            _ => {
+                return self.error(
+                    "required-field",
+                    "services",
+                    "catalog must have a services array".into(),
+                )
+            }
+        };
+
+        for (i, service) in services.iter().enumerate() {
+            self.service(service, &format!("services[{}]", i));
+        }
+    }
+
+    fn service(&mut self, service: &Value, path: &str) {
+        if !service.is_object() {
+            return self.error("required-field", path, "service must be an object".into());
+        }
+
+        if let Some(id) = self.id(service, path, "service") {
+            if let Some(first) = unique(&mut self.service_ids, id, path) {
+                self.error(
+                    "id-unique",
+                    &format!("{}.id", path),
+                    duplicate("service id", id, &first),
+                );
+            }
+        }
+        if let Some(name) = self.name(service, path, "service") {
+            if let Some(first) = unique(&mut self.service_names, name, path) {
+                self.error(
+                    "name-unique",
+                    &format!("{}.name", path),
+                    duplicate("service name", name, &first),
+                );
+            }
+        }
+        self.description(service, path);
+        if !service.get("bindable").is_some_and(Value::is_boolean) {
+            self.error(
+                "required-field",
+                &format!("{}.bindable", path),
+                "bindable must be a boolean".into(),
+            );
+        }
+        let plan_updateable = self.plan_updateable(service, path);
+        if let Some(client) = service.get("dashboard_client") {
+            self.dashboard_client(client, &format!("{}.dashboard_client", path));
+        }
+
+        let plans = match service.get("plans") {
+            Some(Value::Array(plans)) if !plans.is_empty() => plans,
+            _ => {
+                return self.error(
+                    "required-field",
+                    &format!("{}.plans", path),
+                    "service must have at least one plan".into(),
+                )
+            }
+        };
+
+        let mut plan_names = HashMap::new();
+        for (i, plan) in plans.iter().enumerate() {
+            let plan_path = format!("{}.plans[{}]", path, i);
+            self.plan(plan, &plan_path, &mut plan_names);
+
+            // a plan's flag takes precedence over the service's
+            let updateable = self.plan_updateable(plan, &plan_path).or(plan_updateable);
+            if updateable == Some(true) && plans.len() == 1 {
+                self.warning(
+                    "plan-updateable",
+                    &plan_path,
+                    "plan is updateable but the service has no other plan to update to".into(),
+                );
+            }
+        }
+    }
+
+    fn plan(&mut self, plan: &Value, path: &str, plan_names: &mut HashMap<String, String>) {
+        if !plan.is_object() {
+            return self.error("required-field", path, "plan must be an object".into());
+        }
+
+        if let Some(id) = self.id(plan, path, "plan") {
+            if let Some(first) = unique(&mut self.plan_ids, id, path) {
+                self.error(
+                    "id-unique",
+                    &format!("{}.id", path),
+                    duplicate("plan id", id, &first),
+                );
+            }
+        }
+        if let Some(name) = self.name(plan, path, "plan") {
+            if let Some(first) = unique(plan_names, name, path) {
+                self.error(
+                    "name-unique",
+                    &format!("{}.name", path),
+                    duplicate("plan name", name, &first),
+                );
+            }
+        }
+        self.description(plan, path);
+
+        if let Some(duration) = plan.get("maximum_polling_duration") {
+            self.maximum_polling_duration(duration, &format!("{}.maximum_polling_duration", path));
+        }
+        if let Some(maintenance_info) = plan.get("maintenance_info") {
+            self.maintenance_info(maintenance_info, &format!("{}.maintenance_info", path));
+        }
+
+        for (resource, action) in [
+            ("service_instance", "create"),
+            ("service_instance", "update"),
+            ("service_binding", "create"),
+        ] {
+            if let Some(schema) = plan
+                .get("schemas")
+                .and_then(|s| s.get(resource))
+                .and_then(|r| r.get(action))
+                .and_then(|a| a.get("parameters"))
+            {
+                let schema_path = format!("{}.schemas.{}.{}.parameters", path, resource, action);
+                self.schema(schema, &schema_path);
+            }
+        }
+    }
+
+    /// IDs must be non-empty strings, GUIDs being recommended.
+    fn id<'a>(&mut self, value: &'a Value, path: &str, kind: &str) -> Option<&'a str> {
+        let path = format!("{}.id", path);
+        let id = match value.get("id").and_then(Value::as_str) {
+            Some(id) if !id.is_empty() => id,
+            _ => {
+                self.error(
+                    "required-field",
+                    &path,
+                    format!("{} id must be a non-empty string", kind),
+                );
+                return None;
+            }
+        };
+
+        if id.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            self.error(
+                "id-format",
+                &path,
+                format!(
+                    "{} id {:?} contains whitespace or control characters",
+                    kind, id
+                ),
+            );
+        } else if !is_guid(id) {
+            self.warning(
+                "id-format",
+                &path,
+                format!("{} id {} is not a GUID, which is recommended", kind, id),
+            );
+        }
+
+        Some(id)
+    }
+
+    /// Names must be non-empty strings, CLI-friendly ones being recommended.
+    fn name<'a>(&mut self, value: &'a Value, path: &str, kind: &str) -> Option<&'a str> {
+        let path = format!("{}.name", path);
+        let name = match value.get("name").and_then(Value::as_str) {
+            Some(name) if !name.is_empty() => name,
+            _ => {
+                self.error(
+                    "required-field",
+                    &path,
+                    format!("{} name must be a non-empty string", kind),
+                );
+                return None;
+            }
+        };
+
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        {
+            self.warning(
+                "name-format",
+                &path,
+                format!(
+                    "{} name {:?} is not CLI-friendly, use letters, digits, '-', '_' and '.'",
+                    kind, name
+                ),
+            );
+        }
+
+        Some(name)
+    }
+
+    fn description(&mut self, value: &Value, path: &str) {
+        match value.get("description").and_then(Value::as_str) {
+            Some(description) if !description.is_empty() => {}
+            _ => self.error(
+                "required-field",
+                &format!("{}.description", path),
+                "description must be a non-empty string".into(),
+            ),
+        }
+    }
+
+    fn plan_updateable(&mut self, value: &Value, path: &str) -> Option<bool> {
+        match value.get("plan_updateable") {
+            None | Some(Value::Null) => None,
+            Some(Value::Bool(updateable)) => Some(*updateable),
+            Some(_) => {
+                self.error(
+                    "plan-updateable",
+                    &format!("{}.plan_updateable", path),
+                    "plan_updateable must be a boolean".into(),
+                );
+                None
+            }
+        }
+    }
+
+    fn dashboard_client(&mut self, client: &Value, path: &str) {
+        if !client.is_object() {
+            return self.error(
+                "dashboard-client",
+                path,
+                "dashboard_client must be an object".into(),
+            );
+        }
+
+        for field in ["id", "secret"] {
+            match client.get(field).and_then(Value::as_str) {
+                Some(value) if !value.is_empty() => {}
+                _ => self.error(
+                    "dashboard-client",
+                    &format!("{}.{}", path, field),
+                    format!("dashboard_client {} must be a non-empty string", field),
+                ),
+            }
+        }
+
+        let redirect_path = format!("{}.redirect_uri", path);
+        match client.get("redirect_uri") {
+            None | Some(Value::Null) => self.warning(
+                "dashboard-client",
+                &redirect_path,
+                "dashboard_client has no redirect_uri, the OAuth server cannot validate it".into(),
+            ),
+            Some(Value::String(uri)) => match Url::parse(uri) {
+                Ok(url) if url.scheme() == "https" || url.scheme() == "http" => {}
+                _ => self.error(
+                    "dashboard-client",
+                    &redirect_path,
+                    format!("redirect_uri {} is not an absolute HTTP URL", uri),
+                ),
+            },
+            Some(_) => self.error(
+                "dashboard-client",
+                &redirect_path,
+                "redirect_uri must be a string".into(),
+            ),
+        }
+    }
+
+    fn maximum_polling_duration(&mut self, duration: &Value, path: &str) {
+        match duration.as_u64() {
+            Some(seconds) if seconds > 0 && seconds < POOL_INTERVAL => self.warning(
+                "maximum-polling-duration",
+                path,
+                format!(
+                    "{} seconds is shorter than the {} second polling interval",
+                    seconds, POOL_INTERVAL
+                ),
+            ),
+            Some(seconds) if seconds > MAX_POLLING_DURATION => self.warning(
+                "maximum-polling-duration",
+                path,
+                format!(
+                    "{} seconds is more than a week, platforms will give up earlier",
+                    seconds
+                ),
+            ),
+            Some(seconds) if seconds > 0 => {}
+            _ => self.error(
+                "maximum-polling-duration",
+                path,
+                format!("{} is not a positive number of seconds", duration),
+            ),
+        }
+    }
+
+    fn maintenance_info(&mut self, maintenance_info: &Value, path: &str) {
+        let path = format!("{}.version", path);
+        match maintenance_info.get("version").and_then(Value::as_str) {
+            Some(version) if is_semver(version) => {}
+            Some(version) => self.error(
+                "maintenance-info-version",
+                &path,
+                format!("{} is not a semantic version", version),
+            ),
+            None => self.error(
+                "maintenance-info-version",
+                &path,
+                "maintenance_info must have a version string".into(),
+            ),
+        }
+    }
+
+    /// OSB requires JSON Schema draft-04 describing an object.
+    fn schema(&mut self, schema: &Value, path: &str) {
+        if !schema.is_object() {
+            return self.error("schema-invalid", path, "schema must be an object".into());
+        }
+
+        match schema.get("$schema").and_then(Value::as_str) {
+            Some(uri) if DRAFT_04.contains(&uri) => {}
+            Some(uri) => self.error(
+                "schema-draft-04",
+                &format!("{}.$schema", path),
+                format!("{} is not JSON Schema draft-04", uri),
+            ),
+            None => self.warning(
+                "schema-draft-04",
+                &format!("{}.$schema", path),
+                "schema does not declare $schema, draft-04 is assumed".into(),
+            ),
+        }
+
+        if let Some(schema_type) = schema.get("type") {
+            if schema_type != "object" {
+                self.error(
+                    "schema-invalid",
+                    &format!("{}.type", path),
+                    format!(
+                        "parameters schema must be of type object, not {}",
+                        schema_type
+                    ),
+                );
+            }
+        }
+
+        if let Err(e) = Scope::new().compile(schema.clone(), false) {
+            self.error("schema-invalid", path, e.to_string());
+        }
+
+        self.draft_04_keywords(schema, path);
+    }
+
+    // reports keywords from later drafts, which draft-04 validators ignore or reject
+    fn draft_04_keywords(&mut self, schema: &Value, path: &str) {
+        let keywords = match schema {
+            Value::Object(keywords) => keywords,
+            _ => return,
+        };
+
+        for (keyword, value) in keywords {
+            let keyword_path = format!("{}.{}", path, keyword);
+            match keyword.as_str() {
+                "exclusiveMinimum" | "exclusiveMaximum" if !value.is_boolean() => self.error(
+                    "schema-draft-04",
+                    &keyword_path,
+                    format!("{} must be a boolean in draft-04", keyword),
+                ),
+                "const" | "contains" | "propertyNames" | "if" | "then" | "else" => self.error(
+                    "schema-draft-04",
+                    &keyword_path,
+                    format!("{} is not a draft-04 keyword", keyword),
+                ),
+                "properties" | "patternProperties" | "definitions" | "dependencies" => {
+                    for (name, subschema) in value.as_object().into_iter().flatten() {
+                        self.draft_04_keywords(subschema, &format!("{}.{}", keyword_path, name));
+                    }
+                }
+                "items" | "allOf" | "anyOf" | "oneOf" if value.is_array() => {
+                    for (i, subschema) in value.as_array().into_iter().flatten().enumerate() {
+                        self.draft_04_keywords(subschema, &format!("{}[{}]", keyword_path, i));
+                    }
+                }
+                "items" | "additionalItems" | "additionalProperties" | "not" => {
+                    self.draft_04_keywords(value, &keyword_path)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Records the first path of `value`, returning it when `value` was already seen.
+fn unique(seen: &mut HashMap<String, String>, value: &str, path: &str) -> Option<String> {
+    match seen.get(value) {
+        Some(first) => Some(first.clone()),
+        None => {
+            seen.insert(value.to_string(), path.to_string());
+            None
+        }
+    }
+}
+
+fn duplicate(what: &str, value: &str, first: &str) -> String {
+    format!("{} {} is already used by {}", what, value, first)
+}
+
+fn is_guid(id: &str) -> bool {
+    id.len() == 36
+        && id.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Semantic Versioning 2.0.0, as required for `maintenance_info.version`.
+fn is_semver(version: &str) -> bool {
+    let numeric = |s: &str| {
+        !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) && (s == "0" || !s.starts_with('0'))
+    };
+    let identifier =
+        |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+
+    let (version, build) = match version.split_once('+') {
+        Some((version, build)) => (version, Some(build)),
+        None => (version, None),
+    };
+    let (core, pre_release) = match version.split_once('-') {
+        Some((core, pre_release)) => (core, Some(pre_release)),
+        None => (version, None),
+    };
+
+    core.split('.').count() == 3
+        && core.split('.').all(numeric)
+        && pre_release.is_none_or(|p| {
+            p.split('.')
+                .all(|i| identifier(i) && (numeric(i) || !i.bytes().all(|b| b.is_ascii_digit())))
+        })
+        && build.is_none_or(|b| b.split('.').all(identifier))
+}
@@ -0,0 +1,463 @@
+use crate::broker::RoclBroker;
+use crate::cli::{find_plan, find_service, output, parse_parameters, ExitStatus, Options};
+use crate::client::{Client, ClientError, USER_AGENT};
+use crate::models::{ConformanceCheck, ConformanceOutput};
+use crate::operation::{wait_for_operation, WaitError, WaitOptions, POOL_INTERVAL};
+
+use clap::ArgMatches;
+use reqwest::StatusCode;
+use rocl::models::{last_operation_resource::State, Plan, Service};
+use serde_json::json;
+use std::error::Error;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Checks run after fetching the catalog, in order. Any of them can be left out with `--skip`.
+pub const CONFORMANCE_CHECKS: [&str; 12] = [
+    "invalid-version",
+    "invalid-auth",
+    "provision-sync",
+    "provision-async",
+    "last-operation",
+    "fetch-instance",
+    "update",
+    "bind",
+    "fetch-binding",
+    "unbind",
+    "deprovision",
+    "gone",
+];
+
+pub async fn conformance(
+    matches: &ArgMatches,
+    client: Client,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    if options.curl_output {
+        return Err("conformance drives a whole lifecycle and cannot be printed as curl".into());
+    }
+    // sending other versions or credentials needs the HTTP configuration of the client
+    let not_http = match client.configuration() {
+        Some(_) => None,
+        None => Some("the broker is not reached over HTTP"),
+    };
+
+    let timeout = Duration::from_secs(*matches.get_one::<u64>("operation_timeout").unwrap());
+    let parameters = json!(parse_parameters(matches.values_of("parameters"))?);
+    let mut suite = Suite {
+        checks: Vec::new(),
+        skip: matches.values_of("skip").into_iter().flatten().collect(),
+    };
+
+    let started = Instant::now();
+    let target = match client.catalog().await {
+        Ok(catalog) => select(catalog.services.unwrap_or_default(), matches),
+        Err(e) => Err(e.to_string()),
+    };
+    suite.finish(
+        "catalog",
+        started,
+        target.as_ref().map(|_| ()).map_err(Clone::clone),
+    );
+
+    if let Some(started) = suite.start("invalid-version", not_http) {
+        let config = client.configuration().unwrap().clone();
+        let outdated =
+            Client::with_broker(Arc::new(RoclBroker::new(config, "1.0", Some(USER_AGENT))));
+        let result = match outdated.catalog().await {
+            Err(e) if e.status() == Some(StatusCode::PRECONDITION_FAILED.as_u16()) => Ok(()),
+            Err(e) => Err(expected("412 Precondition Failed", e)),
+            Ok(_) => Err("expected 412 Precondition Failed, got a catalog".into()),
+        };
+        suite.finish("invalid-version", started, result);
+    }
+
+    if let Some(started) = suite.start("invalid-auth", not_http) {
+        // rocl always sends the configured credentials, the request is made here to replace them
+        let config = client.configuration().unwrap();
+        let result = match config
+            .client
+            .get(format!("{}/v2/catalog", config.base_path))
+            .header("X-Broker-API-Version", client.api_version())
+            .basic_auth("rocs-conformance", Some("invalid"))
+            .send()
+            .await
+        {
+            Ok(response) if response.status() == StatusCode::UNAUTHORIZED => Ok(()),
+            Ok(response) => Err(format!(
+                "expected 401 Unauthorized, got {}",
+                response.status()
+            )),
+            Err(e) => Err(format!("catalog request failed: {}", e)),
+        };
+        suite.finish("invalid-auth", started, result);
+    }
+
+    let (service, plan) = match target {
+        Ok(target) => target,
+        Err(_) => {
+            for name in &CONFORMANCE_CHECKS[2..] {
+                suite.skip(name, "the catalog gave no service and plan to test");
+            }
+            return report(matches, suite, &options, None);
+        }
+    };
+    let (service_id, plan_id) = (service.id.as_str(), plan.id.as_str());
+
+    let mut sync_instance = None;
+    if let Some(started) = suite.start("provision-sync", None) {
+        let instance_id = Uuid::new_v4().as_hyphenated().to_string();
+        let result = client
+            .provision(&instance_id, service_id, plan_id)
+            .parameters(parameters.clone())
+            .accepts_incomplete(false)
+            .send()
+            .await;
+        match result {
+            Err(e) if async_required(&e) => suite.skip(
+                "provision-sync",
+                "the broker only supports asynchronous provisioning",
+            ),
+            result => {
+                if result.is_ok() {
+                    sync_instance = Some(instance_id);
+                }
+                let result = result.map(|_| ()).map_err(|e| e.to_string());
+                suite.finish("provision-sync", started, result);
+            }
+        }
+    }
+
+    let mut async_instance = None;
+    if let Some(started) = suite.start("provision-async", None) {
+        let instance_id = Uuid::new_v4().as_hyphenated().to_string();
+        let result = client
+            .provision(&instance_id, service_id, plan_id)
+            .parameters(parameters.clone())
+            .accepts_incomplete(true)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        if result.is_ok() {
+            async_instance = Some(instance_id);
+        }
+        suite.finish("provision-async", started, result);
+    }
+
+    let no_async_instance = match async_instance {
+        Some(_) => None,
+        None => Some("no instance was provisioned asynchronously"),
+    };
+    if let Some(started) = suite.start("last-operation", no_async_instance) {
+        let instance_id = async_instance.as_deref().unwrap();
+        let result =
+            wait_for_instance(&client, instance_id, service_id, plan_id, timeout, false).await;
+        suite.finish("last-operation", started, result);
+    }
+
+    // lifecycle checks prefer the asynchronous instance, it is the one brokers must fully support
+    let instance = async_instance.as_deref().or(sync_instance.as_deref());
+    let no_instance = match instance {
+        Some(_) => None,
+        None => Some("no instance was provisioned"),
+    };
+
+    if let Some(started) = suite.start("fetch-instance", no_instance) {
+        let result = match client.fetch_instance(instance.unwrap()).await {
+            Ok(resource)
+                if resource
+                    .service_id
+                    .as_deref()
+                    .is_some_and(|s| s != service_id) =>
+            {
+                Err(format!(
+                    "instance has service_id {:?}",
+                    resource.service_id.unwrap()
+                ))
+            }
+            Ok(resource) if resource.plan_id.as_deref().is_some_and(|p| p != plan_id) => Err(
+                format!("instance has plan_id {:?}", resource.plan_id.unwrap()),
+            ),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        };
+        suite.finish("fetch-instance", started, result);
+    }
+
+    if let Some(started) = suite.start("update", no_instance) {
+        let instance_id = instance.unwrap();
+        let accepts_incomplete = async_instance.is_some();
+
+        let result = match client
+            .update(instance_id, service_id)
+            .plan_id(plan_id)
+            .parameters(parameters.clone())
+            .accepts_incomplete(accepts_incomplete)
+            .send()
+            .await
+        {
+            Ok(_) if accepts_incomplete => {
+                wait_for_instance(&client, instance_id, service_id, plan_id, timeout, false).await
+            }
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        };
+        suite.finish("update", started, result);
+    }
+
+    let mut binding = None;
+    let not_bindable = match plan.bindable.unwrap_or(service.bindable) {
+        true => no_instance,
+        false => Some("the plan is not bindable"),
+    };
+    if let Some(started) = suite.start("bind", not_bindable) {
+        let binding_id = Uuid::new_v4().as_hyphenated().to_string();
+        let result = client
+            .bind(instance.unwrap(), &binding_id, service_id, plan_id)
+            .parameters(parameters.clone())
+            .accepts_incomplete(false)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        if result.is_ok() {
+            binding = Some(binding_id);
+        }
+        suite.finish("bind", started, result);
+    }
+
+    let no_binding = match binding {
+        Some(_) => None,
+        None => Some("no binding was created"),
+    };
+    if let Some(started) = suite.start("fetch-binding", no_binding) {
+        let result = client
+            .fetch_binding(instance.unwrap(), binding.as_deref().unwrap())
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        suite.finish("fetch-binding", started, result);
+    }
+
+    if let Some(started) = suite.start("unbind", no_binding) {
+        let result = client
+            .unbind(
+                instance.unwrap(),
+                binding.as_deref().unwrap(),
+                service_id,
+                plan_id,
+            )
+            .accepts_incomplete(false)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        suite.finish("unbind", started, result);
+    }
+
+    let mut deprovisioned = None;
+    if let Some(started) = suite.start("deprovision", no_instance) {
+        let mut result = Ok(());
+
+        for (instance_id, accepts_incomplete) in [(&sync_instance, false), (&async_instance, true)]
+        {
+            let instance_id = match instance_id {
+                Some(instance_id) => instance_id,
+                None => continue,
+            };
+            let deprovision = match client
+                .deprovision(instance_id, service_id, plan_id)
+                .accepts_incomplete(accepts_incomplete)
+                .send()
+                .await
+            {
+                Ok(_) if accepts_incomplete => {
+                    wait_for_instance(&client, instance_id, service_id, plan_id, timeout, true)
+                        .await
+                }
+                Ok(_) => Ok(()),
+                Err(e) => Err(e.to_string()),
+            };
+
+            match deprovision {
+                Ok(()) => deprovisioned = deprovisioned.or(Some(instance_id.clone())),
+                Err(e) => result = result.and(Err(e)),
+            }
+        }
+        suite.finish("deprovision", started, result);
+    }
+
+    let not_deprovisioned = match deprovisioned {
+        Some(_) => None,
+        None => Some("no instance was deprovisioned"),
+    };
+    if let Some(started) = suite.start("gone", not_deprovisioned) {
+        let result = match client
+            .deprovision(deprovisioned.as_deref().unwrap(), service_id, plan_id)
+            .accepts_incomplete(false)
+            .send()
+            .await
+        {
+            Err(e) if e.status() == Some(StatusCode::GONE.as_u16()) => Ok(()),
+            Err(e) => Err(expected("410 Gone", e)),
+            Ok(_) => {
+                Err("expected 410 Gone deprovisioning a deleted instance, got a success".into())
+            }
+        };
+        suite.finish("gone", started, result);
+    }
+
+    report(matches, suite, &options, Some((service, plan)))
+}
+
+struct Suite<'a> {
+    checks: Vec<ConformanceCheck>,
+    /// Checks left out with `--skip`.
+    skip: Vec<&'a str>,
+}
+
+impl Suite<'_> {
+    /// Returns when the check started, or records it as skipped when `blocked`
+    /// by an earlier failure or left out by the user.
+    fn start(&mut self, name: &str, blocked: Option<&str>) -> Option<Instant> {
+        match blocked {
+            Some(reason) => self.skip(name, reason),
+            None if self.skip.contains(&name) => self.skip(name, "skipped with --skip"),
+            None => {
+                eprintln!("[INFO] running {}", name);
+                return Some(Instant::now());
+            }
+        }
+        None
+    }
+
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.record(name, "skipped", 0, Some(reason.to_string()));
+    }
+
+    fn finish(&mut self, name: &str, started: Instant, result: Result<(), String>) {
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(()) => self.record(name, "passed", duration_ms, None),
+            Err(message) => {
+                eprintln!("[WARN] {} failed: {}", name, message);
+                self.record(name, "failed", duration_ms, Some(message))
+            }
+        }
+    }
+
+    fn record(&mut self, name: &str, status: &str, duration_ms: u64, message: Option<String>) {
+        self.checks.push(ConformanceCheck {
+            name: name.to_string(),
+            status: status.to_string(),
+            duration_ms,
+            message,
+        });
+    }
+}
+
+fn report(
+    matches: &ArgMatches,
+    suite: Suite,
+    options: &Options,
+    target: Option<(Service, Plan)>,
+) -> Result<(), Box<dyn Error>> {
+    let mut report = ConformanceOutput::new(options.broker_url.clone(), suite.checks);
+    if let Some((service, plan)) = target {
+        report.service_name = Some(service.name);
+        report.plan_name = Some(plan.name);
+    }
+
+    output::print(&report, &options.output)?;
+
+    if let Some(file) = matches.value_of("junit") {
+        fs::write(file, output::junit(&report))
+            .map_err(|e| format!("failed to write {}: {}", file, e))?;
+        eprintln!("[INFO] JUnit report written to {}", file);
+    }
+
+    match report.failed {
+        0 => Ok(()),
+        failed => Err(Box::new(ExitStatus {
+            code: 1,
+            reason: format!("{} conformance checks failed", failed),
+        })),
+    }
+}
+
+/// Service and plan given on the command line, or the first bindable ones of the catalog.
+fn select(services: Vec<Service>, matches: &ArgMatches) -> Result<(Service, Plan), String> {
+    let service = match matches.value_of("service") {
+        Some(service) => find_service(services, service).map_err(|e| e.to_string())?,
+        None => services
+            .iter()
+            .find(|s| s.bindable)
+            .or_else(|| services.first())
+            .cloned()
+            .ok_or("the catalog has no services")?,
+    };
+    let plan = match matches.value_of("plan") {
+        Some(plan) => find_plan(&service, plan).map_err(|e| e.to_string())?,
+        None => service
+            .plans
+            .iter()
+            .find(|p| p.bindable.unwrap_or(service.bindable))
+            .or_else(|| service.plans.first())
+            .cloned()
+            .ok_or_else(|| format!("service {} has no plans", service.name))?,
+    };
+
+    Ok((service, plan))
+}
+
+/// Polls the instance's last operation until it finishes. A 410 Gone counts as
+/// success when `gone` is set, as brokers answer so once a deprovisioning completed.
+async fn wait_for_instance(
+    client: &Client,
+    instance_id: &str,
+    service_id: &str,
+    plan_id: &str,
+    timeout: Duration,
+    gone: bool,
+) -> Result<(), String> {
+    let request = client
+        .last_operation(instance_id)
+        .service_id(service_id)
+        .plan_id(plan_id);
+    let wait_options = WaitOptions {
+        interval: Duration::from_secs(POOL_INTERVAL),
+        backoff: 1.0,
+        timeout: Some(timeout),
+        ..Default::default()
+    };
+
+    match wait_for_operation(request, wait_options).outcome().await {
+        Ok(last_op) if last_op.state == State::Failed => Err(format!(
+            "operation failed: {}",
+            last_op.description.unwrap_or_default()
+        )),
+        Ok(_) => Ok(()),
+        Err(WaitError::Client(e)) if gone && e.status() == Some(StatusCode::GONE.as_u16()) => {
+            Ok(())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// brokers answer 422 AsyncRequired to synchronous requests they cannot serve
+fn async_required(err: &ClientError) -> bool {
+    match err {
+        ClientError::Broker(e) => {
+            e.status == StatusCode::UNPROCESSABLE_ENTITY.as_u16()
+                && e.content.contains("AsyncRequired")
+        }
+        _ => false,
+    }
+}
+
+fn expected(status: &str, err: ClientError) -> String {
+    format!("expected {}, got {}", status, err)
+}
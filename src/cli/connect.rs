@@ -0,0 +1,110 @@
+use crate::auth::{AuthMode, ClientCredentials, ClientCredentialsProvider, TokenProvider};
+use crate::cassette::{Player, Recorder};
+use crate::cli::{Options, REQUEST_IDENTITY_HEADER};
+use crate::client::Client;
+use crate::profile::Profile;
+use crate::proxy::{self, LocalProxy};
+
+use reqwest::header::HeaderValue;
+use std::error::Error;
+use std::sync::Arc;
+
+/// Broker URL shown in traces of replayed requests when none is configured.
+const REPLAY_URL: &str = "http://replay.invalid";
+
+/// Client of the broker a profile points at, flags and environment variables
+/// must already be merged into the profile.
+///
+/// Requests carry the request identity of `options` and go through the local
+/// proxy when tracing, recording or replaying. Replaying needs neither a broker
+/// URL nor credentials.
+pub async fn connect(
+    profile: &Profile,
+    options: &Options,
+    recorder: Option<Recorder>,
+    player: Option<Player>,
+) -> Result<Client, Box<dyn Error>> {
+    // replayed responses come from the cassette, the broker is never contacted
+    let broker_url = match (&profile.broker_url, &player) {
+        (Some(broker_url), _) => broker_url.clone(),
+        (None, Some(_)) => REPLAY_URL.to_string(),
+        (None, None) => return Err("broker URL is required, use --broker or a profile".into()),
+    };
+    let (tls, network) = (profile.tls(), profile.network());
+
+    let mut builder = Client::builder(&broker_url)
+        .tls(tls.clone())
+        .network(network.clone())
+        .header(
+            REQUEST_IDENTITY_HEADER,
+            HeaderValue::from_str(&options.request_id)?,
+        );
+
+    match profile.auth_mode() {
+        _ if player.is_some() => {}
+        // curl commands are printed with a token placeholder, nothing is sent
+        AuthMode::OAuth2 if options.curl_output => {}
+        AuthMode::Basic => {
+            builder = builder.basic_auth(
+                profile
+                    .username
+                    .as_deref()
+                    .ok_or("basic auth requires --username or a profile")?,
+                profile.password.as_deref().unwrap_or_default(),
+            );
+        }
+        AuthMode::Bearer => {
+            let token = profile
+                .token
+                .as_deref()
+                .ok_or("bearer auth requires --token or a profile")?;
+            builder = builder.bearer_token(token);
+        }
+        AuthMode::OAuth2 => {
+            let credentials = ClientCredentials {
+                token_url: profile
+                    .token_url
+                    .clone()
+                    .ok_or("oauth2 auth requires --token-url or a profile")?,
+                client_id: profile
+                    .client_id
+                    .clone()
+                    .ok_or("oauth2 auth requires --client-id or a profile")?,
+                client_secret: profile
+                    .client_secret
+                    .clone()
+                    .ok_or("oauth2 auth requires --client-secret or a profile")?,
+                scope: profile.scope.clone(),
+            };
+            let token_client = network
+                .apply(tls.apply(reqwest::Client::builder())?)?
+                .build()?;
+            let provider = ClientCredentialsProvider {
+                client: token_client,
+                credentials,
+            };
+            // the first token is fetched now so credential errors show before any request
+            let token = provider.token(false).await?;
+            builder = builder
+                .bearer_token(&token)
+                .token_provider(Arc::new(provider));
+        }
+    }
+
+    if options.tracer.enabled() || recorder.is_some() || player.is_some() {
+        let upstream = network
+            .apply(tls.apply(reqwest::Client::builder())?)?
+            .build()?;
+        let proxy_url = proxy::start(LocalProxy {
+            upstream,
+            broker_url,
+            tracer: options.tracer.clone(),
+            recorder,
+            player,
+        })
+        .await?;
+        builder = builder.local_proxy(&proxy_url);
+    }
+
+    builder.build()
+}
@@ -0,0 +1,48 @@
+use crate::mock::{self, MockBroker};
+
+use clap::ArgMatches;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Serves the catalog of `--catalog` as an in-memory broker until interrupted.
+pub async fn mock_broker(
+    matches: &ArgMatches,
+    credentials: Option<(String, String)>,
+) -> Result<(), Box<dyn Error>> {
+    let file = matches.value_of("catalog").unwrap();
+    let content =
+        fs::read_to_string(file).map_err(|e| format!("failed to read {}: {}", file, e))?;
+    // YAML is a superset of JSON, both formats go through the same parser
+    let catalog: Value = serde_yaml::from_str(&content)
+        .map_err(|e| format!("invalid catalog in {}: {}", file, e))?;
+
+    let listen: SocketAddr = matches
+        .value_of("listen")
+        .unwrap()
+        .parse()
+        .map_err(|e| format!("invalid --listen address: {}", e))?;
+    let delay = Duration::from_secs(*matches.get_one::<u64>("delay").unwrap());
+    let failure_rate = *matches.get_one::<f64>("failure_rate").unwrap();
+
+    if credentials.is_none() {
+        eprintln!("[WARN] no --username given, requests are not authenticated");
+    }
+
+    let url = mock::start(
+        MockBroker {
+            catalog,
+            delay,
+            failure_rate,
+            credentials,
+        },
+        listen,
+    )
+    .await?;
+    eprintln!("[INFO] mock broker listening on {}", url);
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
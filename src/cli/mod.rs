@@ -1,25 +1,88 @@
-use rocl::apis::catalog_api::catalog_get;
-use rocl::apis::configuration::Configuration;
-use rocl::models::Schemas;
+use crate::auth::AuthMode;
+use crate::client::{Client, ClientError, LastOperationRequest};
+use crate::http::{NetworkSettings, TlsSettings};
+use crate::models::ErrorOutput;
+use crate::operation::{wait_for_operation, WaitError, WaitOptions, POOL_INTERVAL};
+use crate::store;
+use crate::trace::Tracer;
+use chrono::{SubsecRound, Utc};
+use clap::ArgMatches;
+use futures_util::StreamExt;
+use rocl::models::{last_operation_resource::State, Plan, Service, ServiceBindingResponse};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod app;
+pub use app::app;
+
+pub mod output;
+use output::OutputFormat;
+
+mod apply;
+pub use apply::{apply, plan};
+
+mod batch;
+pub use batch::batch;
 
 mod catalog;
-pub use catalog::catalog;
+pub use catalog::{
+    catalog, catalog_cache_key, catalog_local, fetch_catalog, fetch_catalog_content,
+    parameters_schema, schema_parameters, validate_parameters, CatalogFilter, CatalogSettings,
+    SchemaAction,
+};
+use catalog::{find_plan, find_service};
+
+mod catalog_diff;
+pub use catalog_diff::compare_catalogs;
+use catalog_diff::diff;
+
+mod catalog_lint;
+use catalog_lint::lint;
+pub use catalog_lint::lint_catalog;
+
+mod connect;
+pub use connect::connect;
+
+mod conformance;
+pub use conformance::{conformance, CONFORMANCE_CHECKS};
+
+mod mock_broker;
+pub use mock_broker::mock_broker;
 
 mod service_instance;
-pub use service_instance::{deprovision, info, provision};
+pub use service_instance::{deprovision, describe, info, instances, parse_age, provision};
 
 mod service_binding;
-pub use service_binding::{bind, unbind};
+pub use service_binding::{bind, bindings, unbind};
 
-pub const USER_AGENT: &str = "ROCS v0.2";
-pub const DEFAULT_API_VERSION: &str = "2.15";
-pub const POOL_INTERVAL: u64 = 5;
+mod status;
+pub use status::status;
+
+/// Seconds a cached catalog is used before it is revalidated with the broker.
+pub const DEFAULT_CATALOG_TTL: u64 = 300;
+/// Exit status used when a broker request times out, same as timeout(1).
+pub const EXIT_TIMEOUT: i32 = 124;
+/// Exit status of `status` while the operation is still in progress.
+pub const EXIT_IN_PROGRESS: i32 = 2;
+pub const REQUEST_IDENTITY_HEADER: &str = "X-Broker-API-Request-Identity";
 
 pub struct Options {
-    pub json_output: bool,
+    pub output: OutputFormat,
     pub curl_output: bool,
     pub synchronous: bool,
+    pub request_id: String,
+    pub auth_mode: AuthMode,
+    pub tls: TlsSettings,
+    pub network: NetworkSettings,
+    /// Broker URL as configured, rocl's base path may point at the local proxy.
+    pub broker_url: String,
+    pub catalog: CatalogSettings,
+    /// Shared by the clients of every broker the command talks to.
+    pub tracer: Arc<Tracer>,
 }
 
 pub fn generate_curl_command(
@@ -29,6 +92,7 @@ pub fn generate_curl_command(
     synchronous: bool,
     sid: String,
     bid: String,
+    options: &Options,
 ) -> String {
     let sync_opt = if !synchronous {
         "?accepts_incomplete=true"
@@ -36,14 +100,14 @@ pub fn generate_curl_command(
         ""
     };
 
-    let request_body = if body != "" {
+    let request_body = if !body.is_empty() {
         format!(" \\\n-d '{}'", body)
     } else {
-        format!("")
+        String::new()
     };
 
     let path = match object.as_str() {
-        "catalog" => format!("catalog"),
+        "catalog" => "catalog".to_string(),
         "service_instance" => format!("service_instances/{}", sid),
         "service_binding" => format!("service_instances/{}/service_bindings/{}", sid, bid),
         _ => {
@@ -52,10 +116,19 @@ pub fn generate_curl_command(
         }
     };
 
+    let auth = match options.auth_mode {
+        AuthMode::Basic => "-u $ROCS_BROKER_USERNAME:$ROCS_BROKER_PASSWORD",
+        AuthMode::Bearer | AuthMode::OAuth2 => "-H \"Authorization: Bearer $ROCS_BROKER_TOKEN\"",
+    };
+
     let curl_command = format!(
-        "curl -H 'Content-type: application/json' -H 'X-Broker-API-Version: 2.16' -X {method} -u {userpass} {url}/{version}/{path}{sync_opt}{body}",
-        userpass = "$ROCS_BROKER_USERNAME:$ROCS_BROKER_PASSWORD",
+        "curl -H 'Content-type: application/json' -H 'X-Broker-API-Version: 2.16' -H '{identity_header}: {request_id}' -X {method} {auth}{tls}{network} {url}/{version}/{path}{sync_opt}{body}",
+        auth = auth,
+        tls = options.tls.curl_flags(),
+        network = options.network.curl_flags(),
         url = "$ROCS_BROKER_URL",
+        identity_header = REQUEST_IDENTITY_HEADER,
+        request_id = options.request_id,
         method = method,
         version = "v2",
         path = path,
@@ -63,40 +136,449 @@ pub fn generate_curl_command(
         body = request_body,
     );
 
-    String::from(curl_command)
+    curl_command
+}
+
+/// Whether the command only reads the local store, files or brokers of other
+/// profiles, it then runs without broker settings.
+pub fn is_local(matches: &ArgMatches) -> bool {
+    match matches.subcommand() {
+        Some(("instances" | "bindings", _)) => true,
+        Some(("catalog", m)) => match m.subcommand() {
+            Some(("lint", m)) => m.is_present("file"),
+            Some(("diff", m)) => m.is_present("from") && m.is_present("to"),
+            _ => false,
+        },
+        _ => false,
+    }
 }
 
-async fn find_service_plan_id(
-    config: Configuration,
-    service: String,
-    plan: String,
-) -> Result<(String, String, Schemas), &'static str> {
-    let catalog = catalog_get(&config, DEFAULT_API_VERSION)
-        .await
-        .expect("failed to fetch catalog");
+/// Returned by commands that already printed their result but must still exit
+/// with a non-zero status.
+#[derive(Debug)]
+pub struct ExitStatus {
+    pub code: i32,
+    pub reason: String,
+}
 
-    let mut service_id = String::from("");
-    let mut plan_id = String::from("");
-    let schemas: Schemas = Schemas::new();
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
 
-    'outer: for s in catalog.services.unwrap() {
-        if s.name == service {
-            service_id = s.id;
-            for p in s.plans {
-                if p.name == plan {
-                    plan_id = p.id;
-                    //schemas = *p.schemas.unwrap();
-                    break 'outer;
-                }
-            }
+impl Error for ExitStatus {}
+
+/// Wraps a rocl API error with the operation that failed, see [`ClientError`].
+pub fn broker_error<T: Debug>(context: &str, err: rocl::apis::Error<T>) -> Box<dyn Error> {
+    Box::new(ClientError::new(context, err))
+}
+
+pub fn error_output(err: &(dyn Error + 'static), request_id: &str) -> ErrorOutput {
+    let mut error_output = ErrorOutput::new(err.to_string(), request_id.to_string());
+
+    if let Some(ClientError::Broker(broker_error)) = err.downcast_ref::<ClientError>() {
+        error_output.status = Some(broker_error.status);
+        error_output.broker_error = broker_error.body();
+    }
+
+    error_output
+}
+
+/// Whether a command failed because a broker request timed out.
+pub fn is_timeout(err: &(dyn Error + 'static)) -> bool {
+    matches!(
+        err.downcast_ref::<ClientError>(),
+        Some(ClientError::Timeout(_))
+    )
+}
+
+/// Commands recording their own operation history, see [`record_operation`].
+const SELF_RECORDING: &[&str] = &["provision", "bind"];
+
+/// Whether the caller must record the command's operation history, commands
+/// generating instance or binding IDs record it themselves.
+pub fn records_history(command: &str) -> bool {
+    !SELF_RECORDING.contains(&command)
+}
+
+/// Adds a command to the operation history, failing to do so is only reported.
+pub fn record_operation(
+    request_id: &str,
+    command: &str,
+    instance_id: &str,
+    binding_id: &str,
+    result: &Result<(), Box<dyn Error>>,
+) {
+    if let Err(e) = store::operation_save(
+        request_id.to_owned(),
+        command.to_owned(),
+        instance_id.to_owned(),
+        binding_id.to_owned(),
+        result.as_ref().err().map(|e| e.to_string()),
+    ) {
+        eprintln!("[WARN] failed to record operation history: {}", e);
+    }
+}
+
+/// The local store only mirrors broker state, so failing to read or update it
+/// is reported without failing the command.
+pub fn store_warning<T>(result: Result<T, Box<dyn Error>>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("[WARN] local store: {}", e);
+            None
+        }
+    }
+}
+
+/// Polls the last operation for `--wait`, logging description changes, until
+/// it succeeded or failed.
+async fn wait(request: LastOperationRequest<'_>) -> Result<State, Box<dyn Error>> {
+    let options = WaitOptions {
+        interval: Duration::from_secs(POOL_INTERVAL),
+        backoff: 1.0,
+        ..WaitOptions::default()
+    };
+
+    let mut updates = wait_for_operation(request, options);
+    let mut state = None;
+    while let Some(update) = updates.next().await {
+        let last_op = match update {
+            Ok(last_op) => last_op,
+            // keep the broker error so the error output can report its status
+            Err(WaitError::Client(e)) => return Err(Box::new(e)),
+            Err(e) => return Err(Box::new(e)),
+        };
+        if let Some(description) = &last_op.description {
+            eprintln!("[INFO] {}", description);
         }
+        state = Some(last_op.state);
     }
 
-    if plan_id == "" || service_id == "" {
-        return Err("plan or service not found");
+    state.ok_or_else(|| "stopped waiting for the operation".into())
+}
+
+/// State recorded in the local store when an operation was accepted.
+fn state_name(accepts_incomplete: bool) -> &'static str {
+    match accepts_incomplete {
+        true => "in progress",
+        false => "succeeded",
+    }
+}
+
+/// Waits for an operation and records its final state with `save`.
+async fn finish<F>(
+    request: LastOperationRequest<'_>,
+    operation: &str,
+    save: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&str) -> Result<(), Box<dyn Error>>,
+{
+    let state = wait(request).await?;
+    store_warning(save(output::state_name(state)));
+
+    match state {
+        State::Failed => Err(format!("{} failed", operation).into()),
+        _ => Ok(()),
+    }
+}
+
+/// Waits for an unbinding or deprovisioning, brokers answer 410 Gone once
+/// the resource is deleted.
+async fn finish_deletion(
+    request: LastOperationRequest<'_>,
+    operation: &str,
+) -> Result<(), Box<dyn Error>> {
+    match wait(request).await {
+        Ok(State::Failed) => Err(format!("{} failed", operation).into()),
+        Ok(_) => Ok(()),
+        Err(e) if is_gone(&*e) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the broker answered 410 Gone, the resource does not exist (anymore).
+fn is_gone(e: &(dyn Error + 'static)) -> bool {
+    e.downcast_ref::<ClientError>()
+        .and_then(ClientError::status)
+        == Some(410)
+}
+
+/// Service and plan IDs recorded for the instance at provision time. The OSB
+/// spec requires them on deprovision, bind and unbind requests, instances
+/// the local store has no record of get placeholders.
+fn stored_ids(instance_id: &str) -> (String, String) {
+    match store_warning(store::instance_get(instance_id)).flatten() {
+        Some(instance) => (instance.service_id, instance.plan_id),
+        None => ("ignore".to_string(), "ignore".to_string()),
+    }
+}
+
+/// Instance created with [`provision_instance`].
+struct NewInstance<'a> {
+    instance_id: String,
+    service: Service,
+    plan: Plan,
+    parameters: Value,
+    context: Option<&'a Value>,
+    /// Name recorded in the local store, see [`store::Instance::name`].
+    name: Option<String>,
+    broker_url: &'a str,
+}
+
+/// Provisions and records the instance, then waits for asynchronous
+/// provisionings with `wait`.
+async fn provision_instance(
+    client: &Client,
+    instance: NewInstance<'_>,
+    accepts_incomplete: bool,
+    wait: bool,
+) -> Result<(), Box<dyn Error>> {
+    let NewInstance {
+        instance_id,
+        service,
+        plan,
+        parameters,
+        context,
+        name,
+        broker_url,
+    } = instance;
+
+    let mut request = client
+        .provision(&instance_id, &service.id, &plan.id)
+        .parameters(parameters.clone())
+        .accepts_incomplete(accepts_incomplete);
+    if let Some(context) = context {
+        request = request.context(context.clone());
+    }
+    request.send().await?;
+
+    let now = Utc::now().naive_utc().trunc_subsecs(0);
+    store_warning(store::instance_save(&store::Instance {
+        instance_id: instance_id.clone(),
+        service_id: service.id.clone(),
+        plan_id: plan.id.clone(),
+        service_name: service.name,
+        plan_name: plan.name,
+        parameters: parameters.to_string(),
+        state: state_name(accepts_incomplete).into(),
+        created_at: now,
+        updated_at: now,
+        name,
+        broker_url: broker_url.into(),
+    }));
+
+    if accepts_incomplete && wait {
+        let request = client
+            .last_operation(&instance_id)
+            .service_id(&service.id)
+            .plan_id(&plan.id);
+        finish(request, "provisioning", |state| {
+            store::instance_update_state(instance_id.clone(), state.into())
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Updates, waits for asynchronous updates, then records the new plan and
+/// parameters. The stored instance is left as it was if the update fails.
+async fn update_instance(
+    client: &Client,
+    instance_id: &str,
+    service: &Service,
+    plan: Option<Plan>,
+    parameters: Option<Value>,
+    context: Option<&Value>,
+    accepts_incomplete: bool,
+) -> Result<(), Box<dyn Error>> {
+    let stored = store::instance_get(instance_id)?;
+
+    let mut request = client
+        .update(instance_id, &service.id)
+        .accepts_incomplete(accepts_incomplete);
+    if let Some(plan) = &plan {
+        request = request.plan_id(&plan.id);
+    }
+    if let Some(parameters) = &parameters {
+        request = request.parameters(parameters.clone());
+    }
+    if let Some(context) = context {
+        request = request.context(context.clone());
+    }
+    let response = request.send().await?;
+
+    if accepts_incomplete {
+        let plan_id = match (&plan, &stored) {
+            (Some(plan), _) => Some(plan.id.as_str()),
+            (None, Some(stored)) => Some(stored.plan_id.as_str()),
+            (None, None) => None,
+        };
+        let mut request = client.last_operation(instance_id).service_id(&service.id);
+        if let Some(plan_id) = plan_id {
+            request = request.plan_id(plan_id);
+        }
+        if let Some(operation) = &response.operation {
+            request = request.operation(operation);
+        }
+        // a failed update keeps the instance, its stored state is not changed
+        finish(request, "update", |_| Ok(())).await?;
+    }
+
+    if let Some(mut stored) = stored {
+        if let Some(plan) = plan {
+            stored.plan_id = plan.id;
+            stored.plan_name = plan.name;
+        }
+        if let Some(parameters) = parameters {
+            stored.parameters = parameters.to_string();
+        }
+        stored.state = output::state_name(State::Succeeded).into();
+        stored.updated_at = Utc::now().naive_utc().trunc_subsecs(0);
+        store_warning(store::instance_save(&stored));
+    }
+
+    Ok(())
+}
+
+/// Binding created with [`create_binding`].
+struct NewBinding<'a> {
+    instance_id: &'a str,
+    binding_id: &'a str,
+    parameters: Value,
+    context: Option<&'a Value>,
+    /// Name recorded in the local store, see [`store::Binding::name`].
+    name: Option<String>,
+}
+
+/// Binds and records the binding, then waits for asynchronous bindings with
+/// `wait`. Returns the broker's answer to the bind request.
+async fn create_binding(
+    client: &Client,
+    binding: NewBinding<'_>,
+    accepts_incomplete: bool,
+    wait: bool,
+) -> Result<ServiceBindingResponse, Box<dyn Error>> {
+    let NewBinding {
+        instance_id,
+        binding_id,
+        parameters,
+        context,
+        name,
+    } = binding;
+
+    let (service_id, plan_id) = stored_ids(instance_id);
+    let mut request = client
+        .bind(instance_id, binding_id, &service_id, &plan_id)
+        .parameters(parameters)
+        .accepts_incomplete(accepts_incomplete);
+    if let Some(context) = context {
+        request = request.context(context.clone());
+    }
+    let response = request.send().await?;
+
+    store_warning(store::binding_save(
+        instance_id.to_string(),
+        binding_id.to_string(),
+        serde_json::to_string(&response).unwrap_or_default(),
+        state_name(accepts_incomplete).into(),
+        name,
+    ));
+
+    if accepts_incomplete && wait {
+        let request = client.last_operation(instance_id).binding(binding_id);
+        finish(request, "binding", |state| {
+            store::binding_update_state(binding_id.to_string(), state.into())
+        })
+        .await?;
+    }
+
+    Ok(response)
+}
+
+/// Unbinds, waits for asynchronous unbindings, then forgets the binding.
+async fn remove_binding(
+    client: &Client,
+    instance_id: &str,
+    binding_id: &str,
+    accepts_incomplete: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (service_id, plan_id) = stored_ids(instance_id);
+    client
+        .unbind(instance_id, binding_id, &service_id, &plan_id)
+        .accepts_incomplete(accepts_incomplete)
+        .send()
+        .await?;
+
+    if accepts_incomplete {
+        let request = client.last_operation(instance_id).binding(binding_id);
+        finish_deletion(request, "unbinding").await?;
+    }
+    store_warning(store::binding_delete(binding_id.to_string()));
+
+    Ok(())
+}
+
+/// Deprovisions, waits for asynchronous deprovisionings, then forgets the
+/// instance and its bindings.
+async fn remove_instance(
+    client: &Client,
+    instance_id: &str,
+    accepts_incomplete: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (service_id, plan_id) = stored_ids(instance_id);
+    client
+        .deprovision(instance_id, &service_id, &plan_id)
+        .accepts_incomplete(accepts_incomplete)
+        .send()
+        .await?;
+
+    if accepts_incomplete {
+        let request = client
+            .last_operation(instance_id)
+            .service_id(&service_id)
+            .plan_id(&plan_id);
+        finish_deletion(request, "deprovisioning").await?;
+    }
+    forget_instance(instance_id);
+
+    Ok(())
+}
+
+/// Removes the instance and its bindings from the local store.
+fn forget_instance(instance_id: &str) {
+    store_warning(
+        store::binding_list(Some(instance_id.to_string())).and_then(|bindings| {
+            bindings
+                .into_iter()
+                .try_for_each(|b| store::binding_delete(b.binding_id))
+        }),
+    );
+    store_warning(store::instance_delete(instance_id.to_string()));
+}
+
+/// The service and plan with these names.
+async fn find_service_plan(
+    client: &Client,
+    options: &Options,
+    service: &str,
+    plan: &str,
+) -> Result<(Service, Plan), Box<dyn Error>> {
+    let services = fetch_catalog(client, options).await?;
+
+    for s in services {
+        if s.name == service {
+            if let Some(p) = s.plans.iter().find(|p| p.name == plan).cloned() {
+                return Ok((s, p));
+            }
+        }
     }
 
-    Ok((service_id, plan_id, schemas))
+    Err("plan or service not found".into())
 }
 
 pub fn parse_parameters(
@@ -104,18 +586,15 @@ pub fn parse_parameters(
 ) -> Result<HashMap<String, String>, &'static str> {
     let mut parsed_params: HashMap<String, String> = HashMap::new();
 
-    match params {
-        Some(param) => {
-            for kv in param {
-                let keyvalue: Vec<&str> = kv.splitn(2, '=').collect();
-                if keyvalue.len() < 2 {
-                    println!("{} does not match format key=value, ignoring", kv);
-                    continue;
-                }
-                parsed_params.insert(keyvalue[0].to_string(), keyvalue[1].to_string());
+    if let Some(param) = params {
+        for kv in param {
+            let keyvalue: Vec<&str> = kv.splitn(2, '=').collect();
+            if keyvalue.len() < 2 {
+                println!("{} does not match format key=value, ignoring", kv);
+                continue;
             }
+            parsed_params.insert(keyvalue[0].to_string(), keyvalue[1].to_string());
         }
-        None => {}
     }
     Ok(parsed_params)
 }
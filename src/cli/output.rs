@@ -0,0 +1,840 @@
+use crate::cli::{parameters_schema, SchemaAction};
+use crate::models::{
+    CatalogDiffOutput, CatalogLintOutput, CatalogOutput, ChangeSetOutput, ConformanceOutput,
+    ErrorOutput, LastOperationOutput, Parameter, ParametersOutput, PlanOutput,
+    ServiceBindingListOutput, ServiceBindingOutput, ServiceInstanceDescriptionOutput,
+    ServiceInstanceListOutput, ServiceInstanceOutput, ServiceOutput,
+};
+use prettytable::{Cell, Row, Table};
+use rocl::models::{
+    last_operation_resource::State, MaintenanceInfo, ServiceBindingResource,
+    ServiceInstanceResource,
+};
+use serde::Serialize;
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::error::Error;
+use std::fs;
+use std::str::FromStr;
+use tinytemplate::TinyTemplate;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    /// Table with every available column.
+    Wide,
+    Json,
+    Yaml,
+    /// Prints the values matched by a JSONPath expression, one per line.
+    JsonPath(String),
+    /// Renders a TinyTemplate file with the JSON document as context.
+    Template(String),
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some(("jsonpath", expression)) => Ok(OutputFormat::JsonPath(expression.to_string())),
+            Some(("template", file)) => Ok(OutputFormat::Template(file.to_string())),
+            _ => match s {
+                "table" => Ok(OutputFormat::Table),
+                "wide" => Ok(OutputFormat::Wide),
+                "json" => Ok(OutputFormat::Json),
+                "yaml" => Ok(OutputFormat::Yaml),
+                _ => Err(format!(
+                    "invalid output format {}, expected table, wide, json, yaml, jsonpath=<expr> or template=<file>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Values that can be printed as a table in addition to the serialized formats.
+pub trait Tabular: Serialize {
+    fn headers(&self, wide: bool) -> Vec<&'static str>;
+    fn rows(&self, wide: bool) -> Vec<Vec<String>>;
+}
+
+pub fn print<T: Tabular>(value: &T, format: &OutputFormat) -> Result<(), Box<dyn Error>> {
+    print!("{}", render(value, format)?);
+    Ok(())
+}
+
+/// Text [`print`] writes to stdout.
+pub fn render<T: Tabular>(value: &T, format: &OutputFormat) -> Result<String, Box<dyn Error>> {
+    let output = match format {
+        OutputFormat::Table | OutputFormat::Wide => {
+            let wide = *format == OutputFormat::Wide;
+            let mut table = Table::new();
+
+            table.set_titles(Row::new(
+                value.headers(wide).into_iter().map(Cell::new).collect(),
+            ));
+            for row in value.rows(wide) {
+                table.add_row(Row::new(row.iter().map(|c| Cell::new(c)).collect()));
+            }
+            table.to_string()
+        }
+        OutputFormat::Json => format!("{}\n", serde_json::to_string(value)?),
+        OutputFormat::Yaml => serde_yaml::to_string(value)?,
+        OutputFormat::JsonPath(expression) => {
+            let path = JsonPath::parse(expression)
+                .map_err(|e| format!("invalid JSONPath {}: {}", expression, e))?;
+            let document = serde_json::to_value(value)?;
+
+            let mut output = String::new();
+            for node in path.query(&document).all() {
+                match node {
+                    Value::String(s) => output.push_str(s),
+                    other => output.push_str(&serde_json::to_string(other)?),
+                }
+                output.push('\n');
+            }
+            output
+        }
+        OutputFormat::Template(file) => {
+            let template =
+                fs::read_to_string(file).map_err(|e| format!("failed to read {}: {}", file, e))?;
+            let mut tt = TinyTemplate::new();
+            tt.set_default_formatter(&tinytemplate::format_unescaped);
+            tt.add_template("output", &template)?;
+            tt.render("output", value)?
+        }
+    };
+
+    Ok(output)
+}
+
+fn text(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+fn pretty<T: Serialize>(value: &Option<T>) -> String {
+    match value {
+        Some(v) => serde_json::to_string_pretty(v).unwrap_or_default(),
+        None => String::new(),
+    }
+}
+
+impl Tabular for ServiceInstanceOutput {
+    fn headers(&self, wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["ID", "Service ID", "Plan ID", "Dashboard"];
+        if wide {
+            headers.extend(["Maintenance Version", "Parameters"]);
+        }
+        headers
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        let resource = self
+            .service_instance_resource
+            .clone()
+            .unwrap_or_else(ServiceInstanceResource::new);
+
+        let mut row = vec![
+            text(&self.service_instance_id),
+            text(&resource.service_id),
+            text(&resource.plan_id),
+            text(&resource.dashboard_url),
+        ];
+        if wide {
+            row.push(
+                resource
+                    .maintenance_info
+                    .map(|m| m.version)
+                    .unwrap_or_default(),
+            );
+            row.push(pretty(&resource.parameters));
+        }
+        vec![row]
+    }
+}
+
+impl Tabular for ServiceBindingOutput {
+    fn headers(&self, wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["Instance ID", "Binding ID", "Credentials"];
+        if wide {
+            headers.extend([
+                "Syslog Drain URL",
+                "Route Service URL",
+                "Endpoints",
+                "Volume Mounts",
+            ]);
+        }
+        headers
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        let resource = self
+            .service_binding_resource
+            .clone()
+            .unwrap_or_else(ServiceBindingResource::new);
+
+        let mut row = vec![
+            text(&self.service_instance_id),
+            text(&self.service_binding_id),
+            pretty(&resource.credentials),
+        ];
+        if wide {
+            row.push(text(&resource.syslog_drain_url));
+            row.push(text(&resource.route_service_url));
+            row.push(pretty(&resource.endpoints));
+            row.push(pretty(&resource.volume_mounts));
+        }
+        vec![row]
+    }
+}
+
+impl Tabular for CatalogOutput {
+    fn headers(&self, wide: bool) -> Vec<&'static str> {
+        match wide {
+            false => vec!["Service", "Description", "Plans"],
+            true => vec!["Service", "ID", "Description", "Bindable", "Tags", "Plans"],
+        }
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        self.services
+            .iter()
+            .map(|s| match wide {
+                false => vec![
+                    s.name.clone(),
+                    s.description.clone(),
+                    s.plans
+                        .iter()
+                        .map(|p| p.name.clone())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ],
+                true => vec![
+                    s.name.clone(),
+                    s.id.clone(),
+                    s.description.clone(),
+                    s.bindable.to_string(),
+                    s.tags.clone().unwrap_or_default().join(", "),
+                    s.plans
+                        .iter()
+                        .map(|p| format!("{} ({})", p.name, p.id))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ],
+            })
+            .collect()
+    }
+}
+
+impl Tabular for ServiceInstanceListOutput {
+    fn headers(&self, wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["ID", "Service", "Plan", "State", "Created", "Bindings"];
+        if wide {
+            headers.extend(["Service ID", "Plan ID", "Updated"]);
+        }
+        headers
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        self.service_instances
+            .iter()
+            .map(|i| {
+                let mut row = vec![
+                    i.service_instance_id.clone(),
+                    i.service_name.clone(),
+                    i.plan_name.clone(),
+                    i.state.clone(),
+                    i.created_at.to_string(),
+                    i.binding_count.to_string(),
+                ];
+                if wide {
+                    row.push(i.service_id.clone());
+                    row.push(i.plan_id.clone());
+                    row.push(i.updated_at.to_string());
+                }
+                row
+            })
+            .collect()
+    }
+}
+
+impl Tabular for ServiceBindingListOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec![
+            "Binding ID",
+            "Instance ID",
+            "Service",
+            "Plan",
+            "State",
+            "Created",
+        ]
+    }
+
+    fn rows(&self, _: bool) -> Vec<Vec<String>> {
+        self.service_bindings
+            .iter()
+            .map(|b| {
+                vec![
+                    b.service_binding_id.clone(),
+                    b.service_instance_id.clone(),
+                    b.service_name.clone(),
+                    b.plan_name.clone(),
+                    b.state.clone(),
+                    b.created_at.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Tabular for ServiceInstanceDescriptionOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec!["Field", "Value"]
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        let last_operation = match &self.last_operation {
+            Some(op) => match &op.description {
+                Some(description) => format!("{}: {}", state_name(op.state), description),
+                None => state_name(op.state).to_string(),
+            },
+            None => String::new(),
+        };
+        let bindings = self
+            .service_bindings
+            .iter()
+            .map(|b| format!("{} ({})", b.service_binding_id, b.state))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut rows = vec![
+            vec!["ID".to_string(), self.service_instance_id.clone()],
+            vec!["Service".to_string(), text(&self.service_name)],
+            vec!["Plan".to_string(), text(&self.plan_name)],
+            vec!["Dashboard".to_string(), text(&self.dashboard_url)],
+            vec![
+                "Maintenance".to_string(),
+                maintenance_text(&self.maintenance_info),
+            ],
+            vec![
+                "Available Maintenance".to_string(),
+                maintenance_text(&self.plan_maintenance_info),
+            ],
+            vec!["Last Operation".to_string(), last_operation],
+            vec![
+                "Created".to_string(),
+                self.created_at.map(|c| c.to_string()).unwrap_or_default(),
+            ],
+            vec!["Parameters".to_string(), pretty(&self.parameters)],
+            vec!["Bindings".to_string(), bindings],
+        ];
+        if wide {
+            rows.insert(2, vec!["Service ID".to_string(), text(&self.service_id)]);
+            rows.insert(
+                3,
+                vec![
+                    "Service Description".to_string(),
+                    text(&self.service_description),
+                ],
+            );
+            rows.insert(5, vec!["Plan ID".to_string(), text(&self.plan_id)]);
+            rows.insert(
+                6,
+                vec!["Plan Description".to_string(), text(&self.plan_description)],
+            );
+            rows.push(vec![
+                "Requested Parameters".to_string(),
+                pretty(&self.requested_parameters),
+            ]);
+        }
+        rows
+    }
+}
+
+fn flag(value: Option<bool>) -> String {
+    value.map(|b| b.to_string()).unwrap_or_default()
+}
+
+// rocl keeps the metadata conventions (displayName, costs, bullets) as free-form JSON
+fn metadata_text(metadata: &Option<Value>, field: &str) -> String {
+    match metadata.as_ref().and_then(|m| m.get(field)) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|i| match i {
+                Value::String(s) => format!("- {}", s),
+                other => format!("- {}", other),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+fn metadata_costs(metadata: &Option<Value>) -> String {
+    let costs = match metadata.as_ref().and_then(|m| m.get("costs")) {
+        Some(Value::Array(costs)) => costs,
+        _ => return String::new(),
+    };
+
+    costs
+        .iter()
+        .map(|cost| {
+            let amounts = match cost.get("amount") {
+                Some(Value::Object(amounts)) => amounts
+                    .iter()
+                    .map(|(currency, amount)| format!("{} {}", amount, currency.to_uppercase()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => String::new(),
+            };
+            match cost.get("unit").and_then(Value::as_str) {
+                Some(unit) => format!("{} / {}", amounts, unit),
+                None => amounts,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn maintenance_text(maintenance_info: &Option<MaintenanceInfo>) -> String {
+    match maintenance_info {
+        Some(MaintenanceInfo {
+            version,
+            description: Some(description),
+        }) => format!("{} ({})", version, description),
+        Some(m) => m.version.clone(),
+        None => String::new(),
+    }
+}
+
+impl Tabular for ServiceOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec!["Field", "Value"]
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        let s = &self.service;
+        let requires = s
+            .requires
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|r| serde_json::to_value(r).ok())
+            .filter_map(|r| r.as_str().map(String::from))
+            .collect::<Vec<_>>()
+            .join(", ");
+        // the secret is only included in the serialized formats
+        let dashboard_client = match &s.dashboard_client {
+            Some(client) => format!(
+                "id: {}\nredirect_uri: {}",
+                text(&client.id),
+                text(&client.redirect_uri)
+            ),
+            None => String::new(),
+        };
+        let plans = s
+            .plans
+            .iter()
+            .map(|p| match p.free {
+                Some(false) => format!("{} ({})", p.name, p.id),
+                _ => format!("{} ({}, free)", p.name, p.id),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut rows = vec![
+            vec!["Name".to_string(), s.name.clone()],
+            vec!["ID".to_string(), s.id.clone()],
+            vec![
+                "Display Name".to_string(),
+                metadata_text(&s.metadata, "displayName"),
+            ],
+            vec!["Description".to_string(), s.description.clone()],
+            vec![
+                "Tags".to_string(),
+                s.tags.clone().unwrap_or_default().join(", "),
+            ],
+            vec!["Requires".to_string(), requires],
+            vec!["Bindable".to_string(), s.bindable.to_string()],
+            vec!["Plan Updateable".to_string(), flag(s.plan_updateable)],
+            vec!["Binding Rotatable".to_string(), flag(s.binding_rotatable)],
+            vec!["Dashboard Client".to_string(), dashboard_client],
+            vec!["Plans".to_string(), plans],
+        ];
+        if wide {
+            rows.push(vec!["Metadata".to_string(), pretty(&s.metadata)]);
+        }
+        rows
+    }
+}
+
+impl Tabular for PlanOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec!["Field", "Value"]
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        let p = &self.plan;
+        let schema = |action| pretty(&parameters_schema(p, action));
+
+        let mut rows = vec![
+            vec![
+                "Service".to_string(),
+                format!("{} ({})", self.service_name, self.service_id),
+            ],
+            vec!["Name".to_string(), p.name.clone()],
+            vec!["ID".to_string(), p.id.clone()],
+            vec![
+                "Display Name".to_string(),
+                metadata_text(&p.metadata, "displayName"),
+            ],
+            vec!["Description".to_string(), p.description.clone()],
+            // plans are free unless stated otherwise
+            vec!["Free".to_string(), p.free.unwrap_or(true).to_string()],
+            vec!["Bindable".to_string(), self.bindable.to_string()],
+            vec![
+                "Maintenance".to_string(),
+                maintenance_text(&p.maintenance_info.clone().map(|m| *m)),
+            ],
+            vec!["Costs".to_string(), metadata_costs(&p.metadata)],
+            vec!["Bullets".to_string(), metadata_text(&p.metadata, "bullets")],
+            vec![
+                "Create Parameters".to_string(),
+                schema(SchemaAction::Create),
+            ],
+            vec![
+                "Update Parameters".to_string(),
+                schema(SchemaAction::Update),
+            ],
+            vec!["Bind Parameters".to_string(), schema(SchemaAction::Bind)],
+        ];
+        if wide {
+            rows.push(vec!["Metadata".to_string(), pretty(&p.metadata)]);
+        }
+        rows
+    }
+}
+
+// enum values, ranges and patterns in a single human readable line
+fn parameter_constraints(p: &Parameter) -> String {
+    let mut constraints = Vec::new();
+    let value = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    if let Some(values) = &p._enum {
+        constraints.push(format!(
+            "one of: {}",
+            values.iter().map(value).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    match (&p.minimum, &p.maximum) {
+        (Some(min), Some(max)) => constraints.push(format!("{}..{}", min, max)),
+        (Some(min), None) => constraints.push(format!(">= {}", min)),
+        (None, Some(max)) => constraints.push(format!("<= {}", max)),
+        (None, None) => {}
+    }
+    match (p.min_length, p.max_length) {
+        (Some(min), Some(max)) => constraints.push(format!("length {}..{}", min, max)),
+        (Some(min), None) => constraints.push(format!("length >= {}", min)),
+        (None, Some(max)) => constraints.push(format!("length <= {}", max)),
+        (None, None) => {}
+    }
+    if let Some(pattern) = &p.pattern {
+        constraints.push(format!("pattern {}", pattern));
+    }
+
+    constraints.join("; ")
+}
+
+impl Tabular for ParametersOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec![
+            "Name",
+            "Type",
+            "Required",
+            "Default",
+            "Constraints",
+            "Description",
+        ]
+    }
+
+    fn rows(&self, _: bool) -> Vec<Vec<String>> {
+        self.parameters
+            .iter()
+            .map(|p| {
+                vec![
+                    p.name.clone(),
+                    text(&p._type),
+                    p.required.to_string(),
+                    p.default
+                        .as_ref()
+                        .map(|d| d.to_string())
+                        .unwrap_or_default(),
+                    parameter_constraints(p),
+                    text(&p.description),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Tabular for CatalogDiffOutput {
+    fn headers(&self, wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["Change", "Object", "Service", "Plan", "Field"];
+        if wide {
+            headers.extend(["From", "To"]);
+        }
+        headers.push("Breaking");
+        headers
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        let value = |v: &Option<Value>| match v {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+
+        self.changes
+            .iter()
+            .map(|c| {
+                let mut row = vec![
+                    c.change.clone(),
+                    c.object.clone(),
+                    c.service_name.clone(),
+                    text(&c.plan_name),
+                    text(&c.field),
+                ];
+                if wide {
+                    row.extend([value(&c.from), value(&c.to)]);
+                }
+                row.push(c.breaking.to_string());
+                row
+            })
+            .collect()
+    }
+}
+
+impl Tabular for CatalogLintOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec!["Severity", "Rule", "Path", "Message"]
+    }
+
+    fn rows(&self, _: bool) -> Vec<Vec<String>> {
+        self.findings
+            .iter()
+            .map(|f| {
+                vec![
+                    f.severity.clone(),
+                    f.rule.clone(),
+                    f.path.clone(),
+                    f.message.clone(),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Tabular for ChangeSetOutput {
+    fn headers(&self, wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["Action", "Name", "Instance", "Service", "Plan"];
+        if wide {
+            headers.extend(["Fields", "Instance ID", "Binding ID"]);
+        }
+        if self.applied {
+            headers.extend(["State", "Error"]);
+        }
+        headers
+    }
+
+    fn rows(&self, wide: bool) -> Vec<Vec<String>> {
+        self.changes
+            .iter()
+            .map(|c| {
+                let mut row = vec![
+                    c.action.clone(),
+                    c.name.clone(),
+                    text(&c.instance),
+                    text(&c.service),
+                    text(&c.plan),
+                ];
+                if wide {
+                    row.extend([
+                        c.fields.join(", "),
+                        text(&c.service_instance_id),
+                        text(&c.service_binding_id),
+                    ]);
+                }
+                if self.applied {
+                    row.extend([text(&c.state), text(&c.error)]);
+                }
+                row
+            })
+            .collect()
+    }
+}
+
+impl Tabular for ConformanceOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec!["Check", "Status", "Duration", "Message"]
+    }
+
+    fn rows(&self, _: bool) -> Vec<Vec<String>> {
+        self.checks
+            .iter()
+            .map(|c| {
+                vec![
+                    c.name.clone(),
+                    c.status.clone(),
+                    format!("{}ms", c.duration_ms),
+                    text(&c.message),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// JUnit XML report of the conformance checks, one test case per check.
+pub fn junit(report: &ConformanceOutput) -> String {
+    // XML 1.0 has no representation for the other control characters, they are dropped
+    let escape = |s: &str| {
+        s.chars()
+            .filter_map(|c| match c {
+                '&' => Some("&amp;".to_string()),
+                '<' => Some("&lt;".to_string()),
+                '>' => Some("&gt;".to_string()),
+                '"' => Some("&quot;".to_string()),
+                '\t' | '\n' | '\r' => Some(format!("&#{};", c as u32)),
+                c if c < ' ' => None,
+                c => Some(c.to_string()),
+            })
+            .collect::<String>()
+    };
+    let seconds = |ms: u64| format!("{:.3}", ms as f64 / 1000.0);
+    let total: u64 = report.checks.iter().map(|c| c.duration_ms).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml += &format!(
+        "<testsuites name=\"rocs conformance\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+        report.checks.len(),
+        report.failed,
+        report.skipped,
+        seconds(total)
+    );
+    xml += &format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+        escape(&report.broker_url),
+        report.checks.len(),
+        report.failed,
+        report.skipped,
+        seconds(total)
+    );
+
+    for check in &report.checks {
+        let testcase = format!(
+            "    <testcase classname=\"rocs.conformance\" name=\"{}\" time=\"{}\"",
+            escape(&check.name),
+            seconds(check.duration_ms)
+        );
+        let message = escape(check.message.as_deref().unwrap_or_default());
+        xml += &match check.status.as_str() {
+            "failed" => format!(
+                "{}>\n      <failure message=\"{}\"/>\n    </testcase>\n",
+                testcase, message
+            ),
+            "skipped" => format!(
+                "{}>\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                testcase, message
+            ),
+            _ => format!("{}/>\n", testcase),
+        };
+    }
+
+    xml += "  </testsuite>\n</testsuites>\n";
+    xml
+}
+
+/// Markdown reference of the parameters, suitable for documentation pages.
+pub fn markdown(params: &ParametersOutput) -> String {
+    let cell = |s: String| s.replace('|', "\\|").replace('\n', " ");
+    let mut doc = format!(
+        "# {} {} {} parameters\n\n",
+        params.service_name, params.plan_name, params.action
+    );
+
+    if params.parameters.is_empty() {
+        doc.push_str("This plan accepts no documented parameters.\n");
+        return doc;
+    }
+
+    doc.push_str("| Name | Type | Required | Default | Constraints | Description |\n");
+    doc.push_str("|------|------|----------|---------|-------------|-------------|\n");
+    for p in &params.parameters {
+        doc.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} | {} |\n",
+            p.name,
+            cell(text(&p._type)),
+            if p.required { "yes" } else { "no" },
+            p.default
+                .as_ref()
+                .map(|d| format!("`{}`", d))
+                .unwrap_or_default(),
+            cell(parameter_constraints(p)),
+            cell(text(&p.description)),
+        ));
+    }
+
+    doc
+}
+
+pub fn state_name(state: State) -> &'static str {
+    match state {
+        State::InProgress => "in progress",
+        State::Succeeded => "succeeded",
+        State::Failed => "failed",
+    }
+}
+
+impl Tabular for LastOperationOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec![
+            "Instance ID",
+            "Binding ID",
+            "State",
+            "Description",
+            "Instance Usable",
+            "Update Repeatable",
+        ]
+    }
+
+    fn rows(&self, _: bool) -> Vec<Vec<String>> {
+        let op = &self.last_operation;
+
+        vec![vec![
+            self.service_instance_id.clone(),
+            text(&self.service_binding_id),
+            state_name(op.state).to_string(),
+            text(&op.description),
+            op.instance_usable
+                .map(|b| b.to_string())
+                .unwrap_or_default(),
+            op.update_repeatable
+                .map(|b| b.to_string())
+                .unwrap_or_default(),
+        ]]
+    }
+}
+
+impl Tabular for ErrorOutput {
+    fn headers(&self, _: bool) -> Vec<&'static str> {
+        vec!["Error", "Request ID"]
+    }
+
+    fn rows(&self, _: bool) -> Vec<Vec<String>> {
+        vec![vec![self.message.clone(), self.request_id.clone()]]
+    }
+}
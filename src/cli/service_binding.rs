@@ -1,191 +1,131 @@
 use crate::cli::{
-    generate_curl_command, parse_parameters, Options, DEFAULT_API_VERSION, POOL_INTERVAL,
-    USER_AGENT,
+    create_binding, generate_curl_command, output, parse_parameters, record_operation,
+    remove_binding, stored_ids, NewBinding, Options,
 };
-use crate::models::ServiceBindingOutput;
+use crate::client::Client;
+use crate::models::{ServiceBindingListOutput, ServiceBindingOutput, ServiceBindingSummary};
+use crate::store;
 
 use clap::ArgMatches;
-use prettytable::Table;
-use rocl::{
-    apis::{
-        configuration::Configuration,
-        service_bindings_api::{
-            service_binding_binding, service_binding_get, service_binding_last_operation_get,
-            service_binding_unbinding,
-        },
-    },
-    models::{last_operation_resource::State, ServiceBindingRequest},
-};
+use rocl::models::{ServiceBindingResource, ServiceBindingResponse};
 use serde_json::json;
-use std::{collections::HashMap, error::Error};
-use std::{thread, time};
+use std::error::Error;
 use uuid::Uuid;
 
 pub async fn bind(
     matches: &ArgMatches,
-    config: Configuration,
+    client: Client,
     options: Options,
 ) -> Result<(), Box<dyn Error>> {
-    let mut binding_id = Uuid::new_v4().as_hyphenated().to_string();
-    let instance_id = matches.value_of("instance").unwrap().to_string();
-
     // bindings
     // if binding id is present, just fetch the id
-    if matches.is_present("binding") {
-        binding_id = matches.value_of("binding").unwrap().into();
-    }
+    let binding_id = match matches.value_of("binding") {
+        Some(binding_id) => binding_id.to_owned(),
+        None => Uuid::new_v4().as_hyphenated().to_string(),
+    };
+    let instance_id = matches.value_of("instance").unwrap().to_string();
 
-    let mut binding_request = ServiceBindingRequest::new("".into(), "".into());
-    let parameters = matches.values_of("parameters");
-    let context = matches.values_of("context");
-
-    // - fetch service_instance
-    //   - extract service_id
-    //   - extract plan_id
-    // - fetch catalog
-    //   - extract schemas
-    /*
-    match schemas {
-        Some(s) => match validate_schema(
-            s.service_binding.unwrap().create.unwrap(),
-            parameters.clone(),
-        ) {
-            Ok(_) => {}
-            Err(e) => return Err(e),
-        },
-        None => {}
+    // the history row carries the generated ID, the command line has none
+    let result = bind_command(matches, client, &options, &instance_id, &binding_id).await;
+    if !options.curl_output {
+        record_operation(
+            &options.request_id,
+            "bind",
+            &instance_id,
+            &binding_id,
+            &result,
+        );
     }
-     */
+    result
+}
+
+async fn bind_command(
+    matches: &ArgMatches,
+    client: Client,
+    options: &Options,
+    instance_id: &str,
+    binding_id: &str,
+) -> Result<(), Box<dyn Error>> {
+    let (instance_id, binding_id) = (instance_id.to_owned(), binding_id.to_owned());
+    // an existing binding is only fetched
+    let existing = matches.is_present("binding");
 
-    binding_request.parameters = Some(json!(parse_parameters(parameters).unwrap()));
-    binding_request.context = Some(json!(parse_parameters(context).unwrap()));
+    let parameters = json!(parse_parameters(matches.values_of("parameters")).unwrap());
+    let context = json!(parse_parameters(matches.values_of("context")).unwrap());
 
     if options.curl_output {
-        match matches.is_present("binding") {
+        let (method, body) = match existing {
             false => {
-                println!(
-                    "{}",
-                    generate_curl_command(
-                        "service_binding".to_owned(),
-                        "PUT".to_owned(),
-                        serde_json::to_string_pretty(&binding_request).unwrap(),
-                        options.synchronous,
-                        instance_id.clone(),
-                        binding_id.clone(),
-                    )
-                );
-            }
-            true => {
-                println!(
-                    "{}",
-                    generate_curl_command(
-                        "service_binding".to_owned(),
-                        "GET".to_owned(),
-                        "".to_owned(),
-                        options.synchronous,
-                        instance_id.clone(),
-                        binding_id.clone(),
-                    )
-                );
+                let (service_id, plan_id) = stored_ids(&instance_id);
+                let binding_request = client
+                    .bind(&instance_id, &binding_id, &service_id, &plan_id)
+                    .parameters(parameters)
+                    .context(context)
+                    .accepts_incomplete(!options.synchronous);
+                (
+                    "PUT",
+                    serde_json::to_string_pretty(binding_request.body()).unwrap(),
+                )
             }
-        }
+            true => ("GET", "".to_owned()),
+        };
+        println!(
+            "{}",
+            generate_curl_command(
+                "service_binding".to_owned(),
+                method.to_owned(),
+                body,
+                options.synchronous,
+                instance_id,
+                binding_id,
+                options,
+            )
+        );
         return Ok(());
     }
 
-    if !matches.is_present("binding") {
-        let binding_response = service_binding_binding(
-            &config,
-            DEFAULT_API_VERSION,
-            &*instance_id,
-            &*binding_id,
-            binding_request,
-            Some(USER_AGENT),
-            Some(!options.synchronous),
-        )
-        .await
-        .expect("binding failed");
-
-        if options.synchronous || !matches.is_present("wait") {
-            match options.json_output {
-                true => {
-                    let mut binding_output = HashMap::new();
-                    binding_output.insert(binding_id, &binding_response);
-                    println!("{}", serde_json::to_string(&binding_output).unwrap());
-                }
-                false => {
-                    let mut table = Table::new();
-                    table.add_row(row!["Instance ID", "Binding ID"]);
-                    table.add_row(row![&*instance_id, &*binding_id]);
-                    table.printstd();
-                }
-            }
-            return Ok(());
-        }
-
-        if matches.is_present("wait") {
+    if !existing {
+        let wait = matches.is_present("wait") && !options.synchronous;
+        if wait {
             eprintln!("[INFO] waiting binding {} provisioning", binding_id);
-
-            loop {
-                thread::sleep(time::Duration::new(POOL_INTERVAL, 0));
-
-                let last_op = service_binding_last_operation_get(
-                    &config,
-                    DEFAULT_API_VERSION,
-                    &*instance_id,
-                    &*binding_id,
-                    None, // service_id
-                    None, // plan id
-                    None, // operation
-                )
-                .await
-                .expect("failed to get binding last operation");
-
-                match last_op.state {
-                    State::InProgress => continue,
-                    _ => break,
-                }
-            }
-        }
-    }
-
-    let provisioned_binding = service_binding_get(
-        &config,
-        DEFAULT_API_VERSION,
-        &*instance_id,
-        &*binding_id,
-        Some(USER_AGENT),
-        None,
-        None,
-    )
-    .await
-    .expect("service binding fetch failed");
-
-    match options.json_output {
-        false => {
-            let mut table = Table::new();
-            table.add_row(row!["Instance ID"]);
-            table.add_row(row![&*instance_id]);
-            table.add_row(row!["Binding ID"]);
-            table.add_row(row![&*binding_id]);
-            table.add_row(row!["Credentials"]);
-            table.add_row(row![serde_json::to_string_pretty(&provisioned_binding)?]);
-            table.printstd();
         }
-        true => {
+        let binding = NewBinding {
+            instance_id: &instance_id,
+            binding_id: &binding_id,
+            parameters,
+            context: Some(&context),
+            name: None,
+        };
+        let binding_response = create_binding(&client, binding, !options.synchronous, wait).await?;
+
+        if !wait {
             let sb_out = ServiceBindingOutput {
+                service_instance_id: Some(instance_id),
                 service_binding_id: Some(binding_id),
-                service_binding_resource: Some(provisioned_binding),
+                service_binding_resource: Some(binding_resource(binding_response)),
+                ..ServiceBindingOutput::new()
             };
-            println!("{}", serde_json::to_string(&sb_out).unwrap());
+            output::print(&sb_out, &options.output)?;
+            return Ok(());
         }
+    }
+
+    let provisioned_binding = client.fetch_binding(&instance_id, &binding_id).await?;
+
+    let sb_out = ServiceBindingOutput {
+        service_instance_id: Some(instance_id),
+        service_binding_id: Some(binding_id),
+        service_binding_resource: Some(provisioned_binding),
+        ..ServiceBindingOutput::new()
     };
+    output::print(&sb_out, &options.output)?;
 
     Ok(())
 }
 
 pub async fn unbind(
     matches: &ArgMatches,
-    config: Configuration,
+    client: Client,
     options: Options,
 ) -> Result<(), Box<dyn Error>> {
     let instance_id = matches.value_of("instance").unwrap().to_string();
@@ -200,24 +140,78 @@ pub async fn unbind(
                 "".to_owned(),
                 options.synchronous,
                 instance_id,
-                binding_id
+                binding_id,
+                &options,
             )
         );
         return Ok(());
     }
 
-    let _unbinding_response = service_binding_unbinding(
-        &config,
-        DEFAULT_API_VERSION,
-        &*instance_id,
-        &*binding_id,
-        "", // service_id
-        "", // plan_id
-        Some(USER_AGENT),
-        Some(!options.synchronous),
-    )
-    .await
-    .expect("service binding unbind failed");
+    remove_binding(&client, &instance_id, &binding_id, !options.synchronous).await?;
+
+    let sb_out = ServiceBindingOutput {
+        service_instance_id: Some(instance_id),
+        service_binding_id: Some(binding_id),
+        ..ServiceBindingOutput::new()
+    };
+    output::print(&sb_out, &options.output)?;
+
+    Ok(())
+}
+
+pub async fn bindings(matches: &ArgMatches, options: Options) -> Result<(), Box<dyn Error>> {
+    let instance_id = matches.value_of("instance").map(String::from);
+    let service = matches.value_of("service");
+    let plan = matches.value_of("plan");
+    let state = matches.value_of("state");
+
+    let mut summaries: Vec<ServiceBindingSummary> = Vec::new();
+
+    for binding in store::binding_list(instance_id)? {
+        let instance = store::instance_get(&binding.instance_id)?;
+        let (service_id, service_name, plan_id, plan_name) = match instance {
+            Some(i) => (i.service_id, i.service_name, i.plan_id, i.plan_name),
+            None => Default::default(),
+        };
+
+        if service.is_some_and(|s| s != service_name && s != service_id)
+            || plan.is_some_and(|p| p != plan_name && p != plan_id)
+            || state.is_some_and(|s| s != binding.state)
+        {
+            continue;
+        }
+
+        summaries.push(ServiceBindingSummary {
+            service_binding_id: binding.binding_id,
+            service_instance_id: binding.instance_id,
+            service_name,
+            plan_name,
+            state: binding.state,
+            created_at: binding.created_at,
+        });
+    }
+
+    match matches.value_of("sort_by").unwrap_or("created") {
+        "id" => summaries.sort_by(|a, b| a.service_binding_id.cmp(&b.service_binding_id)),
+        "instance" => summaries.sort_by(|a, b| a.service_instance_id.cmp(&b.service_instance_id)),
+        "state" => summaries.sort_by(|a, b| a.state.cmp(&b.state)),
+        _ => summaries.sort_by_key(|s| s.created_at),
+    }
+
+    output::print(&ServiceBindingListOutput::new(summaries), &options.output)?;
 
     Ok(())
 }
+
+// a binding created without waiting is reported with the same fields as a fetched one
+fn binding_resource(response: ServiceBindingResponse) -> ServiceBindingResource {
+    ServiceBindingResource {
+        metadata: response.metadata,
+        credentials: response.credentials,
+        syslog_drain_url: response.syslog_drain_url,
+        route_service_url: response.route_service_url,
+        volume_mounts: response.volume_mounts,
+        endpoints: response.endpoints,
+        parameters: None,
+    }
+}
@@ -1,29 +1,27 @@
 use crate::cli::{
-    find_service_plan_id, generate_curl_command, parse_parameters, Options, DEFAULT_API_VERSION,
-    POOL_INTERVAL, USER_AGENT,
+    fetch_catalog, find_service_plan, generate_curl_command, output, parse_parameters,
+    provision_instance, record_operation, remove_binding, remove_instance, validate_parameters,
+    ExitStatus, NewInstance, Options, SchemaAction,
 };
-use crate::models::ServiceInstanceOutput;
+use crate::client::Client;
+use crate::models::{
+    ChangeSetOutput, ResourceChange, ServiceBindingSummary, ServiceInstanceDescriptionOutput,
+    ServiceInstanceListOutput, ServiceInstanceOutput, ServiceInstanceSummary,
+};
+use crate::store;
+
+use chrono::{Duration, Utc};
 
 use clap::ArgMatches;
-use prettytable::Table;
-use rocl::{
-    apis::{
-        configuration::Configuration,
-        service_instances_api::{
-            service_instance_deprovision, service_instance_get,
-            service_instance_last_operation_get, service_instance_provision,
-        },
-    },
-    models::{last_operation_resource::State, ServiceInstanceProvisionRequestBody},
-};
+use futures_util::{stream, StreamExt};
 use serde_json::json;
-use std::{collections::HashMap, error::Error};
-use std::{thread, time};
+use std::error::Error;
+use std::io::{self, Write};
 use uuid::Uuid;
 
 pub async fn info(
     args: &ArgMatches,
-    config: Configuration,
+    client: Client,
     options: Options,
 ) -> Result<(), Box<dyn Error>> {
     let instance_id: String = args.value_of("instance").unwrap().to_owned();
@@ -37,55 +35,151 @@ pub async fn info(
                 "".to_owned(),
                 false,
                 instance_id,
-                "".to_owned()
+                "".to_owned(),
+                &options,
             )
         );
         return Ok(());
     }
 
-    let si = service_instance_get(
-        &config,
-        DEFAULT_API_VERSION,
-        &*instance_id,
-        Some(USER_AGENT),
-        None,
-        None,
-    )
-    .await
-    .expect("failed to fetch service instance");
-
-    match options.json_output {
-        false => {
-            let mut service_instance_table = Table::new();
-            service_instance_table.add_row(row!["ID", "Service ID", "Plans ID", "Dashboard"]);
-            service_instance_table.add_row(row![
+    let si = client.fetch_instance(&instance_id).await?;
+
+    let instance_output = ServiceInstanceOutput {
+        service_instance_id: Some(instance_id),
+        service_instance_resource: Some(si),
+        ..ServiceInstanceOutput::new()
+    };
+    output::print(&instance_output, &options.output)?;
+
+    Ok(())
+}
+
+pub async fn describe(
+    args: &ArgMatches,
+    client: Client,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    let instance_id: String = args.value_of("instance").unwrap().to_owned();
+
+    if options.curl_output {
+        println!(
+            "{}",
+            generate_curl_command(
+                "service_instance".to_owned(),
+                "GET".to_owned(),
+                "".to_owned(),
+                false,
                 instance_id,
-                si.service_id.unwrap(),
-                si.plan_id.unwrap(),
-                si.dashboard_url.unwrap()
-            ]);
-            service_instance_table.printstd();
-        }
-        true => {
-            let mut instance_output = HashMap::new();
-            instance_output.insert(instance_id, si);
-            println!("{}", serde_json::to_string(&instance_output).unwrap());
+                "".to_owned(),
+                &options,
+            )
+        );
+        return Ok(());
+    }
+
+    let stored = store::instance_get(&instance_id).unwrap_or_else(|e| {
+        eprintln!("[WARN] failed to read local store: {}", e);
+        None
+    });
+
+    // brokers may not support fetching instances, the local store still
+    // knows about the ones provisioned with rocs
+    let resource = match client.fetch_instance(&instance_id).await {
+        Ok(resource) => Some(resource),
+        Err(e) if stored.is_some() => {
+            eprintln!("[WARN] {}", e);
+            None
         }
+        Err(e) => return Err(e.into()),
     };
 
+    let mut description = ServiceInstanceDescriptionOutput::new(instance_id.clone());
+
+    if let Some(resource) = resource {
+        description.service_id = resource.service_id;
+        description.plan_id = resource.plan_id;
+        description.dashboard_url = resource.dashboard_url;
+        description.maintenance_info = resource.maintenance_info.map(|m| *m);
+        description.parameters = resource.parameters;
+    }
+
+    if let Some(stored) = &stored {
+        description
+            .service_id
+            .get_or_insert(stored.service_id.clone());
+        description.plan_id.get_or_insert(stored.plan_id.clone());
+        description.service_name = Some(stored.service_name.clone());
+        description.plan_name = Some(stored.plan_name.clone());
+        description.requested_parameters = serde_json::from_str(&stored.parameters).ok();
+        description.created_at = Some(stored.created_at);
+    }
+
+    match fetch_catalog(&client, &options).await {
+        Ok(services) => {
+            let service = services
+                .into_iter()
+                .find(|s| Some(&s.id) == description.service_id.as_ref());
+
+            if let Some(service) = service {
+                description.service_name = Some(service.name);
+                description.service_description = Some(service.description);
+
+                if let Some(plan) = service
+                    .plans
+                    .into_iter()
+                    .find(|p| Some(&p.id) == description.plan_id.as_ref())
+                {
+                    description.plan_name = Some(plan.name);
+                    description.plan_description = Some(plan.description);
+                    description.plan_maintenance_info = plan.maintenance_info.map(|m| *m);
+                }
+            }
+        }
+        Err(e) => eprintln!("[WARN] {}", e),
+    }
+
+    // brokers answer with an error when the instance never had an asynchronous operation
+    let mut last_operation = client.last_operation(&instance_id);
+    if let Some(service_id) = &description.service_id {
+        last_operation = last_operation.service_id(service_id);
+    }
+    if let Some(plan_id) = &description.plan_id {
+        last_operation = last_operation.plan_id(plan_id);
+    }
+    description.last_operation = last_operation.send().await.ok();
+
+    match store::binding_list(Some(instance_id)) {
+        Ok(bindings) => {
+            description.service_bindings = bindings
+                .into_iter()
+                .map(|b| ServiceBindingSummary {
+                    service_binding_id: b.binding_id,
+                    service_instance_id: b.instance_id,
+                    service_name: description.service_name.clone().unwrap_or_default(),
+                    plan_name: description.plan_name.clone().unwrap_or_default(),
+                    state: b.state,
+                    created_at: b.created_at,
+                })
+                .collect()
+        }
+        Err(e) => eprintln!("[WARN] failed to read local store: {}", e),
+    }
+
+    output::print(&description, &options.output)?;
+
     Ok(())
 }
 
 pub async fn deprovision(
     matches: &ArgMatches,
-    config: Configuration,
+    client: Client,
     options: Options,
 ) -> Result<(), Box<dyn Error>> {
-    let instance_id = matches.value_of("instance").unwrap().to_string();
+    if matches.is_present("all") {
+        return deprovision_all(matches, client, options).await;
+    }
 
-    // the spec requires this parameters
-    let plan_id = matches.value_of("plan").unwrap_or("ignore").to_string();
-    let service_id = matches.value_of("service").unwrap_or("ignore").to_string();
+    let instance_id = matches.value_of("instance").unwrap().to_string();
 
     if options.curl_output {
         println!(
@@ -96,138 +190,318 @@ pub async fn deprovision(
                 "".to_owned(),
                 options.synchronous,
                 instance_id,
-                "".to_owned()
+                "".to_owned(),
+                &options,
             )
         );
         return Ok(());
     }
 
-    service_instance_deprovision(
-        &config,
-        DEFAULT_API_VERSION,
-        &*instance_id,
-        &*plan_id,
-        &*service_id,
-        Some(USER_AGENT),
-        Some(!options.synchronous),
-    )
-    .await
-    .expect("deprovisioning request failed");
+    remove_instance(&client, &instance_id, !options.synchronous).await?;
+
+    let instance_output = ServiceInstanceOutput {
+        service_instance_id: Some(instance_id),
+        ..ServiceInstanceOutput::new()
+    };
+    output::print(&instance_output, &options.output)?;
+
     Ok(())
 }
 
-pub async fn provision(
+/// `deprovision --all`: deprovisions the stored instances matching the filters,
+/// `--concurrency` at a time.
+async fn deprovision_all(
     matches: &ArgMatches,
-    config: Configuration,
+    client: Client,
     options: Options,
 ) -> Result<(), Box<dyn Error>> {
-    let service = matches.value_of("service").unwrap().to_string();
-    let plan = matches.value_of("plan").unwrap().to_string();
+    if options.curl_output {
+        return Err("deprovision --all sends many requests and cannot be printed as curl".into());
+    }
+
+    let service = matches.value_of("service");
+    let plan = matches.value_of("plan");
+    let older_than = matches.get_one::<Duration>("older_than");
+    let cascade = matches.is_present("cascade");
+    let concurrency = matches
+        .get_one::<usize>("concurrency")
+        .copied()
+        .unwrap_or(4);
+    let accepts_incomplete = !options.synchronous;
+
+    let now = Utc::now().naive_utc();
+    let mut selected = Vec::new();
+    for instance in store::instance_list()? {
+        // instances of other brokers are kept
+        if instance.broker_url != options.broker_url
+            || service.is_some_and(|s| s != instance.service_name && s != instance.service_id)
+            || plan.is_some_and(|p| p != instance.plan_name && p != instance.plan_id)
+            || older_than.is_some_and(|age| now - instance.created_at < *age)
+        {
+            continue;
+        }
+        let bindings = match cascade {
+            true => store::binding_list(Some(instance.instance_id.clone()))?,
+            false => Vec::new(),
+        };
+        selected.push((instance, bindings));
+    }
+
+    if !selected.is_empty() && !matches.is_present("yes") {
+        for (instance, bindings) in &selected {
+            eprintln!(
+                "{}  {}/{}  created {}{}",
+                instance.instance_id,
+                instance.service_name,
+                instance.plan_name,
+                instance.created_at,
+                match bindings.len() {
+                    0 => String::new(),
+                    n => format!(", {} binding(s) to unbind", n),
+                }
+            );
+        }
+        eprint!("deprovision {} instance(s)? [y/N] ", selected.len());
+        io::stderr().flush()?;
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            return Err("deprovisioning cancelled".into());
+        }
+    }
+
+    let client = &client;
+    let mut results: Vec<_> = stream::iter(selected.into_iter().enumerate())
+        .map(|(i, (instance, bindings))| async move {
+            let name = instance
+                .name
+                .clone()
+                .unwrap_or_else(|| instance.instance_id.clone());
+            let change = |action: &str, binding_id: Option<String>| ResourceChange {
+                action: action.into(),
+                name: name.clone(),
+                instance: None,
+                service: Some(instance.service_name.clone()),
+                plan: Some(instance.plan_name.clone()),
+                fields: Vec::new(),
+                service_instance_id: Some(instance.instance_id.clone()),
+                service_binding_id: binding_id,
+                state: None,
+                error: None,
+            };
+
+            // an instance is only deprovisioned once all its bindings are gone
+            let mut changes = Vec::new();
+            let mut failed = false;
+            for binding in bindings {
+                let mut result = change("unbind", Some(binding.binding_id.clone()));
+                outcome(
+                    &mut result,
+                    &mut failed,
+                    remove_binding(
+                        client,
+                        &instance.instance_id,
+                        &binding.binding_id,
+                        accepts_incomplete,
+                    )
+                    .await,
+                );
+                changes.push(result);
+            }
 
-    let (service_id, plan_id, _schemas) = find_service_plan_id(config.clone(), service, plan)
-        .await
-        .expect("service or plan id not found");
+            let mut result = change("deprovision", None);
+            if failed {
+                result.state = Some("skipped".into());
+            } else {
+                eprintln!("[INFO] deprovisioning {}", instance.instance_id);
+                let removed = remove_instance(client, &instance.instance_id, accepts_incomplete);
+                outcome(&mut result, &mut failed, removed.await);
+            }
+            changes.push(result);
 
-    let mut provision_request = ServiceInstanceProvisionRequestBody::new(
-        service_id.clone(),
-        plan_id.clone(),
-        String::from(""),
-        String::from(""),
-    );
+            (i, changes)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
 
-    let parameters = matches.values_of("parameters");
-    let context = matches.values_of("context");
+    results.sort_by_key(|(i, _)| *i);
+    let changes: Vec<_> = results.into_iter().flat_map(|(_, c)| c).collect();
+    let failed = changes.iter().any(|c| c.state.as_deref() == Some("failed"));
+    output::print(&ChangeSetOutput::new(true, changes), &options.output)?;
 
-    //validate_service_schema(schemas, parameters.clone());
+    match failed {
+        true => Err(Box::new(ExitStatus {
+            code: 1,
+            reason: "some instances were not deprovisioned".into(),
+        })),
+        false => Ok(()),
+    }
+}
 
-    provision_request.parameters = Some(json!(parse_parameters(parameters).unwrap()));
-    provision_request.context = Some(json!(parse_parameters(context).unwrap()));
+fn outcome(result: &mut ResourceChange, failed: &mut bool, removed: Result<(), Box<dyn Error>>) {
+    match removed {
+        Ok(()) => result.state = Some("succeeded".into()),
+        Err(e) => {
+            result.state = Some("failed".into());
+            result.error = Some(e.to_string());
+            *failed = true;
+        }
+    }
+}
 
+/// Parses ages such as `90s`, `30m`, `12h`, `7d` or `2w`.
+pub fn parse_age(value: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid age {}, use a number followed by s, m, h, d or w",
+            value
+        )
+    };
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let count: i64 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    if count <= 0 {
+        return Err(invalid());
+    }
+
+    let age = match unit {
+        's' => Duration::try_seconds(count),
+        'm' => Duration::try_minutes(count),
+        'h' => Duration::try_hours(count),
+        'd' => Duration::try_days(count),
+        'w' => Duration::try_weeks(count),
+        _ => None,
+    };
+    // None when the count is out of the range of a Duration
+    age.ok_or_else(invalid)
+}
+
+pub async fn provision(
+    matches: &ArgMatches,
+    client: Client,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    // the history row carries the generated ID, the command line has none
     let instance_id = Uuid::new_v4().as_hyphenated().to_string();
+    let result = provision_command(matches, client, &options, &instance_id).await;
+    if !options.curl_output {
+        record_operation(&options.request_id, "provision", &instance_id, "", &result);
+    }
+    result
+}
+
+async fn provision_command(
+    matches: &ArgMatches,
+    client: Client,
+    options: &Options,
+    instance_id: &str,
+) -> Result<(), Box<dyn Error>> {
+    let instance_id = instance_id.to_owned();
+    let (service, plan) = find_service_plan(
+        &client,
+        options,
+        matches.value_of("service").unwrap(),
+        matches.value_of("plan").unwrap(),
+    )
+    .await?;
+
+    let parameters = json!(parse_parameters(matches.values_of("parameters")).unwrap());
+    let context = json!(parse_parameters(matches.values_of("context")).unwrap());
+
+    validate_parameters(&plan, SchemaAction::Create, &parameters)?;
 
     if options.curl_output {
+        let provision_request = client
+            .provision(&instance_id, &service.id, &plan.id)
+            .parameters(parameters)
+            .context(context)
+            .accepts_incomplete(!options.synchronous);
         println!(
             "{}",
             generate_curl_command(
                 "service_instance".to_owned(),
                 "PUT".to_owned(),
-                serde_json::to_string_pretty(&provision_request).unwrap(),
+                serde_json::to_string_pretty(provision_request.body()).unwrap(),
                 options.synchronous,
                 instance_id,
-                "".to_owned()
+                "".to_owned(),
+                options,
             )
         );
         return Ok(());
     }
 
-    let _provision_response = service_instance_provision(
-        &config,
-        DEFAULT_API_VERSION,
-        &*instance_id, // from String to &str
-        provision_request,
-        Some(USER_AGENT),
-        Some(!options.synchronous), // Accepts-incomplete
-    )
-    .await
-    .expect("provision request failed");
-
-    if matches.is_present("wait") {
+    let wait = matches.is_present("wait");
+    if wait {
         eprintln!(
             "[INFO] waiting service instance {} provisioning",
             instance_id
         );
+    }
+    let instance = NewInstance {
+        instance_id: instance_id.clone(),
+        service,
+        plan,
+        parameters,
+        context: Some(&context),
+        name: None,
+        broker_url: &options.broker_url,
+    };
+    provision_instance(&client, instance, !options.synchronous, wait).await?;
 
-        loop {
-            thread::sleep(time::Duration::new(POOL_INTERVAL, 0));
+    let provisioned_instance = client.fetch_instance(&instance_id).await?;
 
-            let last_op = service_instance_last_operation_get(
-                &config,
-                DEFAULT_API_VERSION,
-                &*instance_id,
-                Some(&*service_id), // service_id
-                Some(&*plan_id),    // plan id
-                None,               // operation
-            )
-            .await
-            .expect("failed to fetch last operation");
+    let si_out = ServiceInstanceOutput {
+        service_instance_id: Some(instance_id),
+        service_instance_resource: Some(provisioned_instance),
+        ..ServiceInstanceOutput::new()
+    };
+    output::print(&si_out, &options.output)?;
 
-            match last_op.state {
-                State::InProgress => continue,
-                _ => break,
-            }
+    Ok(())
+}
+
+pub async fn instances(matches: &ArgMatches, options: Options) -> Result<(), Box<dyn Error>> {
+    let service = matches.value_of("service");
+    let plan = matches.value_of("plan");
+    let state = matches.value_of("state");
+
+    let mut summaries: Vec<ServiceInstanceSummary> = Vec::new();
+
+    for instance in store::instance_list()? {
+        if service.is_some_and(|s| s != instance.service_name && s != instance.service_id)
+            || plan.is_some_and(|p| p != instance.plan_name && p != instance.plan_id)
+            || state.is_some_and(|s| s != instance.state)
+        {
+            continue;
         }
+
+        let binding_count = store::binding_list(Some(instance.instance_id.clone()))?.len();
+        summaries.push(ServiceInstanceSummary {
+            service_instance_id: instance.instance_id,
+            service_id: instance.service_id,
+            service_name: instance.service_name,
+            plan_id: instance.plan_id,
+            plan_name: instance.plan_name,
+            state: instance.state,
+            created_at: instance.created_at,
+            updated_at: instance.updated_at,
+            binding_count,
+        });
     }
 
-    let provisioned_instance = service_instance_get(
-        &config,
-        DEFAULT_API_VERSION,
-        &*instance_id,
-        Some(USER_AGENT),
-        None,
-        None,
-    )
-    .await
-    .expect("service instance fetch failed");
-
-    match options.json_output {
-        false => {
-            let mut table = Table::new();
-            table.add_row(row!["Instance ID", "Dashboard URL"]);
-            table.add_row(row![
-                &*instance_id,
-                provisioned_instance.dashboard_url.unwrap()
-            ]);
-            table.printstd();
-        }
-        true => {
-            let si_out = ServiceInstanceOutput {
-                service_instance_id: Some(instance_id),
-                service_instance_resource: Some(provisioned_instance),
-            };
+    match matches.value_of("sort_by").unwrap_or("created") {
+        "id" => summaries.sort_by(|a, b| a.service_instance_id.cmp(&b.service_instance_id)),
+        "service" => summaries.sort_by(|a, b| a.service_name.cmp(&b.service_name)),
+        "plan" => summaries.sort_by(|a, b| a.plan_name.cmp(&b.plan_name)),
+        "state" => summaries.sort_by(|a, b| a.state.cmp(&b.state)),
+        _ => summaries.sort_by_key(|s| s.created_at),
+    }
 
-            println!("{}", serde_json::to_string(&si_out).unwrap());
-        }
-    };
+    output::print(&ServiceInstanceListOutput::new(summaries), &options.output)?;
 
     Ok(())
 }
@@ -0,0 +1,107 @@
+use crate::cli::{generate_curl_command, output, ExitStatus, Options, EXIT_IN_PROGRESS};
+use crate::client::Client;
+use crate::models::LastOperationOutput;
+use crate::operation::{wait_for_operation, WaitError, WaitOptions, POOL_INTERVAL};
+use crate::store;
+
+use clap::ArgMatches;
+use futures_util::StreamExt;
+use rocl::models::{last_operation_resource::State, LastOperationResource};
+use std::error::Error;
+use std::time::Duration;
+
+pub async fn status(
+    matches: &ArgMatches,
+    client: Client,
+    options: Options,
+) -> Result<(), Box<dyn Error>> {
+    let instance_id = matches.value_of("instance").unwrap().to_string();
+    let binding_id = matches.value_of("binding").map(String::from);
+    let operation = matches.value_of("operation");
+
+    if options.curl_output {
+        let (object, bid) = match &binding_id {
+            Some(binding_id) => ("service_binding", binding_id.clone()),
+            None => ("service_instance", "".to_owned()),
+        };
+        let mut command = generate_curl_command(
+            object.to_owned(),
+            "GET".to_owned(),
+            "".to_owned(),
+            true,
+            instance_id,
+            bid,
+            &options,
+        );
+        command.push_str("/last_operation");
+        if let Some(operation) = operation {
+            command.push_str(&format!("?operation={}", operation));
+        }
+        println!("{}", command);
+        return Ok(());
+    }
+
+    // service and plan ids are optional in the spec but some brokers route on them
+    let (service_id, plan_id) = match store::instance_get(&instance_id) {
+        Ok(Some(instance)) => (Some(instance.service_id), Some(instance.plan_id)),
+        _ => (None, None),
+    };
+
+    let mut request = client.last_operation(&instance_id);
+    if let Some(binding_id) = &binding_id {
+        request = request.binding(binding_id);
+    }
+    if let (Some(service_id), Some(plan_id)) = (&service_id, &plan_id) {
+        request = request.service_id(service_id).plan_id(plan_id);
+    }
+    if let Some(operation) = operation {
+        request = request.operation(operation);
+    }
+
+    let print = |last_op: &LastOperationResource| {
+        let mut last_op_out = LastOperationOutput::new(instance_id.clone(), last_op.clone());
+        last_op_out.service_binding_id = binding_id.clone();
+        output::print(&last_op_out, &options.output)
+    };
+
+    let last_op = match matches.is_present("watch") {
+        false => {
+            let last_op = request.send().await?;
+            print(&last_op)?;
+            last_op
+        }
+        // only changes are printed while watching
+        true => {
+            let wait_options = WaitOptions {
+                interval: Duration::from_secs(POOL_INTERVAL),
+                backoff: 1.0,
+                ..WaitOptions::default()
+            };
+            let mut updates = wait_for_operation(request, wait_options);
+            let mut last = None;
+            while let Some(update) = updates.next().await {
+                let last_op = match update {
+                    Ok(last_op) => last_op,
+                    // keep the broker error so the error output can report its status
+                    Err(WaitError::Client(e)) => return Err(Box::new(e)),
+                    Err(e) => return Err(Box::new(e)),
+                };
+                print(&last_op)?;
+                last = Some(last_op);
+            }
+            last.ok_or("stopped watching the operation")?
+        }
+    };
+
+    match last_op.state {
+        State::Succeeded => Ok(()),
+        State::Failed => Err(Box::new(ExitStatus {
+            code: 1,
+            reason: "operation failed".into(),
+        })),
+        State::InProgress => Err(Box::new(ExitStatus {
+            code: EXIT_IN_PROGRESS,
+            reason: "operation in progress".into(),
+        })),
+    }
+}
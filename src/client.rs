@@ -0,0 +1,795 @@
+use crate::auth::TokenProvider;
+use crate::broker::{Broker, CatalogDocument, LastOperationQuery, RoclBroker};
+use crate::http::{BrokerError, NetworkSettings, TimeoutError, TlsSettings};
+use crate::proxy;
+
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use rocl::apis::{configuration::Configuration, Error as ApiError};
+use rocl::models::{
+    AsyncOperation, Catalog, LastOperationResource, ServiceBindingRequest, ServiceBindingResource,
+    ServiceBindingResponse, ServiceInstanceProvisionRequestBody, ServiceInstanceProvisionResponse,
+    ServiceInstanceResource, ServiceInstanceUpdateRequestBody,
+};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// `User-Agent` sent with every request.
+pub const USER_AGENT: &str = "ROCS v0.2";
+/// `X-Broker-API-Version` sent unless [`ClientBuilder::api_version`] says otherwise.
+pub const DEFAULT_API_VERSION: &str = "2.15";
+
+/// OSB client for applications embedding rocs, the CLI commands are built on it.
+///
+/// Requests that take options are made with builders:
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = rocs::Client::builder("https://broker.example.com")
+///     .basic_auth("admin", "secret")
+///     .build()?;
+/// client
+///     .provision("instance-id", "service-id", "plan-id")
+///     .parameters(serde_json::json!({"region": "eu-west-1"}))
+///     .send()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Requests go through a [`Broker`], [`Client::with_broker`] swaps the HTTP
+/// one for another implementation such as [`FakeBroker`](crate::fake::FakeBroker).
+#[derive(Clone)]
+pub struct Client {
+    broker: Arc<dyn Broker>,
+    config: Option<Configuration>,
+    api_version: String,
+}
+
+impl Client {
+    /// Wraps a rocl configuration that already carries the broker URL, HTTP client and credentials.
+    pub fn new(config: Configuration) -> Client {
+        Client {
+            broker: Arc::new(RoclBroker::new(
+                config.clone(),
+                DEFAULT_API_VERSION,
+                Some(USER_AGENT),
+            )),
+            config: Some(config),
+            api_version: DEFAULT_API_VERSION.to_string(),
+        }
+    }
+
+    /// Client sending its requests to `broker` instead of an HTTP broker.
+    pub fn with_broker(broker: Arc<dyn Broker>) -> Client {
+        Client {
+            broker,
+            config: None,
+            api_version: DEFAULT_API_VERSION.to_string(),
+        }
+    }
+
+    pub fn builder(broker_url: &str) -> ClientBuilder {
+        ClientBuilder {
+            broker_url: broker_url.to_string(),
+            basic_auth: None,
+            bearer_token: None,
+            tls: TlsSettings::default(),
+            network: NetworkSettings::default(),
+            headers: HeaderMap::new(),
+            api_version: DEFAULT_API_VERSION.to_string(),
+            originating_identity: Some(USER_AGENT.to_string()),
+            local_proxy: None,
+            token_provider: None,
+        }
+    }
+
+    /// rocl configuration of the HTTP broker, `None` with [`Client::with_broker`].
+    pub fn configuration(&self) -> Option<&Configuration> {
+        self.config.as_ref()
+    }
+
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+
+    pub fn broker(&self) -> &dyn Broker {
+        &*self.broker
+    }
+
+    pub async fn catalog(&self) -> Result<Catalog, ClientError> {
+        match self.broker.catalog(None).await? {
+            CatalogDocument::Modified { content, .. } => {
+                serde_json::from_str(&content).map_err(|e| ClientError::Request {
+                    context: "failed to get catalog".to_string(),
+                    message: e.to_string(),
+                })
+            }
+            CatalogDocument::NotModified => Err(ClientError::Request {
+                context: "failed to get catalog".to_string(),
+                message: "broker answered not modified without an ETag".to_string(),
+            }),
+        }
+    }
+
+    pub fn provision(
+        &self,
+        instance_id: &str,
+        service_id: &str,
+        plan_id: &str,
+    ) -> ProvisionRequest<'_> {
+        ProvisionRequest {
+            client: self,
+            instance_id: instance_id.to_string(),
+            body: ServiceInstanceProvisionRequestBody::new(
+                service_id.to_string(),
+                plan_id.to_string(),
+                String::new(),
+                String::new(),
+            ),
+            accepts_incomplete: true,
+        }
+    }
+
+    pub fn update(&self, instance_id: &str, service_id: &str) -> UpdateRequest<'_> {
+        UpdateRequest {
+            client: self,
+            instance_id: instance_id.to_string(),
+            body: ServiceInstanceUpdateRequestBody::new(service_id.to_string()),
+            accepts_incomplete: true,
+        }
+    }
+
+    pub fn deprovision(
+        &self,
+        instance_id: &str,
+        service_id: &str,
+        plan_id: &str,
+    ) -> DeprovisionRequest<'_> {
+        DeprovisionRequest {
+            client: self,
+            instance_id: instance_id.to_string(),
+            service_id: service_id.to_string(),
+            plan_id: plan_id.to_string(),
+            accepts_incomplete: true,
+        }
+    }
+
+    pub fn bind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        service_id: &str,
+        plan_id: &str,
+    ) -> BindRequest<'_> {
+        BindRequest {
+            client: self,
+            instance_id: instance_id.to_string(),
+            binding_id: binding_id.to_string(),
+            body: ServiceBindingRequest::new(service_id.to_string(), plan_id.to_string()),
+            accepts_incomplete: true,
+        }
+    }
+
+    pub fn unbind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        service_id: &str,
+        plan_id: &str,
+    ) -> UnbindRequest<'_> {
+        UnbindRequest {
+            client: self,
+            instance_id: instance_id.to_string(),
+            binding_id: binding_id.to_string(),
+            service_id: service_id.to_string(),
+            plan_id: plan_id.to_string(),
+            accepts_incomplete: true,
+        }
+    }
+
+    pub async fn fetch_instance(
+        &self,
+        instance_id: &str,
+    ) -> Result<ServiceInstanceResource, ClientError> {
+        self.broker.fetch_instance(instance_id).await
+    }
+
+    pub async fn fetch_binding(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+    ) -> Result<ServiceBindingResource, ClientError> {
+        self.broker.fetch_binding(instance_id, binding_id).await
+    }
+
+    /// Last operation of an instance, or of one of its bindings with [`LastOperationRequest::binding`].
+    pub fn last_operation(&self, instance_id: &str) -> LastOperationRequest<'_> {
+        LastOperationRequest {
+            client: self,
+            query: LastOperationQuery {
+                instance_id: instance_id.to_string(),
+                ..LastOperationQuery::default()
+            },
+        }
+    }
+}
+
+/// Builds a [`Client`] and the HTTP client it uses.
+#[derive(Clone)]
+pub struct ClientBuilder {
+    broker_url: String,
+    basic_auth: Option<(String, Option<String>)>,
+    bearer_token: Option<String>,
+    tls: TlsSettings,
+    network: NetworkSettings,
+    headers: HeaderMap,
+    api_version: String,
+    originating_identity: Option<String>,
+    local_proxy: Option<String>,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+}
+
+impl ClientBuilder {
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some((username.to_string(), Some(password.to_string())));
+        self
+    }
+
+    pub fn bearer_token(mut self, token: &str) -> Self {
+        self.bearer_token = Some(token.to_string());
+        self
+    }
+
+    /// Asks `provider` for the bearer token before each request, in place of the
+    /// [`bearer_token`](Self::bearer_token) once it changes. A request the broker
+    /// answers with 401 is sent again once with a newly issued token.
+    pub fn token_provider(mut self, provider: Arc<dyn TokenProvider>) -> Self {
+        self.token_provider = Some(provider);
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsSettings) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    pub fn network(mut self, network: NetworkSettings) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Header sent with every request.
+    pub fn header(mut self, name: &'static str, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// `X-Broker-API-Version` sent with every request, 2.15 by default.
+    pub fn api_version(mut self, api_version: &str) -> Self {
+        self.api_version = api_version.to_string();
+        self
+    }
+
+    /// `X-Broker-API-Originating-Identity` sent with requests that accept it.
+    pub fn originating_identity(mut self, identity: Option<&str>) -> Self {
+        self.originating_identity = identity.map(str::to_owned);
+        self
+    }
+
+    /// Sends requests to a [`LocalProxy`](crate::proxy::LocalProxy) listening at
+    /// `proxy_url`, which applies the TLS and proxy settings when forwarding them.
+    pub fn local_proxy(mut self, proxy_url: &str) -> Self {
+        self.local_proxy = Some(proxy_url.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<Client, Box<dyn Error>> {
+        let (config, broker) = self.rocl_broker()?;
+        let broker: Arc<dyn Broker> = match &self.token_provider {
+            Some(provider) => Arc::new(TokenBroker {
+                provider: provider.clone(),
+                current: Mutex::new((
+                    self.bearer_token.clone().unwrap_or_default(),
+                    Arc::new(broker),
+                )),
+                builder: self.clone(),
+            }),
+            None => Arc::new(broker),
+        };
+        Ok(Client {
+            broker,
+            config: Some(config),
+            api_version: self.api_version,
+        })
+    }
+
+    fn rocl_broker(&self) -> Result<(Configuration, RoclBroker), Box<dyn Error>> {
+        let mut headers = self.headers.clone();
+        if let Some(token) = &self.bearer_token {
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token))?,
+            );
+        }
+
+        let mut config = Configuration::new();
+        config.user_agent = Some(USER_AGENT.to_string());
+        config.basic_auth = self.basic_auth.clone();
+        config.bearer_access_token = self.bearer_token.clone();
+        let http = match &self.local_proxy {
+            Some(proxy_url) => {
+                config.base_path = proxy_url.clone();
+                let local = NetworkSettings {
+                    proxy: None,
+                    no_proxy: true,
+                    ..self.network.clone()
+                };
+                local.apply(reqwest::Client::builder())?
+            }
+            None => {
+                config.base_path = self.broker_url.trim_end_matches('/').to_string();
+                self.network
+                    .apply(self.tls.apply(reqwest::Client::builder())?)?
+            }
+        };
+        config.client = http.default_headers(headers).build()?;
+
+        let broker = RoclBroker::new(
+            config.clone(),
+            &self.api_version,
+            self.originating_identity.as_deref(),
+        );
+        Ok((config, broker))
+    }
+}
+
+/// [`RoclBroker`] sending the token of a [`TokenProvider`], rebuilt whenever it changes.
+struct TokenBroker {
+    provider: Arc<dyn TokenProvider>,
+    builder: ClientBuilder,
+    current: Mutex<(String, Arc<RoclBroker>)>,
+}
+
+impl TokenBroker {
+    async fn broker(&self, refresh: bool) -> Result<Arc<RoclBroker>, ClientError> {
+        let token =
+            self.provider
+                .token(refresh)
+                .await
+                .map_err(|e| match e.downcast::<ClientError>() {
+                    Ok(e) => *e,
+                    Err(e) => ClientError::Request {
+                        context: "access token".to_string(),
+                        message: e.to_string(),
+                    },
+                })?;
+
+        let mut current = self.current.lock().unwrap();
+        if current.0 != token {
+            let mut builder = self.builder.clone();
+            builder.bearer_token = Some(token.clone());
+            let (_, broker) = builder.rocl_broker().map_err(|e| ClientError::Request {
+                context: "access token".to_string(),
+                message: e.to_string(),
+            })?;
+            *current = (token, Arc::new(broker));
+        }
+        Ok(current.1.clone())
+    }
+
+    /// Sends `request`, and once more with a new token when the broker answers 401.
+    async fn send<T, F, R>(&self, request: F) -> Result<T, ClientError>
+    where
+        F: Fn(Arc<RoclBroker>) -> R,
+        R: Future<Output = Result<T, ClientError>>,
+    {
+        match request(self.broker(false).await?).await {
+            Err(e) if e.status() == Some(401) => request(self.broker(true).await?).await,
+            result => result,
+        }
+    }
+}
+
+#[async_trait]
+impl Broker for TokenBroker {
+    async fn catalog(&self, etag: Option<&str>) -> Result<CatalogDocument, ClientError> {
+        self.send(|broker| async move { broker.catalog(etag).await })
+            .await
+    }
+
+    async fn provision(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceProvisionRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceInstanceProvisionResponse, ClientError> {
+        self.send(|broker| {
+            let body = body.clone();
+            async move {
+                broker
+                    .provision(instance_id, body, accepts_incomplete)
+                    .await
+            }
+        })
+        .await
+    }
+
+    async fn update(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceUpdateRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        self.send(|broker| {
+            let body = body.clone();
+            async move { broker.update(instance_id, body, accepts_incomplete).await }
+        })
+        .await
+    }
+
+    async fn deprovision(
+        &self,
+        instance_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        self.send(|broker| async move {
+            broker
+                .deprovision(instance_id, service_id, plan_id, accepts_incomplete)
+                .await
+        })
+        .await
+    }
+
+    async fn bind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        body: ServiceBindingRequest,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceBindingResponse, ClientError> {
+        self.send(|broker| {
+            let body = body.clone();
+            async move {
+                broker
+                    .bind(instance_id, binding_id, body, accepts_incomplete)
+                    .await
+            }
+        })
+        .await
+    }
+
+    async fn unbind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        self.send(|broker| async move {
+            broker
+                .unbind(
+                    instance_id,
+                    binding_id,
+                    service_id,
+                    plan_id,
+                    accepts_incomplete,
+                )
+                .await
+        })
+        .await
+    }
+
+    async fn fetch_instance(
+        &self,
+        instance_id: &str,
+    ) -> Result<ServiceInstanceResource, ClientError> {
+        self.send(|broker| async move { broker.fetch_instance(instance_id).await })
+            .await
+    }
+
+    async fn fetch_binding(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+    ) -> Result<ServiceBindingResource, ClientError> {
+        self.send(|broker| async move { broker.fetch_binding(instance_id, binding_id).await })
+            .await
+    }
+
+    async fn last_operation(
+        &self,
+        query: &LastOperationQuery,
+    ) -> Result<LastOperationResource, ClientError> {
+        self.send(|broker| async move { broker.last_operation(query).await })
+            .await
+    }
+}
+
+pub struct ProvisionRequest<'a> {
+    client: &'a Client,
+    instance_id: String,
+    body: ServiceInstanceProvisionRequestBody,
+    accepts_incomplete: bool,
+}
+
+impl ProvisionRequest<'_> {
+    pub fn parameters(mut self, parameters: Value) -> Self {
+        self.body.parameters = Some(parameters);
+        self
+    }
+
+    pub fn context(mut self, context: Value) -> Self {
+        self.body.context = Some(context);
+        self
+    }
+
+    /// Lets the broker provision asynchronously, true by default.
+    pub fn accepts_incomplete(mut self, accepts_incomplete: bool) -> Self {
+        self.accepts_incomplete = accepts_incomplete;
+        self
+    }
+
+    pub fn body(&self) -> &ServiceInstanceProvisionRequestBody {
+        &self.body
+    }
+
+    pub async fn send(self) -> Result<ServiceInstanceProvisionResponse, ClientError> {
+        self.client
+            .broker
+            .provision(&self.instance_id, self.body, self.accepts_incomplete)
+            .await
+    }
+}
+
+pub struct UpdateRequest<'a> {
+    client: &'a Client,
+    instance_id: String,
+    body: ServiceInstanceUpdateRequestBody,
+    accepts_incomplete: bool,
+}
+
+impl UpdateRequest<'_> {
+    /// New plan for the instance, the current one is kept otherwise.
+    pub fn plan_id(mut self, plan_id: &str) -> Self {
+        self.body.plan_id = Some(plan_id.to_string());
+        self
+    }
+
+    pub fn parameters(mut self, parameters: Value) -> Self {
+        self.body.parameters = Some(parameters);
+        self
+    }
+
+    pub fn context(mut self, context: Value) -> Self {
+        self.body.context = Some(context);
+        self
+    }
+
+    /// Lets the broker update asynchronously, true by default.
+    pub fn accepts_incomplete(mut self, accepts_incomplete: bool) -> Self {
+        self.accepts_incomplete = accepts_incomplete;
+        self
+    }
+
+    pub fn body(&self) -> &ServiceInstanceUpdateRequestBody {
+        &self.body
+    }
+
+    pub async fn send(self) -> Result<AsyncOperation, ClientError> {
+        self.client
+            .broker
+            .update(&self.instance_id, self.body, self.accepts_incomplete)
+            .await
+    }
+}
+
+pub struct DeprovisionRequest<'a> {
+    client: &'a Client,
+    instance_id: String,
+    service_id: String,
+    plan_id: String,
+    accepts_incomplete: bool,
+}
+
+impl DeprovisionRequest<'_> {
+    /// Lets the broker deprovision asynchronously, true by default.
+    pub fn accepts_incomplete(mut self, accepts_incomplete: bool) -> Self {
+        self.accepts_incomplete = accepts_incomplete;
+        self
+    }
+
+    pub async fn send(self) -> Result<AsyncOperation, ClientError> {
+        self.client
+            .broker
+            .deprovision(
+                &self.instance_id,
+                &self.service_id,
+                &self.plan_id,
+                self.accepts_incomplete,
+            )
+            .await
+    }
+}
+
+pub struct BindRequest<'a> {
+    client: &'a Client,
+    instance_id: String,
+    binding_id: String,
+    body: ServiceBindingRequest,
+    accepts_incomplete: bool,
+}
+
+impl BindRequest<'_> {
+    pub fn parameters(mut self, parameters: Value) -> Self {
+        self.body.parameters = Some(parameters);
+        self
+    }
+
+    pub fn context(mut self, context: Value) -> Self {
+        self.body.context = Some(context);
+        self
+    }
+
+    /// Lets the broker bind asynchronously, true by default.
+    pub fn accepts_incomplete(mut self, accepts_incomplete: bool) -> Self {
+        self.accepts_incomplete = accepts_incomplete;
+        self
+    }
+
+    pub fn body(&self) -> &ServiceBindingRequest {
+        &self.body
+    }
+
+    pub async fn send(self) -> Result<ServiceBindingResponse, ClientError> {
+        self.client
+            .broker
+            .bind(
+                &self.instance_id,
+                &self.binding_id,
+                self.body,
+                self.accepts_incomplete,
+            )
+            .await
+    }
+}
+
+pub struct UnbindRequest<'a> {
+    client: &'a Client,
+    instance_id: String,
+    binding_id: String,
+    service_id: String,
+    plan_id: String,
+    accepts_incomplete: bool,
+}
+
+impl UnbindRequest<'_> {
+    /// Lets the broker unbind asynchronously, true by default.
+    pub fn accepts_incomplete(mut self, accepts_incomplete: bool) -> Self {
+        self.accepts_incomplete = accepts_incomplete;
+        self
+    }
+
+    pub async fn send(self) -> Result<AsyncOperation, ClientError> {
+        self.client
+            .broker
+            .unbind(
+                &self.instance_id,
+                &self.binding_id,
+                &self.service_id,
+                &self.plan_id,
+                self.accepts_incomplete,
+            )
+            .await
+    }
+}
+
+pub struct LastOperationRequest<'a> {
+    client: &'a Client,
+    query: LastOperationQuery,
+}
+
+impl LastOperationRequest<'_> {
+    pub fn binding(mut self, binding_id: &str) -> Self {
+        self.query.binding_id = Some(binding_id.to_string());
+        self
+    }
+
+    pub fn service_id(mut self, service_id: &str) -> Self {
+        self.query.service_id = Some(service_id.to_string());
+        self
+    }
+
+    pub fn plan_id(mut self, plan_id: &str) -> Self {
+        self.query.plan_id = Some(plan_id.to_string());
+        self
+    }
+
+    /// Operation token the broker returned when the operation started.
+    pub fn operation(mut self, operation: &str) -> Self {
+        self.query.operation = Some(operation.to_string());
+        self
+    }
+
+    pub fn query(&self) -> &LastOperationQuery {
+        &self.query
+    }
+
+    pub async fn send(&self) -> Result<LastOperationResource, ClientError> {
+        self.client.broker.last_operation(&self.query).await
+    }
+}
+
+/// Why a broker request failed.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The broker answered with an error status.
+    Broker(BrokerError),
+    /// The request did not complete within the configured timeouts.
+    Timeout(TimeoutError),
+    /// The request could not be sent, or its response could not be read.
+    Request { context: String, message: String },
+}
+
+impl ClientError {
+    /// Wraps a rocl API error with the operation that failed.
+    pub fn new<T: Debug>(context: &str, err: ApiError<T>) -> ClientError {
+        match err {
+            ApiError::Reqwest(source) if source.is_timeout() => {
+                ClientError::Timeout(TimeoutError {
+                    context: context.to_string(),
+                    source: Box::new(source),
+                })
+            }
+            ApiError::ResponseError(response) if proxy::is_timeout(&response.content) => {
+                ClientError::Timeout(TimeoutError {
+                    context: context.to_string(),
+                    source: proxy::error_description(&response.content).into(),
+                })
+            }
+            ApiError::ResponseError(response) => ClientError::Broker(BrokerError {
+                context: context.to_string(),
+                status: response.status.as_u16(),
+                content: response.content,
+            }),
+            err => ClientError::Request {
+                context: context.to_string(),
+                message: err.to_string(),
+            },
+        }
+    }
+
+    /// HTTP status of the broker's answer, when it answered with an error.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Broker(e) => Some(e.status),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Broker(e) => write!(f, "{}", e),
+            ClientError::Timeout(e) => write!(f, "{}", e),
+            ClientError::Request { context, message } => write!(f, "{}: {}", context, message),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Broker(e) => Some(e),
+            ClientError::Timeout(e) => Some(e),
+            ClientError::Request { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! In-memory [`Broker`] answering with scripted responses, for tests of code
+//! built on [`Client`](crate::Client) that must not reach a broker over HTTP.
+
+use crate::broker::{Broker, CatalogDocument, LastOperationQuery};
+use crate::client::ClientError;
+use crate::http::BrokerError;
+
+use async_trait::async_trait;
+use rocl::models::{
+    AsyncOperation, LastOperationResource, ServiceBindingRequest, ServiceBindingResource,
+    ServiceBindingResponse, ServiceInstanceProvisionRequestBody, ServiceInstanceProvisionResponse,
+    ServiceInstanceResource, ServiceInstanceUpdateRequestBody,
+};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Answer scripted for the next call of one operation.
+#[derive(Debug)]
+pub enum Reply {
+    Catalog(Result<CatalogDocument, ClientError>),
+    Provision(Result<ServiceInstanceProvisionResponse, ClientError>),
+    Update(Result<AsyncOperation, ClientError>),
+    Deprovision(Result<AsyncOperation, ClientError>),
+    Bind(Result<ServiceBindingResponse, ClientError>),
+    Unbind(Result<AsyncOperation, ClientError>),
+    FetchInstance(Result<ServiceInstanceResource, ClientError>),
+    FetchBinding(Result<ServiceBindingResource, ClientError>),
+    LastOperation(Result<LastOperationResource, ClientError>),
+}
+
+/// A call received by the fake broker.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    Catalog {
+        etag: Option<String>,
+    },
+    Provision {
+        instance_id: String,
+        body: ServiceInstanceProvisionRequestBody,
+        accepts_incomplete: bool,
+    },
+    Update {
+        instance_id: String,
+        body: ServiceInstanceUpdateRequestBody,
+        accepts_incomplete: bool,
+    },
+    Deprovision {
+        instance_id: String,
+        service_id: String,
+        plan_id: String,
+        accepts_incomplete: bool,
+    },
+    Bind {
+        instance_id: String,
+        binding_id: String,
+        body: ServiceBindingRequest,
+        accepts_incomplete: bool,
+    },
+    Unbind {
+        instance_id: String,
+        binding_id: String,
+        service_id: String,
+        plan_id: String,
+        accepts_incomplete: bool,
+    },
+    FetchInstance {
+        instance_id: String,
+    },
+    FetchBinding {
+        instance_id: String,
+        binding_id: String,
+    },
+    LastOperation(LastOperationQuery),
+}
+
+/// Broker answering calls with the replies scripted with [`FakeBroker::reply`],
+/// in order, and recording every call it receives.
+///
+/// The catalog set with [`FakeBroker::serve_catalog`] is served to every catalog
+/// call that has no reply scripted. Calls without a matching reply fail.
+///
+/// ```
+/// # async fn example() {
+/// use rocl::models::{last_operation_resource::State, LastOperationResource};
+/// use rocs::fake::{FakeBroker, Reply};
+/// use std::sync::Arc;
+///
+/// let fake = Arc::new(FakeBroker::new());
+/// fake.reply(Reply::LastOperation(Ok(LastOperationResource::new(State::Succeeded))));
+///
+/// let client = rocs::Client::with_broker(fake.clone());
+/// let last_operation = client.last_operation("instance").send().await.unwrap();
+/// assert_eq!(last_operation.state, State::Succeeded);
+/// assert_eq!(fake.calls().len(), 1);
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct FakeBroker {
+    state: Mutex<Script>,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    catalog: Option<String>,
+    replies: VecDeque<Reply>,
+    calls: Vec<Call>,
+}
+
+impl FakeBroker {
+    pub fn new() -> FakeBroker {
+        FakeBroker::default()
+    }
+
+    /// Catalog document served when no catalog reply is scripted.
+    pub fn serve_catalog(&self, catalog: Value) -> &Self {
+        self.state.lock().unwrap().catalog = Some(catalog.to_string());
+        self
+    }
+
+    /// Scripts the answer to a later call, replies are consumed in order.
+    pub fn reply(&self, reply: Reply) -> &Self {
+        self.state.lock().unwrap().replies.push_back(reply);
+        self
+    }
+
+    /// Calls received so far, oldest first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Replies scripted but not consumed yet.
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().replies.len()
+    }
+
+    fn answer(&self, call: Call) -> Result<Reply, ClientError> {
+        let mut state = self.state.lock().unwrap();
+        let operation = operation(&call);
+        state.calls.push(call);
+
+        let next = state.replies.front().map(reply_operation);
+        if next == Some(operation) {
+            return Ok(state.replies.pop_front().unwrap());
+        }
+        if let (Some(catalog), "catalog") = (&state.catalog, operation) {
+            return Ok(Reply::Catalog(Ok(CatalogDocument::Modified {
+                content: catalog.clone(),
+                etag: None,
+            })));
+        }
+
+        Err(unscripted(&match next {
+            Some(next) => format!("expected a {} call, got {}", next, operation),
+            None => format!("no reply scripted for {}", operation),
+        }))
+    }
+}
+
+/// Error the broker answers with `status` and an OSB error body.
+pub fn broker_error(status: u16, error: &str, description: &str) -> ClientError {
+    ClientError::Broker(BrokerError {
+        context: "fake broker".to_string(),
+        status,
+        content: json!({"error": error, "description": description}).to_string(),
+    })
+}
+
+fn unscripted(message: &str) -> ClientError {
+    ClientError::Request {
+        context: "fake broker".to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn operation(call: &Call) -> &'static str {
+    match call {
+        Call::Catalog { .. } => "catalog",
+        Call::Provision { .. } => "provision",
+        Call::Update { .. } => "update",
+        Call::Deprovision { .. } => "deprovision",
+        Call::Bind { .. } => "bind",
+        Call::Unbind { .. } => "unbind",
+        Call::FetchInstance { .. } => "fetch_instance",
+        Call::FetchBinding { .. } => "fetch_binding",
+        Call::LastOperation(_) => "last_operation",
+    }
+}
+
+fn reply_operation(reply: &Reply) -> &'static str {
+    match reply {
+        Reply::Catalog(_) => "catalog",
+        Reply::Provision(_) => "provision",
+        Reply::Update(_) => "update",
+        Reply::Deprovision(_) => "deprovision",
+        Reply::Bind(_) => "bind",
+        Reply::Unbind(_) => "unbind",
+        Reply::FetchInstance(_) => "fetch_instance",
+        Reply::FetchBinding(_) => "fetch_binding",
+        Reply::LastOperation(_) => "last_operation",
+    }
+}
+
+#[async_trait]
+impl Broker for FakeBroker {
+    async fn catalog(&self, etag: Option<&str>) -> Result<CatalogDocument, ClientError> {
+        match self.answer(Call::Catalog {
+            etag: etag.map(str::to_owned),
+        })? {
+            Reply::Catalog(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn provision(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceProvisionRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceInstanceProvisionResponse, ClientError> {
+        match self.answer(Call::Provision {
+            instance_id: instance_id.to_string(),
+            body,
+            accepts_incomplete,
+        })? {
+            Reply::Provision(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn update(
+        &self,
+        instance_id: &str,
+        body: ServiceInstanceUpdateRequestBody,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        match self.answer(Call::Update {
+            instance_id: instance_id.to_string(),
+            body,
+            accepts_incomplete,
+        })? {
+            Reply::Update(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn deprovision(
+        &self,
+        instance_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        match self.answer(Call::Deprovision {
+            instance_id: instance_id.to_string(),
+            service_id: service_id.to_string(),
+            plan_id: plan_id.to_string(),
+            accepts_incomplete,
+        })? {
+            Reply::Deprovision(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn bind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        body: ServiceBindingRequest,
+        accepts_incomplete: bool,
+    ) -> Result<ServiceBindingResponse, ClientError> {
+        match self.answer(Call::Bind {
+            instance_id: instance_id.to_string(),
+            binding_id: binding_id.to_string(),
+            body,
+            accepts_incomplete,
+        })? {
+            Reply::Bind(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn unbind(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+        service_id: &str,
+        plan_id: &str,
+        accepts_incomplete: bool,
+    ) -> Result<AsyncOperation, ClientError> {
+        match self.answer(Call::Unbind {
+            instance_id: instance_id.to_string(),
+            binding_id: binding_id.to_string(),
+            service_id: service_id.to_string(),
+            plan_id: plan_id.to_string(),
+            accepts_incomplete,
+        })? {
+            Reply::Unbind(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn fetch_instance(
+        &self,
+        instance_id: &str,
+    ) -> Result<ServiceInstanceResource, ClientError> {
+        match self.answer(Call::FetchInstance {
+            instance_id: instance_id.to_string(),
+        })? {
+            Reply::FetchInstance(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn fetch_binding(
+        &self,
+        instance_id: &str,
+        binding_id: &str,
+    ) -> Result<ServiceBindingResource, ClientError> {
+        match self.answer(Call::FetchBinding {
+            instance_id: instance_id.to_string(),
+            binding_id: binding_id.to_string(),
+        })? {
+            Reply::FetchBinding(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn last_operation(
+        &self,
+        query: &LastOperationQuery,
+    ) -> Result<LastOperationResource, ClientError> {
+        match self.answer(Call::LastOperation(query.clone()))? {
+            Reply::LastOperation(result) => result,
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use reqwest::{Certificate, ClientBuilder, Identity, Proxy};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::time::Duration;
+
+/// TLS options applied to every HTTP client rocs builds.
+#[derive(Debug, Default, Clone)]
+pub struct TlsSettings {
+    /// PEM bundle with additional trusted certificate authorities.
+    pub cacert: Option<String>,
+    /// PEM client certificate for mutual TLS.
+    pub cert: Option<String>,
+    /// PEM private key matching `cert`.
+    pub key: Option<String>,
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsSettings {
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder, Box<dyn Error>> {
+        if let Some(cacert) = &self.cacert {
+            let pem = fs::read(cacert).map_err(|e| format!("failed to read {}: {}", cacert, e))?;
+            for cert in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => {
+                let mut pem =
+                    fs::read(cert).map_err(|e| format!("failed to read {}: {}", cert, e))?;
+                pem.push(b'\n');
+                pem.extend(fs::read(key).map_err(|e| format!("failed to read {}: {}", key, e))?);
+                builder = builder.identity(Identity::from_pem(&pem)?);
+            }
+            (None, None) => {}
+            _ => return Err("mutual TLS requires both --cert and --key".into()),
+        }
+
+        if self.insecure_skip_verify {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        Ok(builder)
+    }
+
+    /// cURL flags equivalent to these settings.
+    pub fn curl_flags(&self) -> String {
+        let mut flags = String::new();
+
+        if let Some(cacert) = &self.cacert {
+            flags.push_str(&format!(" --cacert {}", cacert));
+        }
+        if let Some(cert) = &self.cert {
+            flags.push_str(&format!(" --cert {}", cert));
+        }
+        if let Some(key) = &self.key {
+            flags.push_str(&format!(" --key {}", key));
+        }
+        if self.insecure_skip_verify {
+            flags.push_str(" -k");
+        }
+
+        flags
+    }
+}
+
+/// Timeout and proxy options applied to every HTTP client rocs builds.
+#[derive(Debug, Default, Clone)]
+pub struct NetworkSettings {
+    /// Total time allowed for a request, from connecting until the response body is read.
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub proxy: Option<String>,
+    /// Ignores proxies configured in the environment (`HTTP_PROXY`, `HTTPS_PROXY`).
+    pub no_proxy: bool,
+}
+
+impl NetworkSettings {
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder, Box<dyn Error>> {
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if self.no_proxy {
+            builder = builder.no_proxy();
+        } else if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+
+        Ok(builder)
+    }
+
+    /// cURL flags equivalent to these settings.
+    pub fn curl_flags(&self) -> String {
+        let mut flags = String::new();
+
+        if let Some(timeout) = self.timeout {
+            flags.push_str(&format!(" --max-time {}", timeout.as_secs()));
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            flags.push_str(&format!(" --connect-timeout {}", connect_timeout.as_secs()));
+        }
+        if self.no_proxy {
+            flags.push_str(" --noproxy '*'");
+        } else if let Some(proxy) = &self.proxy {
+            flags.push_str(&format!(" --proxy {}", proxy));
+        }
+
+        flags
+    }
+}
+
+/// A broker request that did not complete within the configured timeouts.
+#[derive(Debug)]
+pub struct TimeoutError {
+    pub context: String,
+    /// The client's timeout, or the local proxy's when it forwarded the request.
+    pub source: Box<dyn Error + Send + Sync>,
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: request timed out: {}", self.context, self.source)
+    }
+}
+
+impl Error for TimeoutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// An error response returned by the broker.
+#[derive(Debug)]
+pub struct BrokerError {
+    pub context: String,
+    pub status: u16,
+    pub content: String,
+}
+
+impl BrokerError {
+    /// The OSB error body, when the broker sent one.
+    pub fn body(&self) -> Option<rocl::models::Error> {
+        serde_json::from_str(&self.content).ok()
+    }
+}
+
+impl fmt::Display for BrokerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.content.is_empty() {
+            true => write!(f, "{}: status code {}", self.context, self.status),
+            false => write!(
+                f,
+                "{}: status code {}: {}",
+                self.context, self.status, self.content
+            ),
+        }
+    }
+}
+
+impl Error for BrokerError {}
@@ -1,9 +1,8 @@
 #[macro_use]
 extern crate serde_derive;
-#[macro_use]
-extern crate prettytable;
 extern crate dirs;
 extern crate openapiv3;
+extern crate prettytable;
 extern crate reqwest;
 extern crate rocl;
 extern crate rusqlite;
@@ -12,7 +11,24 @@ extern crate serde_json;
 extern crate uuid;
 extern crate valico;
 
+pub mod auth;
+pub mod batch;
+pub mod broker;
+pub mod cassette;
 pub mod cli;
+pub mod client;
+pub mod fake;
+pub mod http;
+pub mod manifest;
+pub mod mock;
 //pub mod ext;
 pub mod models;
+pub mod operation;
+pub mod profile;
+pub mod proxy;
 pub mod store;
+pub mod trace;
+
+pub use broker::Broker;
+pub use client::{Client, ClientError};
+pub use operation::{wait_for_operation, OperationStream, WaitError, WaitOptions};
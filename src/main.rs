@@ -1,268 +1,184 @@
 extern crate clap;
 extern crate rocl;
 
-use clap::{crate_authors, crate_version, Arg, Command};
-use rocl::apis::configuration::Configuration;
-use rocs::cli;
+use clap::ArgMatches;
+use rocs::cassette::{Player, Recorder};
+use rocs::cli::output::OutputFormat;
+use rocs::profile::{self, Profile};
+use rocs::trace::Tracer;
+use rocs::{cli, Client};
 use std::error::Error;
-use tokio;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let matches = Command::new("rocs")
-        .version(crate_version!())
-        .author(crate_authors!())
-        .about("Rust OSB Client 'Super'")
-        .subcommand_required(true)
-        .arg_required_else_help(true)
-        .arg(
-            Arg::new("broker_url")
-                .short('b')
-                .long("broker")
-                .env("ROCS_BROKER_URL")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::new("broker_user")
-                .short('u')
-                .long("username")
-                .env("ROCS_BROKER_USERNAME")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::new("broker_pass")
-                .short('a')
-                .long("password")
-                .env("ROCS_BROKER_PASSWORD")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::new("json")
-                .help("Prints result in JSON format")
-                .long("json")
-                .takes_value(false)
-                .required(false),
-        )
-        .arg(
-            Arg::new("sync")
-                .help("Execute provisioning and binding synchronously")
-                .long("sync")
-                .takes_value(false)
-                .required(false),
-        )
-        .arg(
-            Arg::new("curl")
-                .help("Prints cURL command")
-                .long("curl")
-                .takes_value(false)
-                .required(false),
-        )
-        .subcommand(
-            Command::new("provision")
-                .about("Service Instance provisioning")
-                .arg(
-                    Arg::new("service")
-                        .short('s')
-                        .long("service")
-                        .takes_value(true)
-                        .required(true)
-                        .help("service offering to use for provision"),
-                )
-                .arg(
-                    Arg::new("plan")
-                        .short('p')
-                        .long("plan")
-                        .takes_value(true)
-                        .required(true)
-                        .help("service plan to use for provision"),
-                )
-                .arg(
-                    Arg::new("parameters")
-                        .short('P')
-                        .long("params")
-                        .multiple_values(true)
-                        .takes_value(true)
-                        .help("parameters to provision service instances. ex: region=us-east-1 other=value"),
-                )
-                .arg(
-                    Arg::new("context")
-                        .short('C')
-                        .long("context")
-                        .multiple_values(true)
-                        .takes_value(true)
-                        .help("context to provision service instances. ex: account_id=123 other=value"),
-                )
-                .arg(
-                    Arg::new("wait")
-                        .short('w')
-                        .long("wait")
-                        .takes_value(false)
-                        .help("wait service instance provisioning to finish"),
-                ),
-        )
-        .subcommand(
-            Command::new("deprovision")
-                .about("Service Instance deprovisioning")
-                .arg(
-                    Arg::new("instance")
-                        .short('i')
-                        .long("instance")
-                        .takes_value(true)
-                        .required(true)
-                        .help("service instance id to deprovision"),
-                ),
-        )
-        .subcommand(
-            Command::new("bind")
-                .about("Service Binding request")
-                .arg(
-                    Arg::new("instance")
-                        .short('i')
-                        .long("instance")
-                        .takes_value(true)
-                        .help("instance ID or name to bind")
-                        .required(true),
-                )
-                .arg(
-                    Arg::new("binding")
-                        .short('b')
-                        .long("binding")
-                        .takes_value(true)
-                        .help("binding ID to fetch if bindings are fetchable")
-                )
-                .arg(
-                    Arg::new("parameters")
-                        .short('P')
-                        .long("params")
-                        .multiple_values(true)
-                        .takes_value(true)
-                        .help("parameters to provision service bindings. ex: param1=value1 param2=value2"),
-                )
-                .arg(
-                    Arg::new("context")
-                        .short('C')
-                        .long("context")
-                        .multiple_values(true)
-                        .takes_value(true)
-                        .help("context to provision service bindings. ex: account_id=123 other=value"),
-                )
-                .arg(
-                    Arg::new("wait")
-                        .short('w')
-                        .long("wait")
-                        .takes_value(false)
-                        .help("wait service binding provisioning to finish")
-                )
-        )
-        .subcommand(
-            Command::new("unbind")
-                .about("Service Binding removal")
-                .arg(
-                    Arg::new("instance")
-                        .short('i')
-                        .long("instance")
-                        .takes_value(true)
-                        .help("instance ID or name to bind")
-                        .required(true),
-                )
-                .arg(
-                    Arg::new("binding")
-                        .short('b')
-                        .long("binding")
-                        .takes_value(true)
-                        .help("Binding ID to unbind")
-                        .required(true),
-                ),
-        )
-        .subcommand(
-            Command::new("catalog")
-                .about("Catalog request")
-                .alias("cat"),
-        )
-        .subcommand(
-            Command::new("info")
-                .about("Fetch Service Instances information")
-                .arg(
-                    Arg::new("instance")
-                        .short('i')
-                        .long("instance")
-                        .takes_value(true)
-                        .help("instance ID to fetch information")
-                        .required(true),
-                )
-        )
-        .subcommand(
-            Command::new("extension")
-                .about("Extension interaction")
-                .alias("ext")
-                .arg(
-                    Arg::new("instance")
-                        .short('i')
-                        .help("Instance ID to access extension")
-                        .takes_value(true)
-                        .required(true)
-                )
-                .arg(
-                    Arg::new("id")
-                        .short('I')
-                        .help("Extension ID")
-                        .takes_value(true)
-                        .required(true)
-                )
-                .arg(
-                    Arg::new("operation")
-                        .short('o')
-                        .help("Operation to perform")
-                        .takes_value(true)
-                        .required_unless_present("list")
-                )
-                .arg(
-                    Arg::new("parameters")
-                        .short('P')
-                        .help("All parameters required for this action including path parameters")
-                        .takes_value(true)
-                        .multiple_values(true)
-                )
-                .arg(
-                    Arg::new("body")
-                        .short('B')
-                        .help("Request body with required fields")
-                        .takes_value(true)
-                )
-                .arg(
-                    Arg::new("list")
-                        .short('l')
-                        .help("List operations and parameters available for an extension")
-                        .takes_value(false)
-                )
-        )
-        .get_matches();
+    let matches = cli::app().get_matches();
 
-    let mut cfg = Configuration::new();
-    cfg.user_agent = Some(cli::USER_AGENT.to_string());
-    cfg.basic_auth = Some((
-        matches.value_of("broker_user").unwrap().to_owned(),
-        Some(matches.value_of("broker_pass").unwrap().to_owned()),
-    ));
-    cfg.base_path = matches.value_of("broker_url").unwrap().to_owned();
+    let request_id = match matches.value_of("request_id") {
+        Some(id) => id.to_owned(),
+        None => Uuid::new_v4().as_hyphenated().to_string(),
+    };
+
+    let profile = match matches.value_of("profile") {
+        Some(name) => profile::load(name)?,
+        None => Profile::default(),
+    };
+
+    // flags and environment variables override the profile
+    let setting = |arg: &str, value: &Option<String>| {
+        matches
+            .value_of(arg)
+            .map(str::to_owned)
+            .or_else(|| value.clone())
+    };
+    let number = |arg: &str, value: Option<u64>| matches.get_one::<u64>(arg).copied().or(value);
+    let profile = Profile {
+        broker_url: setting("broker_url", &profile.broker_url),
+        username: setting("broker_user", &profile.username),
+        password: setting("broker_pass", &profile.password),
+        auth: match matches.value_of("auth") {
+            Some(mode) => Some(mode.parse()?),
+            None => profile.auth,
+        },
+        token: setting("token", &profile.token),
+        token_url: setting("token_url", &profile.token_url),
+        client_id: setting("client_id", &profile.client_id),
+        client_secret: setting("client_secret", &profile.client_secret),
+        scope: setting("scope", &profile.scope),
+        cacert: setting("cacert", &profile.cacert),
+        cert: setting("cert", &profile.cert),
+        key: setting("key", &profile.key),
+        insecure_skip_verify: Some(
+            matches.is_present("insecure_skip_verify")
+                || profile.insecure_skip_verify.unwrap_or(false),
+        ),
+        timeout: number("timeout", profile.timeout),
+        connect_timeout: number("connect_timeout", profile.connect_timeout),
+        proxy: setting("proxy", &profile.proxy),
+        // an explicit proxy wins over the profile's no_proxy
+        no_proxy: Some(
+            matches.is_present("no_proxy")
+                || (profile.no_proxy.unwrap_or(false) && matches.value_of("proxy").is_none()),
+        ),
+        catalog_ttl: number("catalog_ttl", profile.catalog_ttl),
+    };
+
+    // the mock broker serves requests instead of sending them
+    if let Some(mock_matches) = matches.subcommand_matches("mock-broker") {
+        let credentials = profile
+            .username
+            .clone()
+            .map(|username| (username, profile.password.clone().unwrap_or_default()));
+        if let Err(e) = cli::mock_broker(mock_matches, credentials).await {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let tls = profile.tls();
+    if tls.insecure_skip_verify {
+        eprintln!("[WARN] ****************************************************************");
+        eprintln!("[WARN] TLS certificate verification is DISABLED (--insecure-skip-verify)");
+        eprintln!("[WARN] connections to the broker can be intercepted, do not use this");
+        eprintln!("[WARN] outside of development environments");
+        eprintln!("[WARN] ****************************************************************");
+    }
+
+    // the catalog must be part of cassettes, not come from the local cache
+    let cassette = matches.is_present("record") || matches.is_present("replay");
 
     let options = cli::Options {
-        json_output: matches.is_present("json"),
+        output: match matches.is_present("json") {
+            true => OutputFormat::Json,
+            false => matches.get_one::<OutputFormat>("output").unwrap().clone(),
+        },
         curl_output: matches.is_present("curl"),
         synchronous: matches.is_present("sync"),
+        request_id: request_id.clone(),
+        auth_mode: profile.auth_mode(),
+        tls,
+        network: profile.network(),
+        broker_url: profile.broker_url.clone().unwrap_or_default(),
+        catalog: cli::CatalogSettings {
+            ttl: Duration::from_secs(profile.catalog_ttl.unwrap_or(cli::DEFAULT_CATALOG_TTL)),
+            refresh: matches.is_present("refresh_catalog") || cassette,
+            offline: matches.is_present("offline"),
+            cache_key: cli::catalog_cache_key(&profile),
+        },
+        tracer: Arc::new(Tracer::new(
+            matches.occurrences_of("verbose"),
+            matches.value_of("trace_file").map(str::to_owned),
+        )),
+    };
+
+    let command = matches.subcommand_name().unwrap_or_default().to_owned();
+    let curl_output = options.curl_output;
+    let output_format = options.output.clone();
+
+    let result = match cli::is_local(&matches) {
+        true => run_local(&matches, options).await,
+        false => {
+            let recorder = matches.value_of("record").map(Recorder::new).transpose()?;
+            let player = matches.value_of("replay").map(Player::new).transpose()?;
+            let client = cli::connect(&profile, &options, recorder, player).await?;
+            run(&matches, client, options).await
+        }
     };
 
+    if !curl_output && cli::records_history(&command) {
+        let sub_matches = matches.subcommand_matches(&command);
+        let instance_id = sub_matches
+            .and_then(|m| m.try_get_one::<String>("instance").ok().flatten())
+            .cloned()
+            .unwrap_or_default();
+        let binding_id = sub_matches
+            .and_then(|m| m.try_get_one::<String>("binding").ok().flatten())
+            .cloned()
+            .unwrap_or_default();
+
+        cli::record_operation(&request_id, &command, &instance_id, &binding_id, &result);
+    }
+
+    if let Err(e) = result {
+        if let Some(status) = e.downcast_ref::<cli::ExitStatus>() {
+            std::process::exit(status.code);
+        }
+        match output_format {
+            OutputFormat::Table | OutputFormat::Wide => {
+                eprintln!("[ERROR] {} (request id: {})", e, request_id)
+            }
+            _ => cli::output::print(&cli::error_output(&*e, &request_id), &output_format)?,
+        }
+        if cli::is_timeout(&*e) {
+            std::process::exit(cli::EXIT_TIMEOUT);
+        }
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn run(
+    matches: &ArgMatches,
+    client: Client,
+    options: cli::Options,
+) -> Result<(), Box<dyn Error>> {
     match matches.subcommand_name() {
         Some("catalog") => {
-            cli::catalog(matches.subcommand_matches("catalog").unwrap(), cfg, options).await
+            cli::catalog(
+                matches.subcommand_matches("catalog").unwrap(),
+                client,
+                options,
+            )
+            .await
         }
         Some("provision") => {
             cli::provision(
                 matches.subcommand_matches("provision").unwrap(),
-                cfg,
+                client,
                 options,
             )
             .await
@@ -270,16 +186,68 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Some("deprovision") => {
             cli::deprovision(
                 matches.subcommand_matches("deprovision").unwrap(),
-                cfg,
+                client,
                 options,
             )
             .await
         }
-        Some("bind") => cli::bind(matches.subcommand_matches("bind").unwrap(), cfg, options).await,
+        Some("bind") => {
+            cli::bind(matches.subcommand_matches("bind").unwrap(), client, options).await
+        }
         Some("unbind") => {
-            cli::unbind(matches.subcommand_matches("unbind").unwrap(), cfg, options).await
+            cli::unbind(
+                matches.subcommand_matches("unbind").unwrap(),
+                client,
+                options,
+            )
+            .await
+        }
+        Some("info") => {
+            cli::info(matches.subcommand_matches("info").unwrap(), client, options).await
+        }
+        Some("describe") => {
+            cli::describe(
+                matches.subcommand_matches("describe").unwrap(),
+                client,
+                options,
+            )
+            .await
+        }
+        Some("status") => {
+            cli::status(
+                matches.subcommand_matches("status").unwrap(),
+                client,
+                options,
+            )
+            .await
+        }
+        Some("conformance") => {
+            cli::conformance(
+                matches.subcommand_matches("conformance").unwrap(),
+                client,
+                options,
+            )
+            .await
+        }
+        Some("plan") => {
+            cli::plan(matches.subcommand_matches("plan").unwrap(), client, options).await
+        }
+        Some("apply") => {
+            cli::apply(
+                matches.subcommand_matches("apply").unwrap(),
+                client,
+                options,
+            )
+            .await
+        }
+        Some("batch") => {
+            cli::batch(
+                matches.subcommand_matches("batch").unwrap(),
+                client,
+                options,
+            )
+            .await
         }
-        Some("info") => cli::info(matches.subcommand_matches("info").unwrap(), cfg, options).await,
         /*Some("extension") => ext::command(
             matches.subcommand_matches("extension").unwrap(),
             client,
@@ -289,3 +257,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
         _ => Err(Box::from("unknown command")),
     }
 }
+
+/// Commands reading only the local store or files, see [`cli::is_local`].
+async fn run_local(matches: &ArgMatches, options: cli::Options) -> Result<(), Box<dyn Error>> {
+    match matches.subcommand() {
+        Some(("instances", m)) => cli::instances(m, options).await,
+        Some(("bindings", m)) => cli::bindings(m, options).await,
+        Some(("catalog", m)) => cli::catalog_local(m, options).await,
+        _ => Err(Box::from("unknown command")),
+    }
+}
@@ -0,0 +1,259 @@
+use crate::store;
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+
+/// State of stored instances and bindings whose last operation failed.
+const FAILED: &str = "failed";
+
+/// Instances and bindings an environment should have, applied with `rocs apply`.
+/// Entries are matched with the local store by name.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(rename = "instances", default)]
+    pub instances: Vec<ManifestInstance>,
+    #[serde(rename = "bindings", default)]
+    pub bindings: Vec<ManifestBinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestInstance {
+    #[serde(rename = "name")]
+    pub name: String,
+    /// Service offering name or ID.
+    #[serde(rename = "service")]
+    pub service: String,
+    /// Plan name or ID.
+    #[serde(rename = "plan")]
+    pub plan: String,
+    #[serde(rename = "parameters", skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+    /// Sent when the instance is provisioned or updated, it is not compared.
+    #[serde(rename = "context", skip_serializing_if = "Option::is_none")]
+    pub context: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestBinding {
+    #[serde(rename = "name")]
+    pub name: String,
+    /// Name of an instance of the manifest.
+    #[serde(rename = "instance")]
+    pub instance: String,
+    #[serde(rename = "parameters", skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+    #[serde(rename = "context", skip_serializing_if = "Option::is_none")]
+    pub context: Option<Value>,
+}
+
+/// A step needed to converge the local store with a manifest, in the order
+/// they are returned by [`Manifest::changes`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Provision(ManifestInstance),
+    Update {
+        instance_id: String,
+        instance: ManifestInstance,
+        /// Fields that differ from the stored instance.
+        fields: Vec<String>,
+    },
+    Unbind {
+        binding_id: String,
+        instance_id: String,
+        name: String,
+    },
+    Bind(ManifestBinding),
+    Deprovision {
+        instance_id: String,
+        name: String,
+    },
+}
+
+impl Manifest {
+    /// Reads a manifest from a YAML (or JSON) file and checks its references.
+    pub fn load(path: &str) -> Result<Manifest, Box<dyn Error>> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        let manifest: Manifest =
+            serde_yaml::from_str(&content).map_err(|e| format!("invalid manifest: {}", e))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let mut instances = HashSet::new();
+        for instance in &self.instances {
+            if !instances.insert(instance.name.as_str()) {
+                return Err(format!("instance {} is declared twice", instance.name).into());
+            }
+        }
+
+        let mut bindings = HashSet::new();
+        for binding in &self.bindings {
+            if !bindings.insert(binding.name.as_str()) {
+                return Err(format!("binding {} is declared twice", binding.name).into());
+            }
+            if !instances.contains(binding.instance.as_str()) {
+                return Err(format!(
+                    "binding {} refers to instance {} which is not in the manifest",
+                    binding.name, binding.instance
+                )
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Steps turning the stored `instances` and `bindings` into the manifest.
+    ///
+    /// Only named entries, made by a previous apply, are compared. Entries
+    /// missing from the manifest are removed only with `prune`, and removing
+    /// an instance also unbinds every binding it has. Entries whose last
+    /// operation failed are removed first, then made again.
+    pub fn changes(
+        &self,
+        instances: &[store::Instance],
+        bindings: &[store::Binding],
+        prune: bool,
+    ) -> Result<Vec<Change>, Box<dyn Error>> {
+        let stored: HashMap<&str, &store::Instance> = instances
+            .iter()
+            .filter_map(|i| i.name.as_deref().map(|name| (name, i)))
+            .collect();
+        let wanted: HashSet<&str> = self.instances.iter().map(|i| i.name.as_str()).collect();
+
+        let mut cleanups = Vec::new();
+        let mut provisions = Vec::new();
+        let mut updates = Vec::new();
+        let mut unbinds = Vec::new();
+        let mut binds = Vec::new();
+        let mut deprovisions = Vec::new();
+
+        for instance in &self.instances {
+            let current = match stored.get(instance.name.as_str()) {
+                Some(current) => current,
+                None => {
+                    provisions.push(Change::Provision(instance.clone()));
+                    continue;
+                }
+            };
+
+            if current.state == FAILED {
+                cleanups.push(Change::Deprovision {
+                    instance_id: current.instance_id.clone(),
+                    name: instance.name.clone(),
+                });
+                provisions.push(Change::Provision(instance.clone()));
+                continue;
+            }
+
+            if instance.service != current.service_name && instance.service != current.service_id {
+                return Err(format!(
+                    "instance {} cannot change service from {} to {}, remove it first",
+                    instance.name, current.service_name, instance.service
+                )
+                .into());
+            }
+
+            let mut fields = Vec::new();
+            if instance.plan != current.plan_name && instance.plan != current.plan_id {
+                fields.push("plan".to_string());
+            }
+            let parameters = instance.parameters.clone().unwrap_or_else(|| json!({}));
+            if serde_json::from_str::<Value>(&current.parameters).ok() != Some(parameters) {
+                fields.push("parameters".to_string());
+            }
+            if !fields.is_empty() {
+                updates.push(Change::Update {
+                    instance_id: current.instance_id.clone(),
+                    instance: instance.clone(),
+                    fields,
+                });
+            }
+        }
+
+        let instance_name = |instance_id: &str| {
+            instances
+                .iter()
+                .find(|i| i.instance_id == instance_id)
+                .and_then(|i| i.name.as_deref())
+        };
+        let failed: HashSet<&str> = instances
+            .iter()
+            .filter(|i| i.state == FAILED && i.name.as_deref().is_some_and(|n| wanted.contains(n)))
+            .map(|i| i.instance_id.as_str())
+            .collect();
+        let mut bound = HashSet::new();
+
+        for binding in bindings {
+            // bindings of failed instances go away with them, and are made again
+            if failed.contains(binding.instance_id.as_str()) {
+                continue;
+            }
+            let instance = instance_name(&binding.instance_id);
+            let desired = binding
+                .name
+                .as_ref()
+                .and_then(|name| self.bindings.iter().find(|b| &b.name == name));
+
+            let unbind = match desired {
+                // made again once removed, see the binds below
+                Some(_) if binding.state == FAILED => true,
+                Some(desired) if Some(desired.instance.as_str()) == instance => {
+                    bound.insert(desired.name.as_str());
+                    false
+                }
+                // moved to another instance, it is replaced even without prune
+                Some(_) => true,
+                None if binding.name.is_some() => prune,
+                // bindings made with `bind` only go away with their instance
+                None => prune && instance.is_some_and(|name| !wanted.contains(name)),
+            };
+            if unbind {
+                unbinds.push(Change::Unbind {
+                    binding_id: binding.binding_id.clone(),
+                    instance_id: binding.instance_id.clone(),
+                    name: binding
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| binding.binding_id.clone()),
+                });
+            }
+        }
+
+        for binding in &self.bindings {
+            if !bound.contains(binding.name.as_str()) {
+                binds.push(Change::Bind(binding.clone()));
+            }
+        }
+
+        if prune {
+            for instance in instances {
+                match &instance.name {
+                    Some(name) if !wanted.contains(name.as_str()) => {
+                        deprovisions.push(Change::Deprovision {
+                            instance_id: instance.instance_id.clone(),
+                            name: name.clone(),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(cleanups
+            .into_iter()
+            .chain(provisions)
+            .chain(updates)
+            .chain(unbinds)
+            .chain(binds)
+            .chain(deprovisions)
+            .collect())
+    }
+}
@@ -0,0 +1,748 @@
+use hyper::header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use rand::Rng;
+use reqwest::Url;
+use rocl::models::{Catalog, Plan, Service};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use valico::json_schema::Scope;
+
+/// In-memory OSB broker serving a catalog, for local development and end-to-end tests.
+///
+/// Instances and bindings only live as long as the process. Asynchronous
+/// requests (`accepts_incomplete=true`) stay in progress for `delay`, and
+/// every operation fails with probability `failure_rate`.
+pub struct MockBroker {
+    /// Catalog document served as is, its services and plans validate requests.
+    pub catalog: Value,
+    pub delay: Duration,
+    /// Between 0 (never fails) and 1 (always fails).
+    pub failure_rate: f64,
+    /// Basic auth credentials clients must send, any request is accepted without them.
+    pub credentials: Option<(String, String)>,
+}
+
+struct Mock {
+    broker: MockBroker,
+    services: Vec<Service>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    instances: HashMap<String, Instance>,
+    /// Keyed by instance and binding ID.
+    bindings: HashMap<(String, String), Binding>,
+    /// Deleted resources, answered with 410 Gone.
+    gone_instances: HashSet<String>,
+    gone_bindings: HashSet<(String, String)>,
+}
+
+struct Instance {
+    service_id: String,
+    plan_id: String,
+    parameters: Option<Value>,
+    /// False until provisioning succeeded.
+    ready: bool,
+    operation: Option<Operation>,
+}
+
+struct Binding {
+    service_id: String,
+    plan_id: String,
+    parameters: Option<Value>,
+    credentials: Value,
+    ready: bool,
+    operation: Option<Operation>,
+}
+
+struct Operation {
+    id: String,
+    kind: OperationKind,
+    done_at: Instant,
+    fails: bool,
+    /// Whether the outcome was applied to the resource.
+    applied: bool,
+}
+
+enum OperationKind {
+    Provision,
+    Update {
+        plan_id: String,
+        parameters: Option<Value>,
+    },
+    Deprovision,
+    Bind,
+    Unbind,
+}
+
+impl Operation {
+    fn new(kind: OperationKind, delay: Duration, fails: bool) -> Operation {
+        Operation {
+            id: Uuid::new_v4().as_simple().to_string(),
+            kind,
+            done_at: Instant::now() + delay,
+            fails,
+            applied: false,
+        }
+    }
+
+    fn in_progress(&self) -> bool {
+        Instant::now() < self.done_at
+    }
+
+    fn last_operation(&self) -> Value {
+        match (self.in_progress(), self.fails) {
+            (true, _) => json!({"state": "in progress", "description": "operation in progress"}),
+            (false, true) => json!({"state": "failed", "description": "simulated failure"}),
+            (false, false) => json!({"state": "succeeded", "description": "operation succeeded"}),
+        }
+    }
+}
+
+type Reply = (StatusCode, Value);
+
+/// Starts the broker on `address` and returns its base URL. The server runs
+/// in the background until the runtime shuts down.
+pub async fn start(broker: MockBroker, address: SocketAddr) -> Result<String, Box<dyn Error>> {
+    let catalog: Catalog = serde_json::from_value(broker.catalog.clone())
+        .map_err(|e| format!("invalid catalog: {}", e))?;
+    if !(0.0..=1.0).contains(&broker.failure_rate) {
+        return Err("failure rate must be between 0 and 1".into());
+    }
+
+    let mock = Arc::new(Mock {
+        broker,
+        services: catalog.services.unwrap_or_default(),
+        state: Mutex::new(State::default()),
+    });
+    let make_service = make_service_fn(move |_| {
+        let mock = mock.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let mock = mock.clone();
+                async move { Ok::<_, Infallible>(handle(&mock, req).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&address)?.serve(make_service);
+    let address = server.local_addr();
+    tokio::spawn(server);
+
+    Ok(format!("http://{}", address))
+}
+
+async fn handle(mock: &Mock, req: Request<Body>) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let (status, reply) = match hyper::body::to_bytes(body).await {
+        Ok(body) => mock.route(&parts.method, &parts.uri, &parts.headers, &body),
+        Err(e) => error(StatusCode::BAD_REQUEST, None, &e.to_string()),
+    };
+
+    eprintln!("[INFO] {} {} {}", parts.method, parts.uri, status.as_u16());
+
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(reply.to_string()))
+        .unwrap()
+}
+
+fn error(status: StatusCode, code: Option<&str>, description: &str) -> Reply {
+    match code {
+        Some(code) => (status, json!({"error": code, "description": description})),
+        None => (status, json!({ "description": description })),
+    }
+}
+
+fn bad_request(description: &str) -> Reply {
+    error(StatusCode::BAD_REQUEST, None, description)
+}
+
+fn concurrency_error() -> Reply {
+    error(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Some("ConcurrencyError"),
+        "another operation is in progress",
+    )
+}
+
+fn simulated_failure() -> Reply {
+    error(StatusCode::INTERNAL_SERVER_ERROR, None, "simulated failure")
+}
+
+impl Mock {
+    fn route(&self, method: &Method, uri: &hyper::Uri, headers: &HeaderMap, body: &[u8]) -> Reply {
+        let version = headers
+            .get("X-Broker-API-Version")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if !version.starts_with("2.") {
+            return error(
+                StatusCode::PRECONDITION_FAILED,
+                None,
+                &format!("unsupported X-Broker-API-Version {:?}", version),
+            );
+        }
+        if !self.authorized(headers) {
+            return error(StatusCode::UNAUTHORIZED, None, "invalid credentials");
+        }
+
+        let url = match Url::parse(&format!("http://mock{}", uri)) {
+            Ok(url) => url,
+            Err(e) => return bad_request(&e.to_string()),
+        };
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let accepts_incomplete =
+            query.get("accepts_incomplete").map(String::as_str) == Some("true");
+        let body: Value = match body.is_empty() {
+            true => Value::Null,
+            false => match serde_json::from_slice(body) {
+                Ok(body) => body,
+                Err(e) => return bad_request(&format!("invalid JSON body: {}", e)),
+            },
+        };
+
+        let segments: Vec<&str> = url.path().trim_matches('/').split('/').collect();
+        let mut state = self.state.lock().unwrap();
+        self.settle(&mut state);
+
+        match (method, segments.as_slice()) {
+            (&Method::GET, ["v2", "catalog"]) => (StatusCode::OK, self.broker.catalog.clone()),
+            (&Method::PUT, ["v2", "service_instances", id]) => {
+                self.provision(&mut state, id, &body, accepts_incomplete)
+            }
+            (&Method::PATCH, ["v2", "service_instances", id]) => {
+                self.update(&mut state, id, &body, accepts_incomplete)
+            }
+            (&Method::DELETE, ["v2", "service_instances", id]) => {
+                self.deprovision(&mut state, id, &query, accepts_incomplete)
+            }
+            (&Method::GET, ["v2", "service_instances", id]) => match state.instances.get(*id) {
+                Some(instance) if instance.ready => (
+                    StatusCode::OK,
+                    json!({
+                        "service_id": instance.service_id,
+                        "plan_id": instance.plan_id,
+                        "parameters": instance.parameters,
+                    }),
+                ),
+                _ => error(StatusCode::NOT_FOUND, None, "instance not found"),
+            },
+            (&Method::GET, ["v2", "service_instances", id, "last_operation"]) => {
+                match state.instances.get(*id) {
+                    Some(instance) => last_operation(&instance.operation),
+                    None if state.gone_instances.contains(*id) => (StatusCode::GONE, json!({})),
+                    None => error(StatusCode::NOT_FOUND, None, "instance not found"),
+                }
+            }
+            (&Method::PUT, ["v2", "service_instances", id, "service_bindings", binding_id]) => {
+                self.bind(&mut state, id, binding_id, &body, accepts_incomplete)
+            }
+            (&Method::DELETE, ["v2", "service_instances", id, "service_bindings", binding_id]) => {
+                self.unbind(&mut state, id, binding_id, &query, accepts_incomplete)
+            }
+            (&Method::GET, ["v2", "service_instances", id, "service_bindings", binding_id]) => {
+                match state.bindings.get(&key(id, binding_id)) {
+                    Some(binding) if binding.ready => (
+                        StatusCode::OK,
+                        json!({
+                            "credentials": binding.credentials,
+                            "parameters": binding.parameters,
+                        }),
+                    ),
+                    _ => error(StatusCode::NOT_FOUND, None, "binding not found"),
+                }
+            }
+            (
+                &Method::GET,
+                ["v2", "service_instances", id, "service_bindings", binding_id, "last_operation"],
+            ) => {
+                let key = key(id, binding_id);
+                match state.bindings.get(&key) {
+                    Some(binding) => last_operation(&binding.operation),
+                    None if state.gone_bindings.contains(&key) => (StatusCode::GONE, json!({})),
+                    None => error(StatusCode::NOT_FOUND, None, "binding not found"),
+                }
+            }
+            _ => error(StatusCode::NOT_FOUND, None, "unknown endpoint"),
+        }
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let (username, password) = match &self.broker.credentials {
+            Some(credentials) => credentials,
+            None => return true,
+        };
+        let expected = format!(
+            "Basic {}",
+            base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                format!("{}:{}", username, password)
+            )
+        );
+        headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok()) == Some(expected.as_str())
+    }
+
+    fn fails(&self) -> bool {
+        rand::thread_rng().gen_bool(self.broker.failure_rate)
+    }
+
+    /// Applies the outcome of asynchronous operations that finished.
+    fn settle(&self, state: &mut State) {
+        let mut deleted = Vec::new();
+        for (id, instance) in state.instances.iter_mut() {
+            let operation = match &mut instance.operation {
+                Some(operation) if !operation.applied && !operation.in_progress() => operation,
+                _ => continue,
+            };
+            operation.applied = true;
+            if operation.fails {
+                continue;
+            }
+            match &operation.kind {
+                OperationKind::Provision => instance.ready = true,
+                OperationKind::Update {
+                    plan_id,
+                    parameters,
+                } => {
+                    instance.plan_id = plan_id.clone();
+                    instance.parameters = parameters.clone();
+                }
+                OperationKind::Deprovision => deleted.push(id.clone()),
+                _ => {}
+            }
+        }
+        for id in deleted {
+            state.instances.remove(&id);
+            state
+                .bindings
+                .retain(|(instance_id, _), _| *instance_id != id);
+            state.gone_instances.insert(id);
+        }
+
+        let mut deleted = Vec::new();
+        for (key, binding) in state.bindings.iter_mut() {
+            let operation = match &mut binding.operation {
+                Some(operation) if !operation.applied && !operation.in_progress() => operation,
+                _ => continue,
+            };
+            operation.applied = true;
+            match (&operation.kind, operation.fails) {
+                (OperationKind::Bind, false) => binding.ready = true,
+                (OperationKind::Unbind, false) => deleted.push(key.clone()),
+                _ => {}
+            }
+        }
+        for key in deleted {
+            state.bindings.remove(&key);
+            state.gone_bindings.insert(key);
+        }
+    }
+
+    fn provision(
+        &self,
+        state: &mut State,
+        id: &str,
+        body: &Value,
+        accepts_incomplete: bool,
+    ) -> Reply {
+        let (service, plan) = match self.service_plan(body) {
+            Ok(found) => found,
+            Err(reply) => return reply,
+        };
+        let parameters = body.get("parameters").cloned();
+        if let Err(reply) = validate(&plan, Schema::Create, &parameters) {
+            return reply;
+        }
+
+        if let Some(instance) = state.instances.get(id) {
+            return match instance.service_id == service.id
+                && instance.plan_id == plan.id
+                && instance.parameters == parameters
+            {
+                true if instance.ready => (StatusCode::OK, json!({})),
+                true => accepted(&instance.operation),
+                false => error(
+                    StatusCode::CONFLICT,
+                    None,
+                    "instance exists with other attributes",
+                ),
+            };
+        }
+
+        let fails = self.fails();
+        let mut instance = Instance {
+            service_id: service.id.clone(),
+            plan_id: plan.id.clone(),
+            parameters,
+            ready: !accepts_incomplete,
+            operation: None,
+        };
+
+        if accepts_incomplete {
+            let operation = Operation::new(OperationKind::Provision, self.broker.delay, fails);
+            let reply = (StatusCode::ACCEPTED, json!({"operation": operation.id}));
+            instance.operation = Some(operation);
+            state.instances.insert(id.to_string(), instance);
+            return reply;
+        }
+        if fails {
+            return simulated_failure();
+        }
+        state.gone_instances.remove(id);
+        state.instances.insert(id.to_string(), instance);
+        (StatusCode::CREATED, json!({}))
+    }
+
+    fn update(&self, state: &mut State, id: &str, body: &Value, accepts_incomplete: bool) -> Reply {
+        let fails = self.fails();
+        let instance = match state.instances.get_mut(id) {
+            Some(instance) if instance.ready => instance,
+            _ => return error(StatusCode::NOT_FOUND, None, "instance not found"),
+        };
+        if instance
+            .operation
+            .as_ref()
+            .is_some_and(Operation::in_progress)
+        {
+            return concurrency_error();
+        }
+
+        match body.get("service_id").and_then(Value::as_str) {
+            Some(service_id) if service_id == instance.service_id => {}
+            Some(_) => return bad_request("service_id does not match the instance"),
+            None => return bad_request("service_id is required"),
+        }
+        let service = self.service(&instance.service_id).unwrap();
+        let plan_id = match body.get("plan_id").and_then(Value::as_str) {
+            Some(plan_id) => plan_id,
+            None => instance.plan_id.as_str(),
+        };
+        let plan = match service.plans.iter().find(|p| p.id == plan_id) {
+            Some(plan) => plan,
+            None => return bad_request(&format!("unknown plan_id {}", plan_id)),
+        };
+        if plan.id != instance.plan_id && service.plan_updateable != Some(true) {
+            return error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                None,
+                "the service does not support plan changes",
+            );
+        }
+        let parameters = match body.get("parameters") {
+            Some(parameters) => Some(parameters.clone()),
+            None => instance.parameters.clone(),
+        };
+        if let Err(reply) = validate(plan, Schema::Update, &body.get("parameters").cloned()) {
+            return reply;
+        }
+
+        if accepts_incomplete {
+            let kind = OperationKind::Update {
+                plan_id: plan.id.clone(),
+                parameters,
+            };
+            let operation = Operation::new(kind, self.broker.delay, fails);
+            let reply = (StatusCode::ACCEPTED, json!({"operation": operation.id}));
+            instance.operation = Some(operation);
+            return reply;
+        }
+        if fails {
+            return simulated_failure();
+        }
+        instance.plan_id = plan.id.clone();
+        instance.parameters = parameters;
+        (StatusCode::OK, json!({}))
+    }
+
+    fn deprovision(
+        &self,
+        state: &mut State,
+        id: &str,
+        query: &HashMap<String, String>,
+        accepts_incomplete: bool,
+    ) -> Reply {
+        let fails = self.fails();
+        let instance = match state.instances.get_mut(id) {
+            Some(instance) => instance,
+            None => return (StatusCode::GONE, json!({})),
+        };
+        if instance
+            .operation
+            .as_ref()
+            .is_some_and(Operation::in_progress)
+        {
+            return concurrency_error();
+        }
+        if let Err(reply) = matching_ids(query, &instance.service_id, &instance.plan_id) {
+            return reply;
+        }
+
+        if accepts_incomplete {
+            let operation = Operation::new(OperationKind::Deprovision, self.broker.delay, fails);
+            let reply = (StatusCode::ACCEPTED, json!({"operation": operation.id}));
+            instance.operation = Some(operation);
+            return reply;
+        }
+        if fails {
+            return simulated_failure();
+        }
+        state.instances.remove(id);
+        state
+            .bindings
+            .retain(|(instance_id, _), _| instance_id != id);
+        state.gone_instances.insert(id.to_string());
+        (StatusCode::OK, json!({}))
+    }
+
+    fn bind(
+        &self,
+        state: &mut State,
+        id: &str,
+        binding_id: &str,
+        body: &Value,
+        accepts_incomplete: bool,
+    ) -> Reply {
+        let instance = match state.instances.get(id) {
+            Some(instance) if instance.ready => instance,
+            _ => return error(StatusCode::NOT_FOUND, None, "instance not found"),
+        };
+        if instance
+            .operation
+            .as_ref()
+            .is_some_and(Operation::in_progress)
+        {
+            return concurrency_error();
+        }
+        let (service, plan) = match self.service_plan(body) {
+            Ok(found) => found,
+            Err(reply) => return reply,
+        };
+        if service.id != instance.service_id || plan.id != instance.plan_id {
+            return bad_request("service_id and plan_id do not match the instance");
+        }
+        if !plan.bindable.unwrap_or(service.bindable) {
+            return bad_request("the plan is not bindable");
+        }
+        let parameters = body.get("parameters").cloned();
+        if let Err(reply) = validate(&plan, Schema::Bind, &parameters) {
+            return reply;
+        }
+
+        let key = key(id, binding_id);
+        if let Some(binding) = state.bindings.get(&key) {
+            return match binding.parameters == parameters {
+                true if binding.ready => {
+                    (StatusCode::OK, json!({"credentials": binding.credentials}))
+                }
+                true => accepted(&binding.operation),
+                false => error(
+                    StatusCode::CONFLICT,
+                    None,
+                    "binding exists with other attributes",
+                ),
+            };
+        }
+
+        let fails = self.fails();
+        let mut binding = Binding {
+            service_id: service.id.clone(),
+            plan_id: plan.id.clone(),
+            parameters,
+            credentials: json!({
+                "username": binding_id,
+                "password": Uuid::new_v4().as_simple().to_string(),
+            }),
+            ready: !accepts_incomplete,
+            operation: None,
+        };
+
+        if accepts_incomplete {
+            let operation = Operation::new(OperationKind::Bind, self.broker.delay, fails);
+            let reply = (StatusCode::ACCEPTED, json!({"operation": operation.id}));
+            binding.operation = Some(operation);
+            state.bindings.insert(key, binding);
+            return reply;
+        }
+        if fails {
+            return simulated_failure();
+        }
+        let reply = (
+            StatusCode::CREATED,
+            json!({"credentials": binding.credentials}),
+        );
+        state.gone_bindings.remove(&key);
+        state.bindings.insert(key, binding);
+        reply
+    }
+
+    fn unbind(
+        &self,
+        state: &mut State,
+        id: &str,
+        binding_id: &str,
+        query: &HashMap<String, String>,
+        accepts_incomplete: bool,
+    ) -> Reply {
+        let fails = self.fails();
+        let key = key(id, binding_id);
+        // the instance may have changed plan since the binding was made
+        let instance_plan_id = state.instances.get(id).map(|i| i.plan_id.clone());
+        let binding = match state.bindings.get_mut(&key) {
+            Some(binding) => binding,
+            None => return (StatusCode::GONE, json!({})),
+        };
+        if binding
+            .operation
+            .as_ref()
+            .is_some_and(Operation::in_progress)
+        {
+            return concurrency_error();
+        }
+        let plan_id = instance_plan_id.as_ref().unwrap_or(&binding.plan_id);
+        if let Err(reply) = matching_ids(query, &binding.service_id, plan_id) {
+            return reply;
+        }
+
+        if accepts_incomplete {
+            let operation = Operation::new(OperationKind::Unbind, self.broker.delay, fails);
+            let reply = (StatusCode::ACCEPTED, json!({"operation": operation.id}));
+            binding.operation = Some(operation);
+            return reply;
+        }
+        if fails {
+            return simulated_failure();
+        }
+        state.bindings.remove(&key);
+        state.gone_bindings.insert(key);
+        (StatusCode::OK, json!({}))
+    }
+
+    fn service(&self, service_id: &str) -> Option<&Service> {
+        self.services.iter().find(|s| s.id == service_id)
+    }
+
+    /// Service and plan named by the `service_id` and `plan_id` of a request body.
+    fn service_plan(&self, body: &Value) -> Result<(&Service, Plan), Reply> {
+        let field = |name: &str| {
+            body.get(name)
+                .and_then(Value::as_str)
+                .ok_or_else(|| bad_request(&format!("{} is required", name)))
+        };
+        let (service_id, plan_id) = (field("service_id")?, field("plan_id")?);
+
+        let service = self
+            .service(service_id)
+            .ok_or_else(|| bad_request(&format!("unknown service_id {}", service_id)))?;
+        let plan = service
+            .plans
+            .iter()
+            .find(|p| p.id == plan_id)
+            .cloned()
+            .ok_or_else(|| bad_request(&format!("unknown plan_id {}", plan_id)))?;
+
+        Ok((service, plan))
+    }
+}
+
+enum Schema {
+    Create,
+    Update,
+    Bind,
+}
+
+/// Checks parameters against the plan's JSON schema for the operation, when it has one.
+fn validate(plan: &Plan, schema: Schema, parameters: &Option<Value>) -> Result<(), Reply> {
+    let parameters = match parameters {
+        None | Some(Value::Null) => return Ok(()),
+        Some(parameters) if parameters.is_object() => parameters,
+        Some(_) => return Err(bad_request("parameters must be an object")),
+    };
+    let schemas = match &plan.schemas {
+        Some(schemas) => schemas,
+        None => return Ok(()),
+    };
+    let schema = match schema {
+        Schema::Create => schemas
+            .service_instance
+            .as_ref()
+            .and_then(|s| s.create.as_ref()),
+        Schema::Update => schemas
+            .service_instance
+            .as_ref()
+            .and_then(|s| s.update.as_ref()),
+        Schema::Bind => schemas
+            .service_binding
+            .as_ref()
+            .and_then(|s| s.create.as_ref()),
+    };
+    let schema = match schema.and_then(|s| s.parameters.as_ref()) {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+
+    let mut scope = Scope::new();
+    let compiled = scope
+        .compile_and_return(schema.clone(), false)
+        .map_err(|e| bad_request(&format!("the plan's schema is invalid: {}", e)))?;
+    let validation = compiled.validate(parameters);
+    if validation.is_valid() {
+        return Ok(());
+    }
+
+    let errors: Vec<String> = validation
+        .errors
+        .iter()
+        .map(|e| {
+            format!(
+                "{} {}",
+                e.get_path(),
+                e.get_detail().unwrap_or(e.get_title())
+            )
+        })
+        .collect();
+    Err(bad_request(&format!(
+        "invalid parameters: {}",
+        errors.join(", ")
+    )))
+}
+
+// deletions must name the service and plan the resource was created with
+fn matching_ids(
+    query: &HashMap<String, String>,
+    service_id: &str,
+    plan_id: &str,
+) -> Result<(), Reply> {
+    match (query.get("service_id"), query.get("plan_id")) {
+        (Some(s), Some(p)) if s == service_id && p == plan_id => Ok(()),
+        (Some(_), Some(_)) => Err(bad_request("service_id and plan_id do not match")),
+        _ => Err(bad_request(
+            "service_id and plan_id query parameters are required",
+        )),
+    }
+}
+
+fn accepted(operation: &Option<Operation>) -> Reply {
+    let id = operation.as_ref().map(|o| o.id.clone());
+    (StatusCode::ACCEPTED, json!({ "operation": id }))
+}
+
+fn last_operation(operation: &Option<Operation>) -> Reply {
+    match operation {
+        Some(operation) => (StatusCode::OK, operation.last_operation()),
+        None => (StatusCode::OK, json!({"state": "succeeded"})),
+    }
+}
+
+fn key(id: &str, binding_id: &str) -> (String, String) {
+    (id.to_string(), binding_id.to_string())
+}
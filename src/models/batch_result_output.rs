@@ -0,0 +1,46 @@
+/// Record written by `batch` for each line of the batch file, one JSON document per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResultOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    /// Line of the batch file, starting at 1.
+    #[serde(rename = "line")]
+    pub line: usize,
+    /// `provision`, `update`, `bind`, `unbind` or `deprovision`.
+    #[serde(rename = "operation")]
+    pub operation: String,
+    /// `succeeded`, `failed` or `skipped` when an earlier line failed.
+    #[serde(rename = "state")]
+    pub state: String,
+    #[serde(
+        rename = "service_instance_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_instance_id: Option<String>,
+    #[serde(rename = "service_binding_id", skip_serializing_if = "Option::is_none")]
+    pub service_binding_id: Option<String>,
+    #[serde(rename = "error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// HTTP status of the broker error.
+    #[serde(rename = "status", skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+impl BatchResultOutput {
+    pub fn new(line: usize, operation: String) -> BatchResultOutput {
+        BatchResultOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "BatchResult".to_string(),
+            line,
+            operation,
+            state: String::new(),
+            service_instance_id: None,
+            service_binding_id: None,
+            error: None,
+            status: None,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use serde_json::Value;
+
+/// Document printed by `catalog diff`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogDiffOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    /// Whether any change can break existing instances, bindings or scripts.
+    #[serde(rename = "breaking")]
+    pub breaking: bool,
+    #[serde(rename = "changes")]
+    pub changes: Vec<CatalogChange>,
+}
+
+impl CatalogDiffOutput {
+    pub fn new(changes: Vec<CatalogChange>) -> CatalogDiffOutput {
+        CatalogDiffOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "CatalogDiff".to_string(),
+            breaking: changes.iter().any(|c| c.breaking),
+            changes,
+        }
+    }
+}
+
+/// A difference between two catalogs, services and plans being matched by ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogChange {
+    /// `added`, `removed` or `modified`.
+    #[serde(rename = "change")]
+    pub change: String,
+    /// `service`, `plan` or `parameter`.
+    #[serde(rename = "object")]
+    pub object: String,
+    #[serde(rename = "service_id")]
+    pub service_id: String,
+    #[serde(rename = "service_name")]
+    pub service_name: String,
+    #[serde(rename = "plan_id", skip_serializing_if = "Option::is_none")]
+    pub plan_id: Option<String>,
+    #[serde(rename = "plan_name", skip_serializing_if = "Option::is_none")]
+    pub plan_name: Option<String>,
+    /// Changed field, or `<action>.<parameter>` for schema changes.
+    #[serde(rename = "field", skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(rename = "from", skip_serializing_if = "Option::is_none")]
+    pub from: Option<Value>,
+    #[serde(rename = "to", skip_serializing_if = "Option::is_none")]
+    pub to: Option<Value>,
+    #[serde(rename = "breaking")]
+    pub breaking: bool,
+}
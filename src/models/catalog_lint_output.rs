@@ -0,0 +1,43 @@
+/// Document printed by `catalog lint`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogLintOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "errors")]
+    pub errors: usize,
+    #[serde(rename = "warnings")]
+    pub warnings: usize,
+    #[serde(rename = "findings")]
+    pub findings: Vec<LintFinding>,
+}
+
+impl CatalogLintOutput {
+    pub fn new(findings: Vec<LintFinding>) -> CatalogLintOutput {
+        CatalogLintOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "CatalogLint".to_string(),
+            errors: findings.iter().filter(|f| f.severity == "error").count(),
+            warnings: findings.iter().filter(|f| f.severity == "warning").count(),
+            findings,
+        }
+    }
+}
+
+/// A catalog entry breaking an OSB rule (`error`) or a recommendation (`warning`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintFinding {
+    /// `error` or `warning`.
+    #[serde(rename = "severity")]
+    pub severity: String,
+    /// Identifier of the rule, stable across releases.
+    #[serde(rename = "rule")]
+    pub rule: String,
+    /// Location in the catalog, such as `services[0].plans[1].id`.
+    #[serde(rename = "path")]
+    pub path: String,
+    #[serde(rename = "message")]
+    pub message: String,
+}
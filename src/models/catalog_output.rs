@@ -0,0 +1,23 @@
+use rocl::models::Service;
+
+/// Document printed by `catalog`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "services")]
+    pub services: Vec<Service>,
+}
+
+impl CatalogOutput {
+    pub fn new(services: Vec<Service>) -> CatalogOutput {
+        CatalogOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "Catalog".to_string(),
+            services,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+/// Document printed by `plan` and `apply`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeSetOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    /// Whether the changes were made, `plan` only lists them.
+    #[serde(rename = "applied")]
+    pub applied: bool,
+    #[serde(rename = "changes")]
+    pub changes: Vec<ResourceChange>,
+}
+
+impl ChangeSetOutput {
+    pub fn new(applied: bool, changes: Vec<ResourceChange>) -> ChangeSetOutput {
+        ChangeSetOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "ChangeSet".to_string(),
+            applied,
+            changes,
+        }
+    }
+}
+
+/// A change converging the local store and the broker with a manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceChange {
+    /// `provision`, `update`, `bind`, `unbind` or `deprovision`.
+    #[serde(rename = "action")]
+    pub action: String,
+    /// Manifest name of the instance or binding.
+    #[serde(rename = "name")]
+    pub name: String,
+    /// Manifest name of the instance, for bindings.
+    #[serde(rename = "instance", skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(rename = "service", skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(rename = "plan", skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    /// Fields an update changes.
+    #[serde(rename = "fields", skip_serializing_if = "Vec::is_empty", default)]
+    pub fields: Vec<String>,
+    #[serde(
+        rename = "service_instance_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_instance_id: Option<String>,
+    #[serde(rename = "service_binding_id", skip_serializing_if = "Option::is_none")]
+    pub service_binding_id: Option<String>,
+    /// `succeeded`, `failed` or `skipped` once applied.
+    #[serde(rename = "state", skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(rename = "error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
@@ -0,0 +1,57 @@
+/// Document printed by `conformance`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "broker_url")]
+    pub broker_url: String,
+    /// Service the lifecycle checks ran against, unknown when the catalog failed.
+    #[serde(rename = "service_name", skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    #[serde(rename = "plan_name", skip_serializing_if = "Option::is_none")]
+    pub plan_name: Option<String>,
+    #[serde(rename = "passed")]
+    pub passed: usize,
+    #[serde(rename = "failed")]
+    pub failed: usize,
+    #[serde(rename = "skipped")]
+    pub skipped: usize,
+    #[serde(rename = "checks")]
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl ConformanceOutput {
+    pub fn new(broker_url: String, checks: Vec<ConformanceCheck>) -> ConformanceOutput {
+        let count = |status: &str| checks.iter().filter(|c| c.status == status).count();
+
+        ConformanceOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "Conformance".to_string(),
+            broker_url,
+            service_name: None,
+            plan_name: None,
+            passed: count("passed"),
+            failed: count("failed"),
+            skipped: count("skipped"),
+            checks,
+        }
+    }
+}
+
+/// Outcome of one step of the conformance suite.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceCheck {
+    #[serde(rename = "name")]
+    pub name: String,
+    /// `passed`, `failed` or `skipped`.
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "duration_ms")]
+    pub duration_ms: u64,
+    /// Why the check failed or was skipped.
+    #[serde(rename = "message", skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
@@ -0,0 +1,33 @@
+/// Document printed instead of the command output when a command fails and a
+/// structured output format is selected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "message")]
+    pub message: String,
+    #[serde(rename = "request_id")]
+    pub request_id: String,
+    /// HTTP status returned by the broker, absent when no response was received.
+    #[serde(rename = "status", skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Error body returned by the broker.
+    #[serde(rename = "broker_error", skip_serializing_if = "Option::is_none")]
+    pub broker_error: Option<rocl::models::Error>,
+}
+
+impl ErrorOutput {
+    pub fn new(message: String, request_id: String) -> ErrorOutput {
+        ErrorOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "Error".to_string(),
+            message,
+            request_id,
+            status: None,
+            broker_error: None,
+        }
+    }
+}
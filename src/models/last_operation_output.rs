@@ -0,0 +1,32 @@
+use rocl::models::LastOperationResource;
+
+/// Document printed for the state of an asynchronous instance or binding operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastOperationOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "service_instance_id")]
+    pub service_instance_id: String,
+    #[serde(rename = "service_binding_id", skip_serializing_if = "Option::is_none")]
+    pub service_binding_id: Option<String>,
+    #[serde(rename = "last_operation")]
+    pub last_operation: LastOperationResource,
+}
+
+impl LastOperationOutput {
+    pub fn new(
+        service_instance_id: String,
+        last_operation: LastOperationResource,
+    ) -> LastOperationOutput {
+        LastOperationOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "LastOperation".to_string(),
+            service_instance_id,
+            service_binding_id: None,
+            last_operation,
+        }
+    }
+}
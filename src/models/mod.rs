@@ -1,4 +1,36 @@
+/// Version of the JSON and YAML documents printed by rocs. It changes only
+/// when a field is removed or changes meaning; new optional fields keep it.
+pub const OUTPUT_API_VERSION: &str = "rocs/v1";
+
+mod batch_result_output;
+pub use batch_result_output::BatchResultOutput;
+mod catalog_diff_output;
+pub use catalog_diff_output::{CatalogChange, CatalogDiffOutput};
+mod catalog_lint_output;
+pub use catalog_lint_output::{CatalogLintOutput, LintFinding};
+mod catalog_output;
+pub use catalog_output::CatalogOutput;
+mod change_set_output;
+pub use change_set_output::{ChangeSetOutput, ResourceChange};
+mod conformance_output;
+pub use conformance_output::{ConformanceCheck, ConformanceOutput};
+mod error_output;
+pub use error_output::ErrorOutput;
+mod last_operation_output;
+pub use last_operation_output::LastOperationOutput;
+mod parameters_output;
+pub use parameters_output::{Parameter, ParametersOutput};
+mod plan_output;
+pub use plan_output::PlanOutput;
+mod service_binding_list_output;
+pub use service_binding_list_output::{ServiceBindingListOutput, ServiceBindingSummary};
 mod service_binding_output;
 pub use service_binding_output::ServiceBindingOutput;
+mod service_instance_description_output;
+pub use service_instance_description_output::ServiceInstanceDescriptionOutput;
+mod service_instance_list_output;
+pub use service_instance_list_output::{ServiceInstanceListOutput, ServiceInstanceSummary};
 mod service_instance_output;
 pub use service_instance_output::ServiceInstanceOutput;
+mod service_output;
+pub use service_output::ServiceOutput;
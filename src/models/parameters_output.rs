@@ -0,0 +1,84 @@
+use serde_json::Value;
+
+/// Document printed by `catalog params`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParametersOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "service_name")]
+    pub service_name: String,
+    #[serde(rename = "plan_name")]
+    pub plan_name: String,
+    /// `create`, `update` or `bind`.
+    #[serde(rename = "action")]
+    pub action: String,
+    #[serde(rename = "parameters")]
+    pub parameters: Vec<Parameter>,
+}
+
+impl ParametersOutput {
+    pub fn new(
+        service_name: String,
+        plan_name: String,
+        action: String,
+        parameters: Vec<Parameter>,
+    ) -> ParametersOutput {
+        ParametersOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "Parameters".to_string(),
+            service_name,
+            plan_name,
+            action,
+            parameters,
+        }
+    }
+}
+
+/// A parameter described by a plan's JSON schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parameter {
+    /// Property name, nested properties are joined with dots.
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub _type: Option<String>,
+    #[serde(rename = "required")]
+    pub required: bool,
+    #[serde(rename = "description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "default", skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
+    pub _enum: Option<Vec<Value>>,
+    #[serde(rename = "minimum", skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<Value>,
+    #[serde(rename = "maximum", skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<Value>,
+    #[serde(rename = "min_length", skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u64>,
+    #[serde(rename = "max_length", skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u64>,
+    #[serde(rename = "pattern", skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+impl Parameter {
+    pub fn new(name: String, required: bool) -> Parameter {
+        Parameter {
+            name,
+            _type: None,
+            required,
+            description: None,
+            default: None,
+            _enum: None,
+            minimum: None,
+            maximum: None,
+            min_length: None,
+            max_length: None,
+            pattern: None,
+        }
+    }
+}
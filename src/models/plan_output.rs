@@ -0,0 +1,34 @@
+use rocl::models::Plan;
+
+/// Document printed by `catalog show <service> <plan>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "service_id")]
+    pub service_id: String,
+    #[serde(rename = "service_name")]
+    pub service_name: String,
+    /// Whether instances of the plan can be bound, the plan's own flag
+    /// taking precedence over the service's.
+    #[serde(rename = "bindable")]
+    pub bindable: bool,
+    #[serde(rename = "plan")]
+    pub plan: Plan,
+}
+
+impl PlanOutput {
+    pub fn new(service_id: String, service_name: String, bindable: bool, plan: Plan) -> PlanOutput {
+        PlanOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "Plan".to_string(),
+            service_id,
+            service_name,
+            bindable,
+            plan,
+        }
+    }
+}
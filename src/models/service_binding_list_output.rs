@@ -0,0 +1,42 @@
+use chrono::NaiveDateTime;
+
+/// Document printed by `bindings`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceBindingListOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "service_bindings")]
+    pub service_bindings: Vec<ServiceBindingSummary>,
+}
+
+impl ServiceBindingListOutput {
+    pub fn new(service_bindings: Vec<ServiceBindingSummary>) -> ServiceBindingListOutput {
+        ServiceBindingListOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "ServiceBindingList".to_string(),
+            service_bindings,
+        }
+    }
+}
+
+/// A service binding created with rocs, as recorded in the local store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceBindingSummary {
+    #[serde(rename = "service_binding_id")]
+    pub service_binding_id: String,
+    #[serde(rename = "service_instance_id")]
+    pub service_instance_id: String,
+    /// Empty when the instance is not in the local store.
+    #[serde(rename = "service_name")]
+    pub service_name: String,
+    /// Empty when the instance is not in the local store.
+    #[serde(rename = "plan_name")]
+    pub plan_name: String,
+    #[serde(rename = "state")]
+    pub state: String,
+    #[serde(rename = "created_at")]
+    pub created_at: NaiveDateTime,
+}
@@ -1,7 +1,18 @@
 use rocl::models::ServiceBindingResource;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// Document printed by `bind` and `unbind`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ServiceBindingOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(
+        rename = "service_instance_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_instance_id: Option<String>,
     #[serde(rename = "service_binding_id", skip_serializing_if = "Option::is_none")]
     pub service_binding_id: Option<String>,
     #[serde(
@@ -14,8 +25,17 @@ pub struct ServiceBindingOutput {
 impl ServiceBindingOutput {
     pub fn new() -> ServiceBindingOutput {
         ServiceBindingOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "ServiceBinding".to_string(),
+            service_instance_id: None,
             service_binding_id: None,
             service_binding_resource: None,
         }
     }
 }
+
+impl Default for ServiceBindingOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
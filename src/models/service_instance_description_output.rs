@@ -0,0 +1,83 @@
+use super::ServiceBindingSummary;
+use chrono::NaiveDateTime;
+use rocl::models::{LastOperationResource, MaintenanceInfo};
+
+/// Document printed by `describe`, combining the broker's view of an instance
+/// with the catalog and the local store. Every field the broker, catalog or
+/// store could not provide is absent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceInstanceDescriptionOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "service_instance_id")]
+    pub service_instance_id: String,
+    #[serde(rename = "service_id", skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    #[serde(rename = "service_name", skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    #[serde(
+        rename = "service_description",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_description: Option<String>,
+    #[serde(rename = "plan_id", skip_serializing_if = "Option::is_none")]
+    pub plan_id: Option<String>,
+    #[serde(rename = "plan_name", skip_serializing_if = "Option::is_none")]
+    pub plan_name: Option<String>,
+    #[serde(rename = "plan_description", skip_serializing_if = "Option::is_none")]
+    pub plan_description: Option<String>,
+    #[serde(rename = "dashboard_url", skip_serializing_if = "Option::is_none")]
+    pub dashboard_url: Option<String>,
+    /// Maintenance version the instance runs.
+    #[serde(rename = "maintenance_info", skip_serializing_if = "Option::is_none")]
+    pub maintenance_info: Option<MaintenanceInfo>,
+    /// Maintenance version the catalog offers for the plan.
+    #[serde(
+        rename = "plan_maintenance_info",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub plan_maintenance_info: Option<MaintenanceInfo>,
+    /// Parameters reported by the broker.
+    #[serde(rename = "parameters", skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+    /// Parameters sent when the instance was provisioned with rocs.
+    #[serde(
+        rename = "requested_parameters",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub requested_parameters: Option<serde_json::Value>,
+    #[serde(rename = "last_operation", skip_serializing_if = "Option::is_none")]
+    pub last_operation: Option<LastOperationResource>,
+    #[serde(rename = "created_at", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<NaiveDateTime>,
+    /// Bindings of the instance recorded in the local store.
+    #[serde(rename = "service_bindings")]
+    pub service_bindings: Vec<ServiceBindingSummary>,
+}
+
+impl ServiceInstanceDescriptionOutput {
+    pub fn new(service_instance_id: String) -> ServiceInstanceDescriptionOutput {
+        ServiceInstanceDescriptionOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "ServiceInstanceDescription".to_string(),
+            service_instance_id,
+            service_id: None,
+            service_name: None,
+            service_description: None,
+            plan_id: None,
+            plan_name: None,
+            plan_description: None,
+            dashboard_url: None,
+            maintenance_info: None,
+            plan_maintenance_info: None,
+            parameters: None,
+            requested_parameters: None,
+            last_operation: None,
+            created_at: None,
+            service_bindings: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+
+/// Document printed by `instances`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceInstanceListOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "service_instances")]
+    pub service_instances: Vec<ServiceInstanceSummary>,
+}
+
+impl ServiceInstanceListOutput {
+    pub fn new(service_instances: Vec<ServiceInstanceSummary>) -> ServiceInstanceListOutput {
+        ServiceInstanceListOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "ServiceInstanceList".to_string(),
+            service_instances,
+        }
+    }
+}
+
+/// A service instance provisioned with rocs, as recorded in the local store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceInstanceSummary {
+    #[serde(rename = "service_instance_id")]
+    pub service_instance_id: String,
+    #[serde(rename = "service_id")]
+    pub service_id: String,
+    #[serde(rename = "service_name")]
+    pub service_name: String,
+    #[serde(rename = "plan_id")]
+    pub plan_id: String,
+    #[serde(rename = "plan_name")]
+    pub plan_name: String,
+    /// State of the last operation rocs observed for the instance.
+    #[serde(rename = "state")]
+    pub state: String,
+    #[serde(rename = "created_at")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updated_at")]
+    pub updated_at: NaiveDateTime,
+    #[serde(rename = "binding_count")]
+    pub binding_count: usize,
+}
@@ -1,7 +1,13 @@
 use rocl::models::ServiceInstanceResource;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// Document printed by `provision`, `info` and `deprovision`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ServiceInstanceOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
     #[serde(
         rename = "service_instance_id",
         skip_serializing_if = "Option::is_none"
@@ -17,8 +23,16 @@ pub struct ServiceInstanceOutput {
 impl ServiceInstanceOutput {
     pub fn new() -> ServiceInstanceOutput {
         ServiceInstanceOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "ServiceInstance".to_string(),
             service_instance_id: None,
             service_instance_resource: None,
         }
     }
 }
+
+impl Default for ServiceInstanceOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,23 @@
+use rocl::models::Service;
+
+/// Document printed by `catalog show <service>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceOutput {
+    /// Output schema version, see [`OUTPUT_API_VERSION`](super::OUTPUT_API_VERSION).
+    #[serde(rename = "api_version")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    #[serde(rename = "service")]
+    pub service: Service,
+}
+
+impl ServiceOutput {
+    pub fn new(service: Service) -> ServiceOutput {
+        ServiceOutput {
+            api_version: super::OUTPUT_API_VERSION.to_string(),
+            kind: "Service".to_string(),
+            service,
+        }
+    }
+}
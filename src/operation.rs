@@ -0,0 +1,212 @@
+use crate::client::{ClientError, LastOperationRequest};
+
+use futures_core::Stream;
+use futures_util::{stream, StreamExt};
+use rocl::models::{last_operation_resource::State, LastOperationResource};
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Seconds between polls of the last operation by default.
+pub const POOL_INTERVAL: u64 = 5;
+
+/// How [`wait_for_operation`] polls the broker.
+#[derive(Debug, Clone)]
+pub struct WaitOptions {
+    /// Delay between the first polls.
+    pub interval: Duration,
+    /// Factor applied to the delay after every poll, 1 keeps it constant.
+    pub backoff: f64,
+    /// Upper bound of the delay once backoff applies.
+    pub max_interval: Duration,
+    /// Time after which waiting stops with [`WaitError::Timeout`].
+    pub timeout: Option<Duration>,
+    /// Stops waiting with [`WaitError::Cancelled`] once cancelled, dropping the stream works too.
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> WaitOptions {
+        WaitOptions {
+            interval: Duration::from_secs(POOL_INTERVAL),
+            backoff: 1.5,
+            max_interval: Duration::from_secs(60),
+            timeout: None,
+            cancel: None,
+        }
+    }
+}
+
+/// Why waiting for an operation stopped before it finished.
+#[derive(Debug)]
+pub enum WaitError {
+    /// Fetching the last operation failed.
+    Client(ClientError),
+    /// The operation was still in progress after the timeout.
+    Timeout(Duration),
+    Cancelled,
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Client(e) => write!(f, "{}", e),
+            WaitError::Timeout(timeout) => write!(
+                f,
+                "operation still in progress after {} seconds",
+                timeout.as_secs()
+            ),
+            WaitError::Cancelled => write!(f, "stopped waiting for the operation"),
+        }
+    }
+}
+
+impl Error for WaitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WaitError::Client(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+type Update = Result<LastOperationResource, WaitError>;
+
+/// Updates of an operation, see [`wait_for_operation`].
+pub struct OperationStream<'a> {
+    inner: Pin<Box<dyn Stream<Item = Update> + Send + 'a>>,
+}
+
+impl OperationStream<'_> {
+    /// Waits until the operation finished, skipping intermediate updates.
+    /// The returned resource is either succeeded or failed.
+    pub async fn outcome(mut self) -> Update {
+        let mut last = Err(WaitError::Cancelled);
+        while let Some(update) = self.next().await {
+            last = update;
+        }
+        last
+    }
+}
+
+impl Stream for OperationStream<'_> {
+    type Item = Update;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Update>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+struct Polling<'a> {
+    request: LastOperationRequest<'a>,
+    options: WaitOptions,
+    interval: Duration,
+    deadline: Option<Instant>,
+    previous: Option<LastOperationResource>,
+    polled: bool,
+    done: bool,
+}
+
+/// Polls the last operation of an instance or binding until it finishes.
+///
+/// The stream yields the first state seen, then every change of state or
+/// description, and ends after the succeeded or failed state. Errors, the
+/// timeout and cancellation are yielded as a last `Err` item.
+///
+/// ```no_run
+/// # use futures_util::StreamExt;
+/// # async fn example(client: rocs::Client) -> Result<(), Box<dyn std::error::Error>> {
+/// let request = client.last_operation("instance-id");
+/// let mut updates = rocs::wait_for_operation(request, Default::default());
+/// while let Some(update) = updates.next().await {
+///     println!("{:?}", update?.description);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn wait_for_operation(
+    request: LastOperationRequest<'_>,
+    options: WaitOptions,
+) -> OperationStream<'_> {
+    let polling = Polling {
+        request,
+        interval: options.interval,
+        deadline: options.timeout.map(|timeout| Instant::now() + timeout),
+        options,
+        previous: None,
+        polled: false,
+        done: false,
+    };
+
+    OperationStream {
+        inner: Box::pin(stream::unfold(polling, poll)),
+    }
+}
+
+async fn poll(mut polling: Polling<'_>) -> Option<(Update, Polling<'_>)> {
+    loop {
+        if polling.done {
+            return None;
+        }
+
+        if polling.polled {
+            let mut delay = polling.interval;
+            if let Some(deadline) = polling.deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    let timeout = polling.options.timeout.unwrap_or_default();
+                    return Some(polling.finish(Err(WaitError::Timeout(timeout))));
+                }
+                delay = delay.min(deadline - now);
+            }
+
+            tokio::select! {
+                _ = cancelled(&polling.options.cancel) => {
+                    return Some(polling.finish(Err(WaitError::Cancelled)));
+                }
+                _ = tokio::time::sleep(delay) => {}
+            }
+
+            let next = polling.interval.mul_f64(polling.options.backoff.max(1.0));
+            polling.interval = next.min(polling.options.max_interval.max(polling.options.interval));
+        }
+        polling.polled = true;
+
+        let result = tokio::select! {
+            _ = cancelled(&polling.options.cancel) => {
+                return Some(polling.finish(Err(WaitError::Cancelled)));
+            }
+            result = polling.request.send() => result,
+        };
+
+        match result {
+            Err(e) => return Some(polling.finish(Err(WaitError::Client(e)))),
+            Ok(resource) if resource.state != State::InProgress => {
+                return Some(polling.finish(Ok(resource)));
+            }
+            Ok(resource) if polling.previous.as_ref() != Some(&resource) => {
+                polling.previous = Some(resource.clone());
+                return Some((Ok(resource), polling));
+            }
+            Ok(_) => {}
+        }
+    }
+}
+
+impl<'a> Polling<'a> {
+    fn finish(mut self, update: Update) -> (Update, Polling<'a>) {
+        self.done = true;
+        (update, self)
+    }
+}
+
+async fn cancelled(cancel: &Option<CancellationToken>) {
+    match cancel {
+        Some(cancel) => cancel.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
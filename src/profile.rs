@@ -0,0 +1,100 @@
+use crate::auth::AuthMode;
+use crate::http::{NetworkSettings, TlsSettings};
+use dirs::config_dir;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+
+/// Broker connection settings stored under a name in `~/.config/rocs/profiles.yaml`.
+/// Command line flags and environment variables take precedence over any value
+/// set here.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(rename = "broker_url", skip_serializing_if = "Option::is_none")]
+    pub broker_url: Option<String>,
+    #[serde(rename = "username", skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(rename = "password", skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(rename = "auth", skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthMode>,
+    #[serde(rename = "token", skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(rename = "token_url", skip_serializing_if = "Option::is_none")]
+    pub token_url: Option<String>,
+    #[serde(rename = "client_id", skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(rename = "client_secret", skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(rename = "scope", skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(rename = "cacert", skip_serializing_if = "Option::is_none")]
+    pub cacert: Option<String>,
+    #[serde(rename = "cert", skip_serializing_if = "Option::is_none")]
+    pub cert: Option<String>,
+    #[serde(rename = "key", skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(
+        rename = "insecure_skip_verify",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub insecure_skip_verify: Option<bool>,
+    /// Request timeout in seconds.
+    #[serde(rename = "timeout", skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Connect timeout in seconds.
+    #[serde(rename = "connect_timeout", skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    #[serde(rename = "proxy", skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(rename = "no_proxy", skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<bool>,
+    /// Seconds the cached catalog is used before revalidating it.
+    #[serde(rename = "catalog_ttl", skip_serializing_if = "Option::is_none")]
+    pub catalog_ttl: Option<u64>,
+}
+
+impl Profile {
+    /// Authentication mode, bearer when only a token is set and basic otherwise.
+    pub fn auth_mode(&self) -> AuthMode {
+        self.auth.unwrap_or(match self.token {
+            Some(_) => AuthMode::Bearer,
+            None => AuthMode::Basic,
+        })
+    }
+
+    pub fn tls(&self) -> TlsSettings {
+        TlsSettings {
+            cacert: self.cacert.clone(),
+            cert: self.cert.clone(),
+            key: self.key.clone(),
+            insecure_skip_verify: self.insecure_skip_verify.unwrap_or(false),
+        }
+    }
+
+    pub fn network(&self) -> NetworkSettings {
+        NetworkSettings {
+            timeout: self.timeout.map(Duration::from_secs),
+            connect_timeout: self.connect_timeout.map(Duration::from_secs),
+            proxy: self.proxy.clone(),
+            no_proxy: self.no_proxy.unwrap_or(false),
+        }
+    }
+}
+
+fn profiles_path() -> Result<String, Box<dyn Error>> {
+    let config = config_dir().ok_or("unable to find configuration directory")?;
+    Ok(format!("{}/rocs/profiles.yaml", config.to_str().unwrap()))
+}
+
+pub fn load(name: &str) -> Result<Profile, Box<dyn Error>> {
+    let path = profiles_path()?;
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let mut profiles: HashMap<String, Profile> = serde_yaml::from_str(&content)?;
+
+    profiles
+        .remove(name)
+        .ok_or_else(|| format!("profile {} not found in {}", name, path).into())
+}
@@ -0,0 +1,244 @@
+use crate::cassette::{Player, Recorder};
+use crate::trace::{Exchange, Tracer};
+use chrono::Utc;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// OSB error code of the proxy's answer when the broker did not answer in time.
+const TIMEOUT_ERROR: &str = "ProxyTimeout";
+
+// the body is buffered, so framing headers from the broker do not apply
+const HOP_BY_HOP_HEADERS: &[hyper::header::HeaderName] =
+    &[hyper::header::CONNECTION, hyper::header::TRANSFER_ENCODING];
+
+/// Loopback HTTP proxy placed between rocl and the broker.
+///
+/// rocl builds and sends requests on its own, so traffic is observed by
+/// pointing `Configuration.base_path` at this proxy, which forwards every
+/// request to the broker with the fully configured client, or answers it
+/// from a cassette when replaying.
+pub struct LocalProxy {
+    pub upstream: reqwest::Client,
+    pub broker_url: String,
+    /// Shared by the proxies of every broker a command talks to.
+    pub tracer: Arc<Tracer>,
+    pub recorder: Option<Recorder>,
+    pub player: Option<Player>,
+}
+
+/// Starts the proxy on an ephemeral local port and returns its base URL.
+pub async fn start(proxy: LocalProxy) -> Result<String, Box<dyn Error>> {
+    let proxy = Arc::new(proxy);
+    let make_service = make_service_fn(move |_| {
+        let proxy = proxy.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let proxy = proxy.clone();
+                async move { Ok::<_, Infallible>(forward(&proxy, req).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_service);
+    let address = server.local_addr();
+    tokio::spawn(server);
+
+    Ok(format!("http://{}", address))
+}
+
+async fn forward(proxy: &LocalProxy, req: Request<Body>) -> Response<Body> {
+    let started = Utc::now();
+    let timer = Instant::now();
+
+    let (parts, body) = req.into_parts();
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let url = format!("{}{}", proxy.broker_url.trim_end_matches('/'), path);
+    let request_headers: Vec<(String, String)> = parts
+        .headers
+        .iter()
+        .filter(|(name, _)| *name != hyper::header::HOST)
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+    let request_body = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => return proxy_error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    let request_text = String::from_utf8_lossy(&request_body).into_owned();
+
+    proxy
+        .tracer
+        .request(parts.method.as_str(), &url, &request_headers, &request_text);
+
+    if let Some(player) = &proxy.player {
+        return replay(
+            proxy,
+            player,
+            parts.method.as_str(),
+            path,
+            url,
+            request_text,
+        );
+    }
+
+    let mut upstream = proxy.upstream.request(parts.method.clone(), &url);
+    for (name, value) in parts.headers.iter() {
+        if name != hyper::header::HOST && name != hyper::header::CONTENT_LENGTH {
+            upstream = upstream.header(name, value);
+        }
+    }
+
+    let response = match upstream.body(request_body).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("[ERROR] {} {} failed: {}", parts.method, url, e);
+            return upstream_error(e);
+        }
+    };
+
+    let status = response.status();
+    let response_headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !HOP_BY_HOP_HEADERS.contains(name))
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+    let response_body = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => return upstream_error(e),
+    };
+
+    let exchange = Exchange {
+        started,
+        elapsed: timer.elapsed(),
+        method: parts.method.to_string(),
+        url,
+        request_headers,
+        request_body: request_text,
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        response_headers,
+        response_body: String::from_utf8_lossy(&response_body).into_owned(),
+    };
+    if let Err(e) = proxy.tracer.response(&exchange) {
+        eprintln!("[WARN] failed to write trace: {}", e);
+    }
+    if let Some(recorder) = &proxy.recorder {
+        if let Err(e) = recorder.record(&exchange) {
+            eprintln!("[WARN] failed to record exchange: {}", e);
+        }
+    }
+
+    let mut builder = Response::builder().status(status);
+    for (name, value) in &exchange.response_headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    builder
+        .body(Body::from(response_body))
+        .unwrap_or_else(|e| proxy_error(StatusCode::BAD_GATEWAY, e.to_string()))
+}
+
+fn replay(
+    proxy: &LocalProxy,
+    player: &Player,
+    method: &str,
+    path: &str,
+    url: String,
+    request_body: String,
+) -> Response<Body> {
+    let interaction = match player.play(method, path) {
+        Some(interaction) => interaction,
+        None => {
+            eprintln!("[ERROR] no recorded response for {} {}", method, path);
+            return proxy_error(
+                StatusCode::BAD_GATEWAY,
+                format!("no recorded response for {} {}", method, path),
+            );
+        }
+    };
+
+    let status = StatusCode::from_u16(interaction.status).unwrap_or(StatusCode::BAD_GATEWAY);
+    let exchange = Exchange {
+        started: Utc::now(),
+        elapsed: Duration::ZERO,
+        method: method.to_string(),
+        url,
+        request_headers: interaction.request_headers.clone().into_iter().collect(),
+        request_body,
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        response_headers: interaction.response_headers.clone().into_iter().collect(),
+        response_body: interaction.response_text(),
+    };
+    if let Err(e) = proxy.tracer.response(&exchange) {
+        eprintln!("[WARN] failed to write trace: {}", e);
+    }
+
+    let mut builder = Response::builder().status(status);
+    for (name, value) in &exchange.response_headers {
+        // the recorded body was re-serialized, its length may differ
+        if !name.eq_ignore_ascii_case("content-length") {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+    builder
+        .body(Body::from(exchange.response_body))
+        .unwrap_or_else(|e| proxy_error(StatusCode::BAD_GATEWAY, e.to_string()))
+}
+
+/// Answers a failed upstream request, timeouts become a 504 the client maps
+/// back to a timeout.
+fn upstream_error(e: reqwest::Error) -> Response<Body> {
+    match e.is_timeout() {
+        true => error_response(StatusCode::GATEWAY_TIMEOUT, TIMEOUT_ERROR, e.to_string()),
+        false => proxy_error(StatusCode::BAD_GATEWAY, e.to_string()),
+    }
+}
+
+/// Whether a response body is the proxy's answer to an upstream timeout.
+pub fn is_timeout(content: &str) -> bool {
+    serde_json::from_str::<Value>(content)
+        .map(|body| body["error"] == TIMEOUT_ERROR)
+        .unwrap_or(false)
+}
+
+/// Description of an error answered by the proxy.
+pub fn error_description(content: &str) -> String {
+    serde_json::from_str::<Value>(content)
+        .ok()
+        .and_then(|body| body["description"].as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+// errors are answered in the OSB error format so rocl reports them as broker errors
+fn proxy_error(status: StatusCode, description: String) -> Response<Body> {
+    error_response(status, "ProxyError", description)
+}
+
+fn error_response(status: StatusCode, error: &str, description: String) -> Response<Body> {
+    let body = json!({"error": error, "description": description});
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
@@ -1,13 +1,39 @@
 use chrono::naive::NaiveDateTime;
 use chrono::Local;
 use dirs::home_dir;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, ToSql};
 use std::error::Error;
 
-struct Binding {
-    binding_id: String,
-    instance_id: String,
-    updated_at: NaiveDateTime,
+pub struct Binding {
+    pub binding_id: String,
+    pub instance_id: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub state: String,
+    /// Name given in an applied manifest, `None` for bindings made with `bind`.
+    pub name: Option<String>,
+}
+
+pub struct Instance {
+    pub instance_id: String,
+    pub service_id: String,
+    pub plan_id: String,
+    pub service_name: String,
+    pub plan_name: String,
+    pub parameters: String,
+    pub state: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// Name given in an applied manifest, `None` for instances made with `provision`.
+    pub name: Option<String>,
+    /// URL of the broker the instance was provisioned on.
+    pub broker_url: String,
+}
+
+pub struct CachedCatalog {
+    pub etag: Option<String>,
+    pub data: String,
+    pub fetched_at: NaiveDateTime,
 }
 
 fn open_connection() -> Result<Connection, Box<dyn Error>> {
@@ -21,18 +47,45 @@ pub fn binding_save(
     instance_id: String,
     binding_id: String,
     data: String,
+    state: String,
+    name: Option<String>,
 ) -> Result<String, Box<dyn Error>> {
     let conn = open_connection()?;
     binding_table_check(&conn)?;
 
     conn.execute(
-        "INSERT INTO service_bindings (binding_id, instance_id, updated_at, data) VALUES (?1, ?2, datetime('now'), ?3)",
-        params![binding_id, instance_id, data],
+        "INSERT INTO service_bindings (binding_id, instance_id, created_at, updated_at, data, state, name) VALUES (?1, ?2, datetime('now'), datetime('now'), ?3, ?4, ?5)
+         ON CONFLICT (binding_id) DO UPDATE SET updated_at = excluded.updated_at, data = excluded.data, state = excluded.state, name = COALESCE(excluded.name, name)",
+        params![binding_id, instance_id, data, state, name],
     )?;
 
     Ok(binding_id)
 }
 
+pub fn binding_update_state(binding_id: String, state: String) -> Result<(), Box<dyn Error>> {
+    let conn = open_connection()?;
+    binding_table_check(&conn)?;
+
+    conn.execute(
+        "UPDATE service_bindings SET state = ?2, updated_at = datetime('now') WHERE binding_id = ?1",
+        params![binding_id, state],
+    )?;
+
+    Ok(())
+}
+
+pub fn binding_delete(binding_id: String) -> Result<(), Box<dyn Error>> {
+    let conn = open_connection()?;
+    binding_table_check(&conn)?;
+
+    conn.execute(
+        "DELETE FROM service_bindings WHERE binding_id = ?1",
+        params![binding_id],
+    )?;
+
+    Ok(())
+}
+
 pub fn binding_instance_id(binding_id: &String) -> Result<(String, String), Box<dyn Error>> {
     let conn = open_connection()?;
     binding_table_check(&conn)?;
@@ -44,7 +97,10 @@ pub fn binding_instance_id(binding_id: &String) -> Result<(String, String), Box<
     let mut binding = Binding {
         binding_id: "".into(),
         instance_id: "".into(),
+        created_at: Local::now().naive_local(),
         updated_at: Local::now().naive_local(),
+        state: "".into(),
+        name: None,
     };
 
     let mut rows = stmt.query(params![binding_id])?;
@@ -58,17 +114,26 @@ pub fn binding_instance_id(binding_id: &String) -> Result<(String, String), Box<
     Ok((binding.instance_id, binding.binding_id))
 }
 
-pub fn binding_list(instance_id: String) -> Result<Vec<String>, Box<dyn Error>> {
+/// Bindings of one instance, or every stored binding when `instance_id` is `None`.
+pub fn binding_list(instance_id: Option<String>) -> Result<Vec<Binding>, Box<dyn Error>> {
     let conn = open_connection()?;
     binding_table_check(&conn)?;
 
-    let mut stmt =
-        conn.prepare("SELECT binding_id FROM service_bindings WHERE instance_id = ?1")?;
+    let mut stmt = conn.prepare(
+        "SELECT binding_id, instance_id, COALESCE(created_at, updated_at), updated_at, state, name FROM service_bindings WHERE ?1 IS NULL OR instance_id = ?1",
+    )?;
     let mut rows = stmt.query(params![instance_id])?;
-    let mut list: Vec<String> = Vec::new();
+    let mut list: Vec<Binding> = Vec::new();
 
     while let Some(row) = rows.next()? {
-        list.push(row.get(0)?)
+        list.push(Binding {
+            binding_id: row.get(0)?,
+            instance_id: row.get(1)?,
+            created_at: row.get(2)?,
+            updated_at: row.get(3)?,
+            state: row.get(4)?,
+            name: row.get(5)?,
+        })
     }
 
     Ok(list)
@@ -99,6 +164,315 @@ fn binding_table_check(conn: &Connection) -> Result<(), Box<dyn Error>> {
         )",
         params![],
     )?;
+    column_check(
+        conn,
+        "service_bindings",
+        "state",
+        "TEXT NOT NULL DEFAULT ''",
+    )?;
+    // bindings saved before creation time was tracked fall back to updated_at
+    column_check(conn, "service_bindings", "created_at", "DATE")?;
+    column_check(conn, "service_bindings", "name", "TEXT")?;
+
+    Ok(())
+}
+
+pub fn instance_save(instance: &Instance) -> Result<String, Box<dyn Error>> {
+    let conn = open_connection()?;
+    instance_table_check(&conn)?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO service_instances (instance_id, service_id, plan_id, service_name, plan_name, parameters, state, created_at, updated_at, name, broker_url) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            instance.instance_id,
+            instance.service_id,
+            instance.plan_id,
+            instance.service_name,
+            instance.plan_name,
+            instance.parameters,
+            instance.state,
+            instance.created_at,
+            instance.updated_at,
+            instance.name,
+            instance.broker_url
+        ],
+    )?;
+
+    Ok(instance.instance_id.clone())
+}
+
+pub fn instance_update_state(instance_id: String, state: String) -> Result<(), Box<dyn Error>> {
+    let conn = open_connection()?;
+    instance_table_check(&conn)?;
+
+    conn.execute(
+        "UPDATE service_instances SET state = ?2, updated_at = datetime('now') WHERE instance_id = ?1",
+        params![instance_id, state],
+    )?;
+
+    Ok(())
+}
+
+pub fn instance_get(instance_id: &str) -> Result<Option<Instance>, Box<dyn Error>> {
+    Ok(instance_query(
+        "SELECT instance_id, service_id, plan_id, service_name, plan_name, parameters, state, created_at, updated_at, name, broker_url FROM service_instances WHERE instance_id = ?1",
+        params![instance_id],
+    )?
+    .pop())
+}
+
+pub fn instance_list() -> Result<Vec<Instance>, Box<dyn Error>> {
+    instance_query(
+        "SELECT instance_id, service_id, plan_id, service_name, plan_name, parameters, state, created_at, updated_at, name, broker_url FROM service_instances",
+        params![],
+    )
+}
+
+pub fn instance_delete(instance_id: String) -> Result<(), Box<dyn Error>> {
+    let conn = open_connection()?;
+    instance_table_check(&conn)?;
+
+    conn.execute(
+        "DELETE FROM service_instances WHERE instance_id = ?1",
+        params![instance_id],
+    )?;
+
+    Ok(())
+}
+
+fn instance_query(query: &str, params: &[&dyn ToSql]) -> Result<Vec<Instance>, Box<dyn Error>> {
+    let conn = open_connection()?;
+    instance_table_check(&conn)?;
+
+    let mut stmt = conn.prepare(query)?;
+    let mut rows = stmt.query(params)?;
+    let mut list: Vec<Instance> = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        list.push(Instance {
+            instance_id: row.get(0)?,
+            service_id: row.get(1)?,
+            plan_id: row.get(2)?,
+            service_name: row.get(3)?,
+            plan_name: row.get(4)?,
+            parameters: row.get(5)?,
+            state: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+            name: row.get(9)?,
+            broker_url: row.get(10)?,
+        })
+    }
+
+    Ok(list)
+}
+
+fn instance_table_check(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS service_instances (
+            instance_id     TEXT PRIMARY KEY,
+            service_id      TEXT NOT NULL,
+            plan_id         TEXT NOT NULL,
+            service_name    TEXT NOT NULL,
+            plan_name       TEXT NOT NULL,
+            parameters      TEXT,
+            state           TEXT NOT NULL,
+            created_at      DATE NOT NULL,
+            updated_at      DATE NOT NULL,
+            name            TEXT,
+            broker_url      TEXT NOT NULL
+        )",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+/// Caches a catalog under `key`, the broker URL and the client identity, see
+/// [`catalog_cache_key`](crate::cli::catalog_cache_key).
+pub fn catalog_cache_save(
+    key: &str,
+    etag: Option<String>,
+    data: String,
+) -> Result<(), Box<dyn Error>> {
+    let conn = open_connection()?;
+    catalog_table_check(&conn)?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO catalogs (key, etag, data, fetched_at) VALUES (?1, ?2, ?3, datetime('now'))",
+        params![key, etag, data],
+    )?;
+
+    Ok(())
+}
+
+/// Marks a cached catalog as fresh after the broker confirmed it did not change.
+pub fn catalog_cache_touch(key: &str) -> Result<(), Box<dyn Error>> {
+    let conn = open_connection()?;
+    catalog_table_check(&conn)?;
+
+    conn.execute(
+        "UPDATE catalogs SET fetched_at = datetime('now') WHERE key = ?1",
+        params![key],
+    )?;
+
+    Ok(())
+}
+
+pub fn catalog_cache_get(key: &str) -> Result<Option<CachedCatalog>, Box<dyn Error>> {
+    let conn = open_connection()?;
+    catalog_table_check(&conn)?;
+
+    let mut stmt = conn.prepare("SELECT etag, data, fetched_at FROM catalogs WHERE key = ?1")?;
+    let mut rows = stmt.query(params![key])?;
+
+    match rows.next()? {
+        Some(row) => Ok(Some(CachedCatalog {
+            etag: row.get(0)?,
+            data: row.get(1)?,
+            fetched_at: row.get(2)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+fn catalog_table_check(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS catalogs (
+            key             TEXT PRIMARY KEY,
+            etag            TEXT,
+            data            TEXT NOT NULL,
+            fetched_at      DATE NOT NULL
+        )",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+// adds columns introduced after a table was first created
+fn column_check(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), Box<dyn Error>> {
+    if has_column(conn, table, column)? {
+        return Ok(());
+    }
+
+    conn.execute(
+        &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+        params![],
+    )?;
+
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = stmt.query(params![])?;
+
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(1)?;
+        if name == column {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+pub fn operation_save(
+    request_id: String,
+    command: String,
+    instance_id: String,
+    binding_id: String,
+    error: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    let conn = open_connection()?;
+    operation_table_check(&conn)?;
+
+    let state = match error {
+        Some(_) => "failed",
+        None => "succeeded",
+    };
+
+    conn.execute(
+        "INSERT INTO operations (request_id, command, instance_id, binding_id, state, error, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+        params![request_id, command, instance_id, binding_id, state, error],
+    )?;
+
+    Ok(request_id)
+}
+
+fn operation_table_check(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS operations (
+            request_id      TEXT NOT NULL,
+            command         TEXT NOT NULL,
+            instance_id     TEXT,
+            binding_id      TEXT,
+            state           TEXT NOT NULL,
+            error           TEXT,
+            created_at      DATE NOT NULL
+        )",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+pub fn token_save(
+    token_url: String,
+    client_id: String,
+    scope: Option<String>,
+    access_token: String,
+    expires_at: NaiveDateTime,
+) -> Result<(), Box<dyn Error>> {
+    let conn = open_connection()?;
+    token_table_check(&conn)?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO oauth_tokens (token_url, client_id, scope, access_token, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![token_url, client_id, scope.unwrap_or_default(), access_token, expires_at],
+    )?;
+
+    Ok(())
+}
+
+pub fn token_get(
+    token_url: &str,
+    client_id: &str,
+    scope: Option<&str>,
+) -> Result<Option<(String, NaiveDateTime)>, Box<dyn Error>> {
+    let conn = open_connection()?;
+    token_table_check(&conn)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT access_token, expires_at FROM oauth_tokens WHERE token_url = ?1 AND client_id = ?2 AND scope = ?3",
+    )?;
+    let mut rows = stmt.query(params![token_url, client_id, scope.unwrap_or_default()])?;
+
+    if let Some(row) = rows.next()? {
+        return Ok(Some((row.get(0)?, row.get(1)?)));
+    }
+
+    Ok(None)
+}
+
+fn token_table_check(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS oauth_tokens (
+            token_url       TEXT NOT NULL,
+            client_id       TEXT NOT NULL,
+            scope           TEXT NOT NULL,
+            access_token    TEXT NOT NULL,
+            expires_at      DATE NOT NULL,
+            PRIMARY KEY (token_url, client_id, scope)
+        )",
+        params![],
+    )?;
 
     Ok(())
 }
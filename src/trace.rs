@@ -0,0 +1,214 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const REDACTED: &str = "[REDACTED]";
+const REDACTED_HEADERS: &[&str] = &["authorization", "proxy-authorization"];
+const REDACTED_FIELDS: &[&str] = &["credentials"];
+
+/// A request sent to the broker and the response it produced.
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub started: DateTime<Utc>,
+    pub elapsed: Duration,
+    pub method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: String,
+    pub status: u16,
+    pub status_text: String,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: String,
+}
+
+impl Exchange {
+    /// Copy of the exchange with secrets removed from headers and bodies.
+    pub fn redacted(&self) -> Exchange {
+        Exchange {
+            request_headers: redact_headers(&self.request_headers),
+            request_body: redact_body(&self.request_body),
+            response_headers: redact_headers(&self.response_headers),
+            response_body: redact_body(&self.response_body),
+            ..self.clone()
+        }
+    }
+}
+
+/// Logs broker traffic to stderr and collects it into a HAR trace file.
+///
+/// Verbosity 1 logs request lines and response status, 2 adds headers and 3
+/// adds bodies.
+pub struct Tracer {
+    verbosity: u64,
+    trace_file: Option<String>,
+    entries: Mutex<Vec<Value>>,
+}
+
+impl Tracer {
+    pub fn new(verbosity: u64, trace_file: Option<String>) -> Tracer {
+        Tracer {
+            verbosity,
+            trace_file,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.verbosity > 0 || self.trace_file.is_some()
+    }
+
+    pub fn request(&self, method: &str, url: &str, headers: &[(String, String)], body: &str) {
+        if self.verbosity == 0 {
+            return;
+        }
+
+        eprintln!("> {} {}", method, url);
+        if self.verbosity > 1 {
+            for (name, value) in redact_headers(headers) {
+                eprintln!("> {}: {}", name, value);
+            }
+        }
+        if self.verbosity > 2 && !body.is_empty() {
+            eprintln!(">");
+            eprintln!("{}", redact_body(body));
+        }
+    }
+
+    pub fn response(&self, exchange: &Exchange) -> Result<(), Box<dyn Error>> {
+        let exchange = exchange.redacted();
+
+        if self.verbosity > 0 {
+            eprintln!(
+                "< {} {} ({} ms)",
+                exchange.status,
+                exchange.status_text,
+                exchange.elapsed.as_millis()
+            );
+            if self.verbosity > 1 {
+                for (name, value) in &exchange.response_headers {
+                    eprintln!("< {}: {}", name, value);
+                }
+            }
+            if self.verbosity > 2 && !exchange.response_body.is_empty() {
+                eprintln!("<");
+                eprintln!("{}", exchange.response_body);
+            }
+        }
+
+        if let Some(trace_file) = &self.trace_file {
+            let mut entries = self.entries.lock().unwrap();
+            entries.push(har_entry(&exchange));
+
+            // rewritten after every exchange so failed runs still leave a trace
+            let har = json!({
+                "log": {
+                    "version": "1.2",
+                    "creator": {"name": "rocs", "version": env!("CARGO_PKG_VERSION")},
+                    "entries": *entries,
+                }
+            });
+            fs::write(trace_file, serde_json::to_string_pretty(&har)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn redact_headers(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            if REDACTED_HEADERS.contains(&name.to_lowercase().as_str()) {
+                (name.clone(), REDACTED.to_string())
+            } else {
+                (name.clone(), value.clone())
+            }
+        })
+        .collect()
+}
+
+fn redact_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| body.to_string())
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+pub fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+fn har_headers(headers: &[(String, String)]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect()
+}
+
+fn har_entry(exchange: &Exchange) -> Value {
+    let content_type = |headers: &[(String, String)]| {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+
+    let mut request = json!({
+        "method": exchange.method,
+        "url": exchange.url,
+        "httpVersion": "HTTP/1.1",
+        "headers": har_headers(&exchange.request_headers),
+        "queryString": [],
+        "cookies": [],
+        "headersSize": -1,
+        "bodySize": exchange.request_body.len(),
+    });
+    if !exchange.request_body.is_empty() {
+        request["postData"] = json!({
+            "mimeType": content_type(&exchange.request_headers),
+            "text": exchange.request_body,
+        });
+    }
+
+    json!({
+        "startedDateTime": exchange.started.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "time": exchange.elapsed.as_millis() as u64,
+        "request": request,
+        "response": {
+            "status": exchange.status,
+            "statusText": exchange.status_text,
+            "httpVersion": "HTTP/1.1",
+            "headers": har_headers(&exchange.response_headers),
+            "cookies": [],
+            "content": {
+                "size": exchange.response_body.len(),
+                "mimeType": content_type(&exchange.response_headers),
+                "text": exchange.response_body,
+            },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": exchange.response_body.len(),
+        },
+        "cache": {},
+        "timings": {"send": 0, "wait": exchange.elapsed.as_millis() as u64, "receive": 0},
+    })
+}
@@ -0,0 +1,74 @@
+//! Tests of the JSONL operations read by `rocs batch`.
+
+use rocs::batch::{self, BatchOperation};
+use serde_json::json;
+
+#[test]
+fn parse() {
+    let operations = batch::parse(
+        r#"{"operation": "provision", "service": "postgres", "plan": "small", "parameters": {"version": "16"}}
+
+{"operation": "bind", "instance_id": "db", "name": "app"}
+{"operation": "unbind", "instance_id": "db", "binding_id": "app"}
+{"operation": "deprovision", "instance_id": "db"}
+"#,
+    )
+    .unwrap();
+
+    let lines: Vec<_> = operations.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, [1, 3, 4, 5]);
+    let names: Vec<_> = operations.iter().map(|(_, o)| o.name()).collect();
+    assert_eq!(names, ["provision", "bind", "unbind", "deprovision"]);
+
+    match &operations[0].1 {
+        BatchOperation::Provision {
+            instance_id,
+            parameters,
+            ..
+        } => {
+            assert_eq!(*instance_id, None);
+            assert_eq!(*parameters, Some(json!({"version": "16"})));
+        }
+        operation => panic!("unexpected operation {:?}", operation),
+    }
+    assert_eq!(operations[1].1.instance_id(), Some("db"));
+}
+
+#[test]
+fn queues() {
+    let operations = batch::parse(
+        r#"{"operation": "provision", "service": "postgres", "plan": "small", "instance_id": "db"}
+{"operation": "provision", "service": "postgres", "plan": "small"}
+{"operation": "deprovision", "instance_id": "cache"}
+{"operation": "bind", "instance_id": "db", "name": "app"}
+{"operation": "provision", "service": "postgres", "plan": "small"}
+{"operation": "unbind", "instance_id": "db", "binding_id": "app"}
+"#,
+    )
+    .unwrap();
+
+    let lines: Vec<Vec<usize>> = batch::queues(operations)
+        .iter()
+        .map(|queue| queue.iter().map(|(line, _)| *line).collect())
+        .collect();
+    assert_eq!(lines, [vec![1, 4, 6], vec![2], vec![3], vec![5]]);
+}
+
+#[test]
+fn parse_errors() {
+    let err = batch::parse(
+        r#"{"operation": "deprovision", "instance_id": "db"}
+{"operation": "migrate", "instance_id": "db"}"#,
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("line 2: unknown variant `migrate`"));
+
+    let err = batch::parse(r#"{"operation": "unbind", "instance_id": "db"}"#).unwrap_err();
+    assert!(err.to_string().contains("missing field `binding_id`"));
+
+    let err = batch::parse(r#"{"operation": "deprovision", "instance_id": "db", "cascade": true}"#)
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown field `cascade`"));
+}
@@ -0,0 +1,336 @@
+//! Tests of the catalog filters, parameter listings, diffs and lint rules.
+
+use rocl::models::{Catalog, Service};
+use rocs::cli::{self, CatalogFilter};
+use rocs::models::CatalogChange;
+use serde_json::{json, Value};
+use std::fs;
+
+fn document() -> Value {
+    let path = format!("{}/tests/fixtures/catalog.yaml", env!("CARGO_MANIFEST_DIR"));
+    serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn services(document: Value) -> Vec<Service> {
+    serde_json::from_value::<Catalog>(document)
+        .unwrap()
+        .services
+        .unwrap()
+}
+
+fn filter(args: &[&str]) -> Vec<String> {
+    let matches = cli::app()
+        .try_get_matches_from([&["rocs", "catalog"], args].concat())
+        .unwrap();
+    let filter = CatalogFilter::from_matches(matches.subcommand_matches("catalog").unwrap());
+
+    let mut plans = Vec::new();
+    for service in filter.unwrap().apply(services(document())) {
+        for plan in service.plans {
+            plans.push(format!("{}/{}", service.name, plan.name));
+        }
+    }
+    plans
+}
+
+#[test]
+fn filters() {
+    let all = ["postgres/small", "postgres/large", "logs/default"];
+    assert_eq!(filter(&[]), all);
+
+    assert_eq!(
+        filter(&["--tag", "sql"]),
+        ["postgres/small", "postgres/large"]
+    );
+    assert!(filter(&["--tag", "sql", "--tag", "nosql"]).is_empty());
+    assert_eq!(
+        filter(&["--bindable"]),
+        ["postgres/small", "postgres/large"]
+    );
+    // plans are free unless stated otherwise
+    assert_eq!(filter(&["--free"]), ["postgres/small", "logs/default"]);
+
+    // a service matching by name keeps all of its plans
+    assert_eq!(
+        filter(&["--name", "post*"]),
+        ["postgres/small", "postgres/large"]
+    );
+    assert_eq!(
+        filter(&["--name", "l*"]),
+        ["postgres/large", "logs/default"]
+    );
+    assert_eq!(
+        filter(&["--search", "GB RAM"]),
+        ["postgres/small", "postgres/large"]
+    );
+    assert_eq!(filter(&["--search", "drain"]), ["logs/default"]);
+    assert_eq!(filter(&["--bindable", "--free"]), ["postgres/small"]);
+}
+
+#[test]
+fn parameters() {
+    let schema = json!({
+        "type": "object",
+        "required": ["size"],
+        "properties": {
+            "size": {"type": "integer", "minimum": 1, "description": "disk size in GB"},
+            "backup": {
+                "type": "object",
+                "required": ["schedule"],
+                "properties": {
+                    "schedule": {"type": "string", "pattern": "^[0-9 *]+$"},
+                    "retention": {
+                        "type": "object",
+                        "properties": {"days": {"type": ["integer", "null"], "default": 7}}
+                    }
+                }
+            }
+        }
+    });
+
+    let parameters = cli::schema_parameters(&schema);
+    let names: Vec<(&str, bool)> = parameters
+        .iter()
+        .map(|p| (p.name.as_str(), p.required))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("backup.retention.days", false),
+            ("backup.schedule", true),
+            ("size", true),
+        ]
+    );
+    assert_eq!(parameters[0]._type.as_deref(), Some("integer|null"));
+    assert_eq!(parameters[0].default, Some(json!(7)));
+    assert_eq!(parameters[1].pattern.as_deref(), Some("^[0-9 *]+$"));
+    assert_eq!(
+        parameters[2].description.as_deref(),
+        Some("disk size in GB")
+    );
+
+    assert!(cli::schema_parameters(&json!({"type": "object"})).is_empty());
+}
+
+fn change(changes: &[CatalogChange], object: &str, field: Option<&str>) -> bool {
+    changes
+        .iter()
+        .find(|c| c.object == object && c.field.as_deref() == field)
+        .unwrap_or_else(|| panic!("no {} change of {:?} in {:?}", object, field, changes))
+        .breaking
+}
+
+#[test]
+fn diff_non_breaking() {
+    let mut to = document();
+    to["services"][0]["description"] = json!("Managed PostgreSQL");
+    to["services"][1]["bindable"] = json!(true);
+    to["services"][0]["plans"][1]["free"] = json!(true);
+    let version = &mut to["services"][0]["plans"][0]["schemas"]["service_instance"]["create"];
+    version["parameters"]["properties"]["version"]["enum"] = json!(["14", "15", "16", "17"]);
+    version["parameters"]["properties"]["ha"] = json!({"type": "boolean"});
+    to["services"][1]["plans"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({"id": "plan-id", "name": "dedicated", "description": "Dedicated"}));
+
+    let changes = cli::compare_catalogs(&services(document()), &services(to));
+    assert_eq!(changes.len(), 7);
+    assert!(changes.iter().all(|c| !c.breaking), "{:?}", changes);
+    assert!(!change(&changes, "parameter", Some("create.ha")));
+    assert!(!change(&changes, "plan", None));
+
+    assert!(cli::compare_catalogs(&services(document()), &services(document())).is_empty());
+}
+
+#[test]
+fn diff_breaking() {
+    let mut to = document();
+    to["services"][0]["bindable"] = json!(false);
+    to["services"][0]["plans"][0]["free"] = json!(false);
+    let schema =
+        &mut to["services"][0]["plans"][0]["schemas"]["service_instance"]["create"]["parameters"];
+    schema["properties"]["version"]["enum"] = json!(["15", "16"]);
+    schema["properties"]["zone"] = json!({"type": "string"});
+    schema["required"] = json!(["zone"]);
+    to["services"][0]["plans"].as_array_mut().unwrap().remove(1);
+    to["services"].as_array_mut().unwrap().remove(1);
+
+    let changes = cli::compare_catalogs(&services(document()), &services(to));
+    assert!(change(&changes, "service", Some("bindable")));
+    assert!(change(&changes, "plan", Some("free")));
+    // the plan inherits the service's bindable flag
+    assert!(change(&changes, "plan", Some("bindable")));
+    assert!(change(&changes, "parameter", Some("create.version")));
+    assert!(change(&changes, "parameter", Some("create.zone")));
+
+    let removed: Vec<(&str, &str)> = changes
+        .iter()
+        .filter(|c| c.change == "removed")
+        .map(|c| (c.object.as_str(), c.service_name.as_str()))
+        .collect();
+    assert_eq!(removed, [("plan", "postgres"), ("service", "logs")]);
+    assert!(changes
+        .iter()
+        .filter(|c| c.change == "removed")
+        .all(|c| c.breaking));
+}
+
+fn lint(document: &Value) -> Vec<(String, String, String)> {
+    cli::lint_catalog(document)
+        .into_iter()
+        .map(|f| (f.severity, f.rule, f.path))
+        .collect()
+}
+
+fn finding(severity: &str, rule: &str, path: &str) -> (String, String, String) {
+    (severity.into(), rule.into(), path.into())
+}
+
+#[test]
+fn lint_fixture() {
+    assert!(lint(&document()).is_empty());
+    assert_eq!(
+        lint(&json!({"services": {}})),
+        [finding("error", "required-field", "services")]
+    );
+}
+
+#[test]
+fn lint_ids() {
+    let mut catalog = document();
+    catalog["services"][0]["plans"][0]["id"] = json!("small");
+    catalog["services"][0]["plans"][1]["id"] = json!("8A7D6B1E-0F4C-4C9E-B2A1-6D5E4F3A2B02");
+    catalog["services"][1]["id"] = json!("logs drain");
+    catalog["services"][1]["plans"][0]["id"] = json!("8a7d6b1e-0f4c-4c9e-b2a1-6d5e4f3a2b0");
+
+    assert_eq!(
+        lint(&catalog),
+        [
+            finding("warning", "id-format", "services[0].plans[0].id"),
+            finding("error", "id-format", "services[1].id"),
+            finding("warning", "id-format", "services[1].plans[0].id"),
+        ]
+    );
+}
+
+#[test]
+fn lint_unique() {
+    let mut catalog = document();
+    // plan names are unique within their service only
+    catalog["services"][1]["plans"][0]["name"] = json!("small");
+    catalog["services"][0]["plans"][1]["name"] = json!("small");
+    catalog["services"][1]["plans"][0]["id"] = catalog["services"][0]["plans"][0]["id"].clone();
+    catalog["services"][1]["name"] = json!("postgres");
+
+    assert_eq!(
+        lint(&catalog),
+        [
+            finding("error", "name-unique", "services[0].plans[1].name"),
+            finding("error", "name-unique", "services[1].name"),
+            finding("error", "id-unique", "services[1].plans[0].id"),
+        ]
+    );
+}
+
+#[test]
+fn lint_maintenance_info() {
+    let plan = |version: &str| {
+        let mut catalog = document();
+        catalog["services"][1]["plans"][0]["maintenance_info"] = json!({"version": version});
+        lint(&catalog)
+    };
+
+    for version in [
+        "1.2.3",
+        "0.0.1",
+        "1.0.0-rc.1",
+        "1.0.0-alpha-1+build.5",
+        "10.20.30",
+    ] {
+        assert!(
+            plan(version).is_empty(),
+            "{} is a semantic version",
+            version
+        );
+    }
+    for version in [
+        "1.2", "1.2.3.4", "01.2.3", "1.2.3-01", "1.2.3-", "1.2.3+", "v1.2.3",
+    ] {
+        assert_eq!(
+            plan(version),
+            [finding(
+                "error",
+                "maintenance-info-version",
+                "services[1].plans[0].maintenance_info.version"
+            )],
+            "{} is not a semantic version",
+            version
+        );
+    }
+}
+
+#[test]
+fn lint_maximum_polling_duration() {
+    let plan = |duration: Value| {
+        let mut catalog = document();
+        catalog["services"][1]["plans"][0]["maximum_polling_duration"] = duration;
+        cli::lint_catalog(&catalog)
+            .into_iter()
+            .map(|f| f.severity)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(plan(json!(3600)).is_empty());
+    assert!(plan(json!(7 * 24 * 60 * 60)).is_empty());
+    assert_eq!(plan(json!(2)), ["warning"]);
+    assert_eq!(plan(json!(8 * 24 * 60 * 60)), ["warning"]);
+    assert_eq!(plan(json!(0)), ["error"]);
+    assert_eq!(plan(json!(-60)), ["error"]);
+    assert_eq!(plan(json!("1h")), ["error"]);
+}
+
+#[test]
+fn lint_draft_04() {
+    let mut catalog = document();
+    let schema = &mut catalog["services"][0]["plans"][0]["schemas"]["service_instance"]["create"]
+        ["parameters"];
+    schema["properties"]["version"]["const"] = json!("15");
+    schema["properties"]["size"] = json!({"type": "integer", "exclusiveMinimum": 0});
+    schema["properties"]["replicas"] = json!({"type": "integer", "exclusiveMaximum": true});
+    schema["properties"]["zones"] = json!({"type": "array", "contains": {"type": "string"}});
+
+    let path = "services[0].plans[0].schemas.service_instance.create.parameters.properties";
+    let findings: Vec<_> = lint(&catalog)
+        .into_iter()
+        .filter(|(_, rule, _)| rule == "schema-draft-04")
+        .collect();
+    assert_eq!(
+        findings,
+        [
+            finding(
+                "error",
+                "schema-draft-04",
+                &format!("{}.size.exclusiveMinimum", path)
+            ),
+            finding(
+                "error",
+                "schema-draft-04",
+                &format!("{}.version.const", path)
+            ),
+            finding(
+                "error",
+                "schema-draft-04",
+                &format!("{}.zones.contains", path)
+            ),
+        ]
+    );
+
+    catalog["services"][0]["plans"][0]["schemas"]["service_instance"]["create"]["parameters"]
+        ["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+    assert!(lint(&catalog).contains(&finding(
+        "error",
+        "schema-draft-04",
+        "services[0].plans[0].schemas.service_instance.create.parameters.$schema"
+    )));
+}
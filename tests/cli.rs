@@ -0,0 +1,662 @@
+//! Tests of the CLI commands against the scripted fake broker, no HTTP involved.
+
+use futures_util::StreamExt;
+use rocl::models::{
+    last_operation_resource::State, AsyncOperation, LastOperationResource, ServiceBindingResource,
+    ServiceBindingResponse, ServiceInstanceProvisionResponse, ServiceInstanceResource,
+};
+use rocs::auth::{self, AuthMode, ClientCredentials};
+use rocs::broker::CatalogDocument;
+use rocs::cli::output::{self, OutputFormat};
+use rocs::cli::{self, CatalogSettings, ExitStatus, Options};
+use rocs::fake::{self, Call, FakeBroker, Reply};
+use rocs::models::{ConformanceCheck, ConformanceOutput, LastOperationOutput};
+use rocs::profile::Profile;
+use rocs::trace::Tracer;
+use rocs::{store, Client, WaitOptions};
+use serde_json::json;
+use std::error::Error;
+use std::fs;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+use uuid::Uuid;
+
+const POSTGRES: &str = "3b4a3f02-7d8c-4f3e-9d0a-5a1f0e6b1c01";
+const SMALL: &str = "8a7d6b1e-0f4c-4c9e-b2a1-6d5e4f3a2b01";
+
+fn home() {
+    // rocs keeps its local store in the home directory
+    static HOME: Once = Once::new();
+    HOME.call_once(|| {
+        let home = std::env::temp_dir().join(format!("rocs-{}", Uuid::new_v4()));
+        fs::create_dir_all(home.join(".local")).unwrap();
+        std::env::set_var("HOME", home);
+    });
+}
+
+fn broker() -> Arc<FakeBroker> {
+    home();
+
+    let path = format!("{}/tests/fixtures/catalog.yaml", env!("CARGO_MANIFEST_DIR"));
+    let catalog: serde_json::Value =
+        serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    let broker = Arc::new(FakeBroker::new());
+    broker.serve_catalog(catalog);
+    broker
+}
+
+fn options() -> Options {
+    let broker_url = format!("fake://{}", Uuid::new_v4());
+    Options {
+        output: OutputFormat::Json,
+        curl_output: false,
+        synchronous: false,
+        request_id: "request".into(),
+        auth_mode: AuthMode::Basic,
+        tls: Default::default(),
+        network: Default::default(),
+        catalog: CatalogSettings {
+            ttl: Duration::from_secs(300),
+            refresh: false,
+            offline: false,
+            cache_key: broker_url.clone(),
+        },
+        broker_url,
+        tracer: Arc::new(Tracer::new(0, None)),
+    }
+}
+
+async fn run(broker: &Arc<FakeBroker>, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let matches = cli::app().try_get_matches_from(args).unwrap();
+    let client = Client::with_broker(broker.clone());
+    match matches.subcommand() {
+        Some(("provision", m)) => cli::provision(m, client, options()).await,
+        Some(("catalog", m)) => cli::catalog(m, client, options()).await,
+        Some(("status", m)) => cli::status(m, client, options()).await,
+        Some(("conformance", m)) => cli::conformance(m, client, options()).await,
+        Some(("batch", m)) => cli::batch(m, client, options()).await,
+        _ => unreachable!(),
+    }
+}
+
+fn provisioned_instance(broker: &FakeBroker) -> String {
+    match &broker.calls()[1] {
+        Call::Provision { instance_id, .. } => instance_id.clone(),
+        call => panic!("unexpected call {:?}", call),
+    }
+}
+
+#[tokio::test]
+async fn provision() {
+    let broker = broker();
+    broker
+        .reply(Reply::Provision(
+            Ok(ServiceInstanceProvisionResponse::new()),
+        ))
+        .reply(Reply::FetchInstance(Ok(ServiceInstanceResource::new())));
+
+    let args = ["rocs", "provision", "-s", "postgres", "-p", "small"];
+    run(&broker, &[&args[..], &["-P", "version=15"]].concat())
+        .await
+        .unwrap();
+
+    let calls = broker.calls();
+    assert_eq!(calls.len(), 3);
+    match &calls[1] {
+        Call::Provision {
+            body,
+            accepts_incomplete,
+            ..
+        } => {
+            assert_eq!(body.service_id, POSTGRES);
+            assert_eq!(body.plan_id, SMALL);
+            assert_eq!(body.parameters, Some(json!({"version": "15"})));
+            assert!(accepts_incomplete);
+        }
+        call => panic!("unexpected call {:?}", call),
+    }
+
+    let instance = store::instance_get(&provisioned_instance(&broker))
+        .unwrap()
+        .unwrap();
+    assert_eq!(instance.service_name, "postgres");
+    assert_eq!(instance.state, "in progress");
+}
+
+#[tokio::test]
+async fn provision_wait() {
+    let broker = broker();
+    broker
+        .reply(Reply::Provision(
+            Ok(ServiceInstanceProvisionResponse::new()),
+        ))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::Succeeded,
+        ))))
+        .reply(Reply::FetchInstance(Ok(ServiceInstanceResource::new())));
+
+    run(
+        &broker,
+        &["rocs", "provision", "-s", "postgres", "-p", "small", "-w"],
+    )
+    .await
+    .unwrap();
+
+    let instance_id = provisioned_instance(&broker);
+    match &broker.calls()[2] {
+        Call::LastOperation(query) => {
+            assert_eq!(query.instance_id, instance_id);
+            assert_eq!(query.service_id.as_deref(), Some(POSTGRES));
+            assert_eq!(query.plan_id.as_deref(), Some(SMALL));
+        }
+        call => panic!("unexpected call {:?}", call),
+    }
+    let instance = store::instance_get(&instance_id).unwrap().unwrap();
+    assert_eq!(instance.state, "succeeded");
+}
+
+#[tokio::test]
+async fn provision_wait_failure() {
+    let broker = broker();
+    broker
+        .reply(Reply::Provision(
+            Ok(ServiceInstanceProvisionResponse::new()),
+        ))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::Failed,
+        ))));
+
+    let err = run(
+        &broker,
+        &["rocs", "provision", "-s", "postgres", "-p", "small", "-w"],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.to_string(), "provisioning failed");
+
+    // the failed instance is kept, and not fetched
+    assert_eq!(broker.calls().len(), 3);
+    let instance = store::instance_get(&provisioned_instance(&broker))
+        .unwrap()
+        .unwrap();
+    assert_eq!(instance.state, "failed");
+}
+
+#[tokio::test]
+async fn provision_errors() {
+    let broker = broker();
+    broker.reply(Reply::Provision(Err(fake::broker_error(
+        409,
+        "Conflict",
+        "instance already exists",
+    ))));
+
+    let err = run(
+        &broker,
+        &["rocs", "provision", "-s", "postgres", "-p", "small"],
+    )
+    .await
+    .unwrap_err();
+    let error_output = cli::error_output(&*err, "request");
+    assert_eq!(error_output.status, Some(409));
+    assert_eq!(
+        error_output.broker_error.unwrap().description.as_deref(),
+        Some("instance already exists")
+    );
+    assert!(store::instance_get(&provisioned_instance(&broker))
+        .unwrap()
+        .is_none());
+
+    let err = run(
+        &broker,
+        &["rocs", "provision", "-s", "mysql", "-p", "small"],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.to_string(), "plan or service not found");
+}
+
+#[tokio::test(start_paused = true)]
+async fn update_failure() {
+    let broker = broker();
+    let instance_id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().naive_utc();
+    store::instance_save(&store::Instance {
+        instance_id: instance_id.clone(),
+        service_id: POSTGRES.into(),
+        plan_id: SMALL.into(),
+        service_name: "postgres".into(),
+        plan_name: "small".into(),
+        parameters: "{}".into(),
+        state: "succeeded".into(),
+        created_at: now,
+        updated_at: now,
+        name: None,
+        broker_url: String::new(),
+    })
+    .unwrap();
+    broker
+        .reply(Reply::Update(Ok(AsyncOperation::new())))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::Failed,
+        ))));
+
+    let file = std::env::temp_dir().join(format!("rocs-{}.jsonl", Uuid::new_v4()));
+    fs::write(
+        &file,
+        format!(
+            r#"{{"operation": "update", "instance_id": "{}", "plan": "large", "parameters": {{"version": "16"}}}}"#,
+            instance_id
+        ),
+    )
+    .unwrap();
+    let err = run(&broker, &["rocs", "batch", file.to_str().unwrap()])
+        .await
+        .unwrap_err();
+    assert!(err.downcast_ref::<ExitStatus>().is_some());
+
+    // the instance keeps its plan and parameters, and is not failed
+    let instance = store::instance_get(&instance_id).unwrap().unwrap();
+    assert_eq!(instance.plan_name, "small");
+    assert_eq!(instance.parameters, "{}");
+    assert_eq!(instance.state, "succeeded");
+}
+
+#[tokio::test]
+async fn catalog_show() {
+    let broker = broker();
+
+    run(&broker, &["rocs", "catalog", "show", "postgres"])
+        .await
+        .unwrap();
+    run(&broker, &["rocs", "catalog", "show", POSTGRES, "small"])
+        .await
+        .unwrap();
+    run(&broker, &["rocs", "catalog", "show", "postgres", SMALL])
+        .await
+        .unwrap();
+
+    let err = run(&broker, &["rocs", "catalog", "show", "mysql"])
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "service mysql not found in catalog");
+    // plans are looked up in the given service only
+    let err = run(&broker, &["rocs", "catalog", "show", "logs", "small"])
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "plan small not found in service logs");
+
+    assert!(broker
+        .calls()
+        .iter()
+        .all(|call| matches!(call, Call::Catalog { .. })));
+}
+
+#[tokio::test]
+async fn status_exit_codes() {
+    let broker = broker();
+    let mut failed = LastOperationResource::new(State::Failed);
+    failed.description = Some("disk quota exceeded".into());
+    broker
+        .reply(Reply::LastOperation(Ok(failed)))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::InProgress,
+        ))));
+
+    let err = run(&broker, &["rocs", "status", "-i", "instance"])
+        .await
+        .unwrap_err();
+    assert_eq!(err.downcast_ref::<ExitStatus>().unwrap().code, 1);
+
+    let err = run(&broker, &["rocs", "status", "-i", "instance"])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ExitStatus>().unwrap().code,
+        cli::EXIT_IN_PROGRESS
+    );
+
+    let err = run(&broker, &["rocs", "status", "-i", "instance"])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "fake broker: no reply scripted for last_operation"
+    );
+}
+
+// paused time only advances while the runtime waits on timers, a blocking
+// sleep would stall the test
+#[tokio::test(start_paused = true)]
+async fn status_watch() {
+    let broker = broker();
+    broker
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::InProgress,
+        ))))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::InProgress,
+        ))))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::Succeeded,
+        ))));
+
+    run(&broker, &["rocs", "status", "-i", "instance", "--watch"])
+        .await
+        .unwrap();
+    assert_eq!(broker.pending(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn conformance() {
+    let broker = broker();
+    let succeeded = || Reply::LastOperation(Ok(LastOperationResource::new(State::Succeeded)));
+    broker
+        .reply(Reply::Provision(Err(fake::broker_error(
+            422,
+            "AsyncRequired",
+            "this plan is only provisioned asynchronously",
+        ))))
+        .reply(Reply::Provision(
+            Ok(ServiceInstanceProvisionResponse::new()),
+        ))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::InProgress,
+        ))))
+        .reply(succeeded())
+        .reply(Reply::FetchInstance(Ok(ServiceInstanceResource::new())))
+        .reply(Reply::Update(Ok(AsyncOperation::new())))
+        .reply(succeeded())
+        .reply(Reply::Bind(Ok(ServiceBindingResponse::new())))
+        .reply(Reply::FetchBinding(Ok(ServiceBindingResource::new())))
+        .reply(Reply::Unbind(Ok(AsyncOperation::new())))
+        .reply(Reply::Deprovision(Ok(AsyncOperation::new())))
+        .reply(Reply::LastOperation(Err(fake::broker_error(
+            410, "Gone", "",
+        ))))
+        .reply(Reply::Deprovision(Err(fake::broker_error(410, "Gone", ""))));
+
+    let junit = std::env::temp_dir().join(format!("rocs-{}.xml", Uuid::new_v4()));
+    let args = ["rocs", "conformance", "-s", "postgres", "-p", "small"];
+    run(
+        &broker,
+        &[&args[..], &["--junit", junit.to_str().unwrap()]].concat(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(broker.pending(), 0);
+
+    // the sync provisioning is skipped, the lifecycle runs on the asynchronous instance
+    let calls = broker.calls();
+    let instance_id = match &calls[2] {
+        Call::Provision {
+            instance_id,
+            accepts_incomplete: true,
+            ..
+        } => instance_id,
+        call => panic!("unexpected call {:?}", call),
+    };
+    match &calls[11] {
+        Call::Deprovision {
+            instance_id: deprovisioned,
+            accepts_incomplete: true,
+            ..
+        } => assert_eq!(deprovisioned, instance_id),
+        call => panic!("unexpected call {:?}", call),
+    }
+
+    // checks sending other versions or credentials need an HTTP broker
+    let report = fs::read_to_string(junit).unwrap();
+    assert!(report.contains("the broker is not reached over HTTP"));
+    assert!(report.contains("the broker only supports asynchronous provisioning"));
+}
+
+#[tokio::test]
+async fn polling() {
+    let broker = broker();
+    let in_progress = |description: &str| {
+        let mut last_operation = LastOperationResource::new(State::InProgress);
+        last_operation.description = Some(description.into());
+        Reply::LastOperation(Ok(last_operation))
+    };
+    broker
+        .reply(in_progress("creating"))
+        .reply(in_progress("creating"))
+        .reply(in_progress("configuring"))
+        .reply(Reply::LastOperation(Ok(LastOperationResource::new(
+            State::Succeeded,
+        ))));
+
+    let client = Client::with_broker(broker.clone());
+    let options = WaitOptions {
+        interval: Duration::from_millis(10),
+        ..WaitOptions::default()
+    };
+    let updates: Vec<_> = rocs::wait_for_operation(client.last_operation("instance"), options)
+        .map(|update| update.unwrap())
+        .collect()
+        .await;
+
+    let descriptions: Vec<_> = updates.iter().map(|u| u.description.as_deref()).collect();
+    assert_eq!(
+        descriptions,
+        vec![Some("creating"), Some("configuring"), None]
+    );
+    assert_eq!(updates.last().unwrap().state, State::Succeeded);
+    assert_eq!(broker.pending(), 0);
+}
+
+#[test]
+fn output_formats() {
+    let mut last_operation = LastOperationResource::new(State::InProgress);
+    last_operation.description = Some("creating".into());
+    let document = LastOperationOutput::new("instance".into(), last_operation);
+
+    let json = output::render(&document, &OutputFormat::Json).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&json).unwrap()["last_operation"]["state"],
+        "in progress"
+    );
+
+    let jsonpath = OutputFormat::JsonPath("$.last_operation.description".into());
+    assert_eq!(output::render(&document, &jsonpath).unwrap(), "creating\n");
+
+    let table = output::render(&document, &OutputFormat::Table).unwrap();
+    assert!(table.contains("instance"));
+    assert!(table.contains("creating"));
+}
+
+#[test]
+fn junit_report() {
+    let message = "broker said \"no\" <status 500> & more\n\tdetails\u{1b}[0m\u{0}";
+    let output = ConformanceOutput::new(
+        "https://broker.example.com/?a=1&b=2".into(),
+        vec![ConformanceCheck {
+            name: "provision-sync".into(),
+            status: "failed".into(),
+            duration_ms: 1500,
+            message: Some(message.into()),
+        }],
+    );
+
+    let xml = output::junit(&output);
+    let document = roxmltree::Document::parse(&xml).unwrap();
+    let suite = document
+        .descendants()
+        .find(|n| n.has_tag_name("testsuite"))
+        .unwrap();
+    assert_eq!(
+        suite.attribute("name"),
+        Some("https://broker.example.com/?a=1&b=2")
+    );
+    assert_eq!(suite.attribute("failures"), Some("1"));
+
+    let failure = document
+        .descendants()
+        .find(|n| n.has_tag_name("failure"))
+        .unwrap();
+    assert_eq!(
+        failure.attribute("message"),
+        Some("broker said \"no\" <status 500> & more\n\tdetails[0m")
+    );
+}
+
+#[test]
+fn ages() {
+    assert_eq!(cli::parse_age("7d").unwrap(), chrono::Duration::days(7));
+    assert_eq!(
+        cli::parse_age("90s").unwrap(),
+        chrono::Duration::seconds(90)
+    );
+    assert!(cli::parse_age("7").is_err());
+    assert!(cli::parse_age("").is_err());
+    assert!(cli::parse_age("0d").is_err());
+    assert!(cli::parse_age("-7d").is_err());
+    assert!(cli::parse_age("7y").is_err());
+    assert!(cli::parse_age("d").is_err());
+    assert!(cli::parse_age("1.5h").is_err());
+    assert!(cli::parse_age("7dd").is_err());
+    assert!(cli::parse_age("9223372036854775807w").is_err());
+    assert!(cli::parse_age("99999999999999999999s").is_err());
+}
+
+#[test]
+fn token_scopes() {
+    home();
+    let token_url = format!("https://{}/token", Uuid::new_v4());
+    let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    let save = |scope: Option<&str>, token: &str| {
+        store::token_save(
+            token_url.clone(),
+            "client".into(),
+            scope.map(String::from),
+            token.into(),
+            expires_at,
+        )
+        .unwrap()
+    };
+    save(Some("read"), "read-token");
+    save(None, "token");
+
+    let get = |scope| store::token_get(&token_url, "client", scope).unwrap();
+    assert_eq!(get(Some("read")).unwrap().0, "read-token");
+    assert_eq!(get(None).unwrap().0, "token");
+    assert!(get(Some("write")).is_none());
+}
+
+#[tokio::test]
+async fn token_timeout() {
+    home();
+    // accepts connections but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((connection, _)) = listener.accept().await {
+            connections.push(connection);
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+    let credentials = ClientCredentials {
+        token_url: format!("http://{}/token", address),
+        client_id: "client".into(),
+        client_secret: "secret".into(),
+        scope: None,
+    };
+    let err = auth::access_token(&client, &credentials).await.unwrap_err();
+    assert!(cli::is_timeout(&*err), "{}", err);
+}
+
+fn catalog_document(etag: &str) -> CatalogDocument {
+    let path = format!("{}/tests/fixtures/catalog.yaml", env!("CARGO_MANIFEST_DIR"));
+    let catalog: serde_json::Value =
+        serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    CatalogDocument::Modified {
+        content: catalog.to_string(),
+        etag: Some(etag.into()),
+    }
+}
+
+#[tokio::test]
+async fn catalog_cache() {
+    let broker = broker();
+    let client = Client::with_broker(broker.clone());
+    let mut options = options();
+    let catalog_calls = |broker: &FakeBroker| {
+        broker
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                Call::Catalog { etag } => Some(etag),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    broker.reply(Reply::Catalog(Ok(catalog_document("v1"))));
+    assert_eq!(
+        cli::fetch_catalog(&client, &options).await.unwrap().len(),
+        2
+    );
+    assert_eq!(catalog_calls(&broker), [None]);
+
+    // fresh catalogs are served from the cache
+    cli::fetch_catalog(&client, &options).await.unwrap();
+    assert_eq!(catalog_calls(&broker), [None]);
+
+    // expired ones are revalidated with their ETag
+    options.catalog.ttl = Duration::ZERO;
+    broker.reply(Reply::Catalog(Ok(CatalogDocument::NotModified)));
+    assert_eq!(
+        cli::fetch_catalog(&client, &options).await.unwrap().len(),
+        2
+    );
+    assert_eq!(catalog_calls(&broker), [None, Some("v1".into())]);
+
+    // offline, expired catalogs are still used
+    options.catalog.offline = true;
+    cli::fetch_catalog(&client, &options).await.unwrap();
+    assert_eq!(catalog_calls(&broker), [None, Some("v1".into())]);
+
+    // other clients of the broker have their own cache entry
+    options.catalog.cache_key = format!("{} basic:other", options.broker_url);
+    let err = cli::fetch_catalog(&client, &options).await.unwrap_err();
+    assert!(err.to_string().starts_with("no cached catalog"), "{}", err);
+    options.catalog.offline = false;
+    cli::fetch_catalog(&client, &options).await.unwrap();
+    assert_eq!(catalog_calls(&broker), [None, Some("v1".into()), None]);
+}
+
+#[test]
+fn catalog_cache_keys() {
+    let profile = |username: &str, token: Option<&str>| Profile {
+        broker_url: Some("https://broker".into()),
+        username: Some(username.into()),
+        token: token.map(String::from),
+        auth: token.map(|_| AuthMode::Bearer),
+        ..Profile::default()
+    };
+
+    assert_eq!(
+        cli::catalog_cache_key(&profile("admin", None)),
+        cli::catalog_cache_key(&profile("admin", None))
+    );
+    assert_ne!(
+        cli::catalog_cache_key(&profile("admin", None)),
+        cli::catalog_cache_key(&profile("viewer", None))
+    );
+    let bearer = cli::catalog_cache_key(&profile("admin", Some("secret-token")));
+    assert!(!bearer.contains("secret-token"));
+    // the SHA-256 digest keeps keys stable across toolchains
+    assert!(
+        bearer.ends_with("bearer:930bbdc51b6aed5c2a5678fd6e28dee7a05e8a4b643cfc0b4427c3efb86c0d94")
+    );
+    assert_ne!(
+        bearer,
+        cli::catalog_cache_key(&profile("admin", Some("other-token")))
+    );
+}
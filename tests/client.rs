@@ -0,0 +1,248 @@
+//! Tests of the library client against the in-memory mock broker.
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use hyper::header::AUTHORIZATION;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use rocl::models::last_operation_resource::State;
+use rocs::auth::TokenProvider;
+use rocs::mock::{self, MockBroker};
+use rocs::{Client, ClientError, WaitError, WaitOptions};
+use serde_json::json;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const POSTGRES: &str = "3b4a3f02-7d8c-4f3e-9d0a-5a1f0e6b1c01";
+const SMALL: &str = "8a7d6b1e-0f4c-4c9e-b2a1-6d5e4f3a2b01";
+const LARGE: &str = "8a7d6b1e-0f4c-4c9e-b2a1-6d5e4f3a2b02";
+
+async fn client() -> Client {
+    let path = format!("{}/tests/fixtures/catalog.yaml", env!("CARGO_MANIFEST_DIR"));
+    let broker = MockBroker {
+        catalog: serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap(),
+        delay: Duration::from_millis(200),
+        failure_rate: 0.0,
+        credentials: Some(("user".into(), "secret".into())),
+    };
+    let url = mock::start(broker, "127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap();
+
+    Client::builder(&url)
+        .basic_auth("user", "secret")
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn lifecycle() {
+    let client = client().await;
+
+    let catalog = client.catalog().await.unwrap();
+    assert_eq!(catalog.services.unwrap().len(), 2);
+
+    client
+        .provision("instance", POSTGRES, SMALL)
+        .parameters(json!({"version": "15"}))
+        .accepts_incomplete(false)
+        .send()
+        .await
+        .unwrap();
+    let instance = client.fetch_instance("instance").await.unwrap();
+    assert_eq!(instance.parameters, Some(json!({"version": "15"})));
+
+    let operation = client
+        .update("instance", POSTGRES)
+        .plan_id(LARGE)
+        .send()
+        .await
+        .unwrap();
+    assert!(operation.operation.is_some());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let last_operation = client
+        .last_operation("instance")
+        .operation(&operation.operation.unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(last_operation.state, State::Succeeded);
+    let instance = client.fetch_instance("instance").await.unwrap();
+    assert_eq!(instance.plan_id.as_deref(), Some(LARGE));
+
+    let binding = client
+        .bind("instance", "binding", POSTGRES, LARGE)
+        .accepts_incomplete(false)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(binding.credentials.unwrap()["username"], "binding");
+    client.fetch_binding("instance", "binding").await.unwrap();
+
+    client
+        .unbind("instance", "binding", POSTGRES, LARGE)
+        .accepts_incomplete(false)
+        .send()
+        .await
+        .unwrap();
+    client
+        .deprovision("instance", POSTGRES, LARGE)
+        .accepts_incomplete(false)
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn broker_errors() {
+    let client = client().await;
+
+    let err = client
+        .provision("instance", POSTGRES, "unknown")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(400));
+    match err {
+        ClientError::Broker(e) => {
+            assert_eq!(e.context, "provision request failed");
+            assert!(e.body().unwrap().description.unwrap().contains("unknown"));
+        }
+        e => panic!("unexpected error {:?}", e),
+    }
+
+    let err = client
+        .deprovision("missing", POSTGRES, SMALL)
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(410));
+}
+
+#[tokio::test]
+async fn wait_for_operation() {
+    let client = client().await;
+    let options = || WaitOptions {
+        interval: Duration::from_millis(50),
+        ..WaitOptions::default()
+    };
+
+    client
+        .provision("instance", POSTGRES, SMALL)
+        .send()
+        .await
+        .unwrap();
+    let mut updates = rocs::wait_for_operation(client.last_operation("instance"), options());
+    let first = updates.next().await.unwrap().unwrap();
+    assert_eq!(first.state, State::InProgress);
+    let outcome = updates.outcome().await.unwrap();
+    assert_eq!(outcome.state, State::Succeeded);
+
+    client
+        .update("instance", POSTGRES)
+        .plan_id(LARGE)
+        .send()
+        .await
+        .unwrap();
+    let err = rocs::wait_for_operation(
+        client.last_operation("instance"),
+        WaitOptions {
+            timeout: Some(Duration::from_millis(100)),
+            ..options()
+        },
+    )
+    .outcome()
+    .await
+    .unwrap_err();
+    assert!(matches!(err, WaitError::Timeout(_)));
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let err = rocs::wait_for_operation(
+        client.last_operation("missing"),
+        WaitOptions {
+            cancel: Some(cancel),
+            ..options()
+        },
+    )
+    .outcome()
+    .await
+    .unwrap_err();
+    assert!(matches!(err, WaitError::Cancelled));
+}
+
+/// Hands out "old" until asked to refresh, "new" afterwards.
+struct Tokens {
+    refreshed: AtomicUsize,
+}
+
+#[async_trait]
+impl TokenProvider for Tokens {
+    async fn token(&self, refresh: bool) -> Result<String, Box<dyn Error>> {
+        if refresh {
+            self.refreshed.fetch_add(1, Ordering::SeqCst);
+        }
+        match self.refreshed.load(Ordering::SeqCst) {
+            0 => Ok("old".into()),
+            _ => Ok("new".into()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn token_refresh() {
+    // answers 401 to any token but "new", and records the ones it got
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorded = seen.clone();
+    let make_service = make_service_fn(move |_| {
+        let seen = recorded.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let authorization = request
+                    .headers()
+                    .get(AUTHORIZATION)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                let status = match authorization.as_str() {
+                    "Bearer new" => 200,
+                    _ => 401,
+                };
+                seen.lock().unwrap().push(authorization);
+                async move {
+                    Ok::<_, Infallible>(
+                        Response::builder()
+                            .status(status)
+                            .header("Content-Type", "application/json")
+                            .body(Body::from("{}"))
+                            .unwrap(),
+                    )
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+
+    let tokens = Arc::new(Tokens {
+        refreshed: AtomicUsize::new(0),
+    });
+    let client = Client::builder(&url)
+        .token_provider(tokens.clone())
+        .build()
+        .unwrap();
+
+    client.fetch_instance("instance").await.unwrap();
+    client.fetch_instance("instance").await.unwrap();
+    assert_eq!(tokens.refreshed.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["Bearer old", "Bearer new", "Bearer new"]
+    );
+}
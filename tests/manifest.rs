@@ -0,0 +1,193 @@
+//! Tests of the changes computed between a manifest and the local store.
+
+use chrono::NaiveDateTime;
+use rocs::manifest::{Change, Manifest};
+use rocs::store::{Binding, Instance};
+
+fn manifest(yaml: &str) -> Manifest {
+    let manifest: Manifest = serde_yaml::from_str(yaml).unwrap();
+    manifest.validate().unwrap();
+    manifest
+}
+
+fn instance(id: &str, name: Option<&str>, plan: &str, parameters: &str) -> Instance {
+    Instance {
+        instance_id: id.into(),
+        service_id: "service-id".into(),
+        plan_id: format!("{}-id", plan),
+        service_name: "postgres".into(),
+        plan_name: plan.into(),
+        parameters: parameters.into(),
+        state: "succeeded".into(),
+        created_at: NaiveDateTime::default(),
+        updated_at: NaiveDateTime::default(),
+        name: name.map(String::from),
+        broker_url: "https://broker".into(),
+    }
+}
+
+fn binding(id: &str, instance_id: &str, name: Option<&str>) -> Binding {
+    Binding {
+        binding_id: id.into(),
+        instance_id: instance_id.into(),
+        created_at: NaiveDateTime::default(),
+        updated_at: NaiveDateTime::default(),
+        state: "succeeded".into(),
+        name: name.map(String::from),
+    }
+}
+
+fn actions(changes: &[Change]) -> Vec<String> {
+    changes
+        .iter()
+        .map(|c| match c {
+            Change::Provision(i) => format!("provision {}", i.name),
+            Change::Update {
+                instance, fields, ..
+            } => {
+                format!("update {} {}", instance.name, fields.join(","))
+            }
+            Change::Unbind { name, .. } => format!("unbind {}", name),
+            Change::Bind(b) => format!("bind {}", b.name),
+            Change::Deprovision { name, .. } => format!("deprovision {}", name),
+        })
+        .collect()
+}
+
+const MANIFEST: &str = "
+instances:
+  - name: db
+    service: postgres
+    plan: small
+    parameters: {version: '15'}
+  - name: cache
+    service: postgres
+    plan: small
+bindings:
+  - name: app
+    instance: db
+";
+
+#[test]
+fn creates_missing_entries() {
+    let changes = manifest(MANIFEST).changes(&[], &[], false).unwrap();
+    assert_eq!(
+        actions(&changes),
+        ["provision db", "provision cache", "bind app"]
+    );
+}
+
+#[test]
+fn updates_changed_instances() {
+    let instances = [
+        instance("1", Some("db"), "large", r#"{"version":"15"}"#),
+        instance("2", Some("cache"), "small", "{}"),
+    ];
+    let bindings = [binding("b1", "1", Some("app"))];
+
+    let changes = manifest(MANIFEST)
+        .changes(&instances, &bindings, false)
+        .unwrap();
+    assert_eq!(actions(&changes), ["update db plan"]);
+
+    let instances = [
+        instance("1", Some("db"), "small", r#"{"version":"14"}"#),
+        instance("2", Some("cache"), "small", "{}"),
+    ];
+    let changes = manifest(MANIFEST)
+        .changes(&instances, &bindings, false)
+        .unwrap();
+    assert_eq!(actions(&changes), ["update db parameters"]);
+}
+
+#[test]
+fn prunes_removed_entries() {
+    let instances = [
+        instance("1", Some("db"), "small", r#"{"version":"15"}"#),
+        instance("2", Some("cache"), "small", "{}"),
+        instance("3", Some("old"), "small", "{}"),
+        instance("4", None, "small", "{}"),
+    ];
+    let bindings = [
+        binding("b1", "1", Some("app")),
+        binding("b2", "1", Some("worker")),
+        binding("b3", "3", None),
+        binding("b4", "4", None),
+    ];
+
+    let changes = manifest(MANIFEST)
+        .changes(&instances, &bindings, false)
+        .unwrap();
+    assert!(changes.is_empty());
+
+    let changes = manifest(MANIFEST)
+        .changes(&instances, &bindings, true)
+        .unwrap();
+    assert_eq!(
+        actions(&changes),
+        ["unbind worker", "unbind b3", "deprovision old"]
+    );
+}
+
+#[test]
+fn replaces_moved_bindings() {
+    let instances = [
+        instance("1", Some("db"), "small", r#"{"version":"15"}"#),
+        instance("2", Some("cache"), "small", "{}"),
+    ];
+    let bindings = [binding("b1", "2", Some("app"))];
+
+    let changes = manifest(MANIFEST)
+        .changes(&instances, &bindings, false)
+        .unwrap();
+    assert_eq!(actions(&changes), ["unbind app", "bind app"]);
+}
+
+#[test]
+fn replaces_failed_entries() {
+    let mut instances = [
+        instance("1", Some("db"), "small", r#"{"version":"15"}"#),
+        instance("2", Some("cache"), "small", "{}"),
+    ];
+    let mut bindings = [binding("b1", "1", Some("app"))];
+
+    bindings[0].state = "failed".into();
+    let changes = manifest(MANIFEST)
+        .changes(&instances, &bindings, false)
+        .unwrap();
+    assert_eq!(actions(&changes), ["unbind app", "bind app"]);
+
+    // the failed instance's bindings go away with it
+    instances[0].state = "failed".into();
+    bindings[0].state = "succeeded".into();
+    let changes = manifest(MANIFEST)
+        .changes(&instances, &bindings, false)
+        .unwrap();
+    assert_eq!(
+        actions(&changes),
+        ["deprovision db", "provision db", "bind app"]
+    );
+}
+
+#[test]
+fn rejects_invalid_manifests() {
+    let changed_service = "instances: [{name: db, service: mysql, plan: small}]";
+    let instances = [instance("1", Some("db"), "small", "{}")];
+    let err = manifest(changed_service)
+        .changes(&instances, &[], false)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "instance db cannot change service from postgres to mysql, remove it first"
+    );
+
+    let dangling: Manifest = serde_yaml::from_str("bindings: [{name: app, instance: db}]").unwrap();
+    assert_eq!(
+        dangling.validate().unwrap_err().to_string(),
+        "binding app refers to instance db which is not in the manifest"
+    );
+
+    assert!(
+        serde_yaml::from_str::<Manifest>("instances: [{name: db, servce: x, plan: y}]").is_err()
+    );
+}
@@ -0,0 +1,647 @@
+//! End-to-end tests running the rocs binary against the in-memory mock broker
+//! or replaying cassettes, no network access needed.
+
+use rocs::mock::{self, MockBroker};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::Duration;
+use uuid::Uuid;
+
+struct Rocs {
+    url: String,
+    home: PathBuf,
+}
+
+impl Rocs {
+    async fn start(failure_rate: f64) -> Rocs {
+        let path = format!("{}/tests/fixtures/catalog.yaml", env!("CARGO_MANIFEST_DIR"));
+        let catalog = serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        let broker = MockBroker {
+            catalog,
+            delay: Duration::from_secs(1),
+            failure_rate,
+            credentials: Some(("user".into(), "secret".into())),
+        };
+        let url = mock::start(broker, "127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+
+        // rocs keeps its local store in the home directory
+        let home = std::env::temp_dir().join(format!("rocs-{}", Uuid::new_v4()));
+        fs::create_dir_all(home.join(".local")).unwrap();
+
+        Rocs { url, home }
+    }
+
+    fn run_as(&self, password: &str, args: &[&str]) -> Output {
+        run(&self.home, &self.url, password, args)
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.run_as("secret", args)
+    }
+
+    fn json(&self, args: &[&str]) -> Value {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "rocs {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+}
+
+impl Drop for Rocs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.home);
+    }
+}
+
+fn run(home: &Path, url: &str, password: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rocs"))
+        .env("HOME", home)
+        .env_remove("ROCS_PROFILE")
+        .args(["--broker", url, "-u", "user", "-a", password])
+        .args(["-o", "json"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs rocs without any broker settings, for commands reading only local state.
+fn local(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rocs"))
+        .env("HOME", home)
+        .env_remove("ROCS_PROFILE")
+        .env_remove("ROCS_BROKER_URL")
+        .env_remove("XDG_CONFIG_HOME")
+        .args(["-o", "json"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn error(output: &Output) -> Value {
+    assert!(!output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn catalog() {
+    let rocs = Rocs::start(0.0).await;
+
+    let catalog = rocs.json(&["catalog"]);
+    assert_eq!(catalog["kind"], "Catalog");
+    assert_eq!(catalog["services"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lifecycle() {
+    let rocs = Rocs::start(0.0).await;
+
+    let instance = rocs.json(&[
+        "--sync",
+        "provision",
+        "-s",
+        "postgres",
+        "-p",
+        "small",
+        "-P",
+        "version=16",
+    ]);
+    let instance_id = instance["service_instance_id"].as_str().unwrap();
+    assert_eq!(
+        instance["service_instance_resource"]["parameters"]["version"],
+        "16"
+    );
+
+    let binding = rocs.json(&["--sync", "bind", "-i", instance_id]);
+    let binding_id = binding["service_binding_id"].as_str().unwrap();
+    assert_eq!(
+        binding["service_binding_resource"]["credentials"]["username"],
+        binding_id
+    );
+
+    // generated IDs are recorded in the operation history
+    let history = rusqlite::Connection::open(rocs.home.join(".local/rocs")).unwrap();
+    let recorded: Vec<(String, String, String)> = history
+        .prepare("SELECT command, instance_id, binding_id FROM operations")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        recorded,
+        [
+            ("provision".into(), instance_id.into(), "".into()),
+            ("bind".into(), instance_id.into(), binding_id.into()),
+        ]
+    );
+
+    rocs.json(&["--sync", "unbind", "-i", instance_id, "-b", binding_id]);
+    rocs.json(&["--sync", "deprovision", "-i", instance_id]);
+
+    let output = rocs.run(&["--sync", "deprovision", "-i", instance_id]);
+    assert_eq!(error(&output)["status"], 410);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn local_store() {
+    let rocs = Rocs::start(0.0).await;
+
+    let instance = rocs.json(&["--sync", "provision", "-s", "postgres", "-p", "small"]);
+    let instance_id = instance["service_instance_id"].as_str().unwrap();
+    rocs.json(&["--sync", "bind", "-i", instance_id]);
+
+    let output = local(&rocs.home, &["instances"]);
+    assert!(output.status.success());
+    let instances: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        instances["service_instances"][0]["service_instance_id"],
+        instance_id
+    );
+
+    let output = local(&rocs.home, &["bindings", "-i", instance_id]);
+    assert!(output.status.success());
+    let bindings: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(bindings["kind"], "ServiceBindingList");
+}
+
+#[test]
+fn lint_file() {
+    let home = std::env::temp_dir().join(format!("rocs-{}", Uuid::new_v4()));
+    fs::create_dir_all(home.join(".local")).unwrap();
+    let catalog = format!("{}/tests/fixtures/catalog.yaml", env!("CARGO_MANIFEST_DIR"));
+
+    let output = local(&home, &["catalog", "lint", "--file", &catalog]);
+    fs::remove_dir_all(&home).unwrap();
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["kind"], "CatalogLint");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn diff_profile() {
+    let rocs = Rocs::start(0.0).await;
+    let catalog = format!("{}/tests/fixtures/catalog.yaml", env!("CARGO_MANIFEST_DIR"));
+    let trace = rocs.home.join("trace.har");
+    fs::create_dir_all(rocs.home.join(".config/rocs")).unwrap();
+    fs::write(
+        rocs.home.join(".config/rocs/profiles.yaml"),
+        format!(
+            "mock:\n  broker_url: {}\n  username: user\n  password: secret\n",
+            rocs.url
+        ),
+    )
+    .unwrap();
+
+    let output = local(
+        &rocs.home,
+        &[
+            "--request-id",
+            "diff-request",
+            "--trace-file",
+            trace.to_str().unwrap(),
+            "catalog",
+            "diff",
+            "--from",
+            &catalog,
+            "--to",
+            "mock",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let diff: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["kind"], "CatalogDiff");
+    assert_eq!(diff["breaking"], false);
+
+    // the profile's client carries the request identity and is traced
+    let har: Value = serde_json::from_str(&fs::read_to_string(&trace).unwrap()).unwrap();
+    let headers = &har["log"]["entries"][0]["request"]["headers"];
+    assert!(headers.as_array().unwrap().iter().any(|header| {
+        header["name"]
+            .as_str()
+            .unwrap()
+            .eq_ignore_ascii_case("x-broker-api-request-identity")
+            && header["value"] == "diff-request"
+    }));
+}
+
+#[test]
+fn proxy_precedence() {
+    let home = std::env::temp_dir().join(format!("rocs-{}", Uuid::new_v4()));
+    fs::create_dir_all(home.join(".config/rocs")).unwrap();
+    fs::write(
+        home.join(".config/rocs/profiles.yaml"),
+        "direct:\n  broker_url: http://127.0.0.1:9\n  username: user\n  no_proxy: true\n",
+    )
+    .unwrap();
+
+    let curl = |args: &[&str]| {
+        let output = local(&home, &[&["--profile", "direct", "--curl"], args].concat());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert!(curl(&["catalog"]).contains("--noproxy '*'"));
+    assert!(
+        curl(&["--proxy", "http://proxy:3128", "catalog"]).contains("--proxy http://proxy:3128")
+    );
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_parameters() {
+    let rocs = Rocs::start(0.0).await;
+
+    let output = rocs.run(&[
+        "--sync",
+        "provision",
+        "-s",
+        "postgres",
+        "-p",
+        "small",
+        "-P",
+        "version=9",
+    ]);
+    // the plan's schema rejects the parameters before anything is sent
+    let error = error(&output);
+    assert_eq!(error["status"], Value::Null);
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid parameters for plan small"));
+    assert_eq!(
+        rocs.json(&["instances"])["service_instances"],
+        serde_json::json!([])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn not_bindable() {
+    let rocs = Rocs::start(0.0).await;
+
+    let instance = rocs.json(&["--sync", "provision", "-s", "logs", "-p", "default"]);
+    let instance_id = instance["service_instance_id"].as_str().unwrap();
+
+    let output = rocs.run(&["--sync", "bind", "-i", instance_id]);
+    assert_eq!(error(&output)["status"], 400);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_credentials() {
+    let rocs = Rocs::start(0.0).await;
+
+    let output = rocs.run_as("wrong", &["--refresh-catalog", "catalog"]);
+    assert_eq!(error(&output)["status"], 401);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn simulated_failures() {
+    let rocs = Rocs::start(1.0).await;
+
+    let output = rocs.run(&["--sync", "provision", "-s", "postgres", "-p", "small"]);
+    assert_eq!(error(&output)["status"], 500);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn conformance() {
+    let rocs = Rocs::start(0.0).await;
+
+    let report = rocs.json(&["conformance", "-s", "postgres", "-p", "small"]);
+    assert_eq!(report["failed"], 0, "{}", report);
+    assert_eq!(report["skipped"], 0, "{}", report);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn record_and_replay() {
+    let rocs = Rocs::start(0.0).await;
+    let cassette = rocs.home.join("cassette.jsonl");
+    let cassette = cassette.to_str().unwrap();
+
+    let instance = rocs.json(&[
+        "--record",
+        cassette,
+        "--sync",
+        "provision",
+        "-s",
+        "postgres",
+        "-p",
+        "small",
+    ]);
+    let instance_id = instance["service_instance_id"].as_str().unwrap();
+    rocs.json(&["--record", cassette, "--sync", "bind", "-i", instance_id]);
+
+    let recorded = fs::read_to_string(cassette).unwrap();
+    assert!(!recorded.contains("secret"));
+    assert!(recorded.contains("\"credentials\":\"[REDACTED]\""));
+
+    // nothing listens on the discard port, every answer comes from the cassette
+    let output = run(
+        &rocs.home,
+        "http://127.0.0.1:9",
+        "secret",
+        &["--replay", cassette, "--sync", "bind", "-i", instance_id],
+    );
+    assert!(output.status.success());
+    let binding: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(binding["kind"], "ServiceBinding");
+    assert_eq!(
+        binding["service_binding_resource"]["credentials"],
+        "[REDACTED]"
+    );
+}
+
+#[test]
+fn replay_fixture() {
+    let home = std::env::temp_dir().join(format!("rocs-{}", Uuid::new_v4()));
+    fs::create_dir_all(home.join(".local")).unwrap();
+    let cassette = format!(
+        "{}/tests/fixtures/provision.jsonl",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    // neither a broker nor credentials are needed to replay
+    let output = local(
+        &home,
+        &[
+            "--replay",
+            &cassette,
+            "--sync",
+            "provision",
+            "-s",
+            "postgres",
+            "-p",
+            "small",
+            "-P",
+            "version=16",
+        ],
+    );
+    fs::remove_dir_all(&home).unwrap();
+
+    assert!(output.status.success());
+    let instance: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        instance["service_instance_resource"]["parameters"]["version"],
+        "16"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn apply_manifest() {
+    let rocs = Rocs::start(0.0).await;
+    let manifest = rocs.home.join("services.yaml");
+    let file = manifest.to_str().unwrap();
+
+    fs::write(
+        &manifest,
+        "instances:
+  - name: db
+    service: postgres
+    plan: small
+    parameters: {version: \"15\"}
+bindings:
+  - name: app
+    instance: db
+",
+    )
+    .unwrap();
+
+    let plan = rocs.json(&["plan", "-f", file]);
+    let actions: Vec<_> = plan["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["provision", "bind"]);
+
+    let applied = rocs.json(&["--sync", "apply", "-f", file]);
+    assert_eq!(applied["applied"], true);
+    assert_eq!(applied["changes"][1]["state"], "succeeded");
+    assert!(rocs.json(&["plan", "-f", file])["changes"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    fs::write(
+        &manifest,
+        "instances:
+  - name: db
+    service: postgres
+    plan: large
+    parameters: {version: \"15\"}
+",
+    )
+    .unwrap();
+
+    let applied = rocs.json(&["--sync", "apply", "-f", file]);
+    assert_eq!(applied["changes"].as_array().unwrap().len(), 1);
+    assert_eq!(applied["changes"][0]["action"], "update");
+    assert_eq!(applied["changes"][0]["fields"][0], "plan");
+
+    fs::write(&manifest, "instances: []\n").unwrap();
+    // another broker's instances are not pruned
+    let other = Rocs::start(0.0).await;
+    let output = run(
+        &rocs.home,
+        &other.url,
+        "secret",
+        &["plan", "-f", file, "--prune"],
+    );
+    assert!(output.status.success());
+    let plan: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(plan["changes"].as_array().unwrap().is_empty());
+
+    let applied = rocs.json(&["--sync", "apply", "-f", file, "--prune"]);
+    let actions: Vec<_> = applied["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["unbind", "deprovision"]);
+    assert!(rocs.json(&["instances"])["service_instances"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batch() {
+    let rocs = Rocs::start(0.0).await;
+    let (first, second) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+    let file = rocs.home.join("batch.jsonl");
+    let results = rocs.home.join("results.jsonl");
+    let records = || -> Vec<Value> {
+        let mut records: Vec<Value> = fs::read_to_string(&results)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        records.sort_by_key(|r| r["line"].as_u64());
+        records
+    };
+
+    let provision = |id: &str, service: &str| {
+        format!(
+            r#"{{"operation": "provision", "service": "{}", "plan": "small", "instance_id": "{}"}}"#,
+            service, id
+        )
+    };
+    fs::write(
+        &file,
+        [
+            provision(&first, "postgres"),
+            provision(&second, "postgres"),
+            String::new(),
+            provision(&Uuid::new_v4().to_string(), "mysql"),
+        ]
+        .join("\n"),
+    )
+    .unwrap();
+
+    let args = ["--sync", "batch", file.to_str().unwrap()];
+    let args = [&args[..], &["-r", results.to_str().unwrap()]].concat();
+    let output = rocs.run(&[&args[..], &["-c", "3", "--continue-on-error"]].concat());
+    assert_eq!(output.status.code(), Some(1));
+    let states: Vec<_> = records().iter().map(|r| r["state"].clone()).collect();
+    assert_eq!(states, ["succeeded", "succeeded", "failed"]);
+    assert_eq!(records()[2]["line"], 4);
+    assert_eq!(records()[2]["kind"], "BatchResult");
+
+    fs::write(
+        &file,
+        format!(
+            "{}\n{}\n{}\n{}\n",
+            json_line("update", &first, r#", "plan": "large""#),
+            json_line("bind", &first, r#", "binding_id": "app""#),
+            json_line("deprovision", &Uuid::new_v4().to_string(), ""),
+            json_line("deprovision", &second, ""),
+        ),
+    )
+    .unwrap();
+
+    let output = rocs.run(&[&args[..], &["-c", "1"]].concat());
+    assert_eq!(output.status.code(), Some(1));
+    let written = records();
+    let states: Vec<_> = written.iter().map(|r| r["state"].clone()).collect();
+    assert_eq!(states, ["succeeded", "succeeded", "failed", "skipped"]);
+    assert_eq!(written[1]["service_binding_id"], "app");
+    assert_eq!(written[2]["status"], 410);
+
+    let instance = rocs.json(&["describe", "-i", &first]);
+    assert_eq!(instance["plan_name"], "large");
+    assert_eq!(
+        rocs.json(&["bindings", "-i", &first])["service_bindings"][0]["service_binding_id"],
+        "app"
+    );
+    assert!(rocs.run(&["describe", "-i", &second]).status.success());
+
+    // a bind waits for the asynchronous provisioning of its instance above it
+    let third = Uuid::new_v4().to_string();
+    fs::write(
+        &file,
+        format!(
+            "{}\n{}\n",
+            provision(&third, "postgres"),
+            json_line("bind", &third, r#", "binding_id": "worker""#),
+        ),
+    )
+    .unwrap();
+    let args = [
+        "batch",
+        file.to_str().unwrap(),
+        "-r",
+        results.to_str().unwrap(),
+    ];
+    let output = rocs.run(&[&args[..], &["-c", "4"]].concat());
+    assert!(output.status.success());
+    let states: Vec<_> = records().iter().map(|r| r["state"].clone()).collect();
+    assert_eq!(states, ["succeeded", "succeeded"]);
+}
+
+fn json_line(operation: &str, instance_id: &str, fields: &str) -> String {
+    format!(
+        r#"{{"operation": "{}", "instance_id": "{}"{}}}"#,
+        operation, instance_id, fields
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deprovision_all() {
+    let rocs = Rocs::start(0.0).await;
+    let provision = |plan: &str| {
+        let instance = rocs.json(&["--sync", "provision", "-s", "postgres", "-p", plan]);
+        instance["service_instance_id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let small = [provision("small"), provision("small")];
+    let large = provision("large");
+    rocs.json(&["--sync", "bind", "-i", &small[0]]);
+
+    let selection = [
+        "--sync",
+        "deprovision",
+        "--all",
+        "--plan",
+        "small",
+        "--cascade",
+    ];
+    let output = rocs.run(&selection);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("deprovision 2 instance(s)?"));
+
+    // another broker's instances are kept
+    let other = Rocs::start(0.0).await;
+    let output = run(
+        &rocs.home,
+        &other.url,
+        "secret",
+        &[&selection[..], &["--yes"]].concat(),
+    );
+    assert!(output.status.success());
+    let kept: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(kept["changes"].as_array().unwrap().is_empty());
+
+    let old = rocs.json(&[&selection[..], &["--older-than", "7d", "--yes"]].concat());
+    assert!(old["changes"].as_array().unwrap().is_empty());
+
+    let removed = rocs.json(&[&selection[..], &["--yes", "-c", "2"]].concat());
+    let actions: Vec<_> = removed["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| (c["action"].as_str().unwrap(), c["state"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            ("unbind", "succeeded"),
+            ("deprovision", "succeeded"),
+            ("deprovision", "succeeded")
+        ]
+    );
+
+    let instances = rocs.json(&["instances"]);
+    let remaining: Vec<_> = instances["service_instances"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["service_instance_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(remaining, [large.as_str()]);
+    assert!(rocs.json(&["bindings"])["service_bindings"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
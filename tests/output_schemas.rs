@@ -0,0 +1,356 @@
+//! Golden-file tests for the documents printed with `-o json`. A failure here
+//! means scripts consuming rocs output would break: either restore the previous
+//! shape or bump `OUTPUT_API_VERSION`, then regenerate the files with
+//! `UPDATE_GOLDEN=1 cargo test --test output_schemas`.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rocl::models::{
+    last_operation_resource::State, LastOperationResource, MaintenanceInfo, Plan, Service,
+    ServiceBindingResource, ServiceInstanceResource,
+};
+use rocs::models::{
+    BatchResultOutput, CatalogChange, CatalogDiffOutput, CatalogLintOutput, CatalogOutput,
+    ChangeSetOutput, ConformanceCheck, ConformanceOutput, ErrorOutput, LastOperationOutput,
+    LintFinding, Parameter, ParametersOutput, PlanOutput, ResourceChange, ServiceBindingListOutput,
+    ServiceBindingOutput, ServiceBindingSummary, ServiceInstanceDescriptionOutput,
+    ServiceInstanceListOutput, ServiceInstanceOutput, ServiceInstanceSummary, ServiceOutput,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use std::fmt::Debug;
+use std::fs;
+
+fn assert_golden<T>(name: &str, value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let path = format!("{}/tests/golden/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+    let actual = serde_json::to_string_pretty(value).unwrap() + "\n";
+
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        fs::write(&path, &actual).unwrap();
+    }
+
+    let expected = fs::read_to_string(&path).unwrap();
+    assert_eq!(actual, expected, "{} does not match {}", name, path);
+
+    let parsed: T = serde_json::from_str(&expected).unwrap();
+    assert_eq!(&parsed, value);
+}
+
+fn timestamp(hour: u32, min: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2022, 6, 1)
+        .and_then(|d| d.and_hms_opt(hour, min, 0))
+        .unwrap()
+}
+
+#[test]
+fn catalog_output() {
+    let mut plan = Plan::new("plan-id".into(), "small".into(), "Small plan".into());
+    plan.free = Some(true);
+    let mut service = Service::new(
+        "database".into(),
+        "service-id".into(),
+        "Database".into(),
+        true,
+        vec![plan],
+    );
+    service.tags = Some(vec!["sql".into()]);
+
+    assert_golden("catalog", &CatalogOutput::new(vec![service]));
+}
+
+#[test]
+fn catalog_diff_output() {
+    let removed = CatalogChange {
+        change: "removed".into(),
+        object: "plan".into(),
+        service_id: "service-id".into(),
+        service_name: "database".into(),
+        plan_id: Some("plan-id".into()),
+        plan_name: Some("small".into()),
+        field: None,
+        from: None,
+        to: None,
+        breaking: true,
+    };
+    let modified = CatalogChange {
+        change: "modified".into(),
+        object: "service".into(),
+        plan_id: None,
+        plan_name: None,
+        field: Some("description".into()),
+        from: Some(json!("Database")),
+        to: Some(json!("Managed database")),
+        breaking: false,
+        ..removed.clone()
+    };
+
+    assert_golden(
+        "catalog_diff",
+        &CatalogDiffOutput::new(vec![removed, modified]),
+    );
+}
+
+#[test]
+fn batch_result_output() {
+    let result = BatchResultOutput {
+        state: "failed".into(),
+        service_instance_id: Some("instance-id".into()),
+        service_binding_id: Some("binding-id".into()),
+        error: Some("binding failed: status code 409".into()),
+        status: Some(409),
+        ..BatchResultOutput::new(3, "bind".into())
+    };
+
+    assert_golden("batch_result", &result);
+}
+
+#[test]
+fn change_set_output() {
+    let update = ResourceChange {
+        action: "update".into(),
+        name: "db".into(),
+        instance: None,
+        service: Some("postgres".into()),
+        plan: Some("large".into()),
+        fields: vec!["plan".into()],
+        service_instance_id: Some("instance-id".into()),
+        service_binding_id: None,
+        state: Some("succeeded".into()),
+        error: None,
+    };
+    let bind = ResourceChange {
+        action: "bind".into(),
+        name: "app".into(),
+        instance: Some("db".into()),
+        service: None,
+        plan: None,
+        fields: vec![],
+        service_binding_id: None,
+        state: Some("failed".into()),
+        error: Some("binding failed: status code 500".into()),
+        ..update.clone()
+    };
+
+    assert_golden(
+        "change_set",
+        &ChangeSetOutput::new(true, vec![update, bind]),
+    );
+}
+
+#[test]
+fn catalog_lint_output() {
+    let findings = vec![
+        LintFinding {
+            severity: "error".into(),
+            rule: "maintenance-info-version".into(),
+            path: "services[0].plans[0].maintenance_info.version".into(),
+            message: "1.2 is not a semantic version".into(),
+        },
+        LintFinding {
+            severity: "warning".into(),
+            rule: "id-format".into(),
+            path: "services[0].id".into(),
+            message: "service id database is not a GUID, which is recommended".into(),
+        },
+    ];
+
+    assert_golden("catalog_lint", &CatalogLintOutput::new(findings));
+}
+
+#[test]
+fn service_output() {
+    let mut service = Service::new(
+        "database".into(),
+        "service-id".into(),
+        "Database".into(),
+        true,
+        vec![Plan::new(
+            "plan-id".into(),
+            "small".into(),
+            "Small plan".into(),
+        )],
+    );
+    service.plan_updateable = Some(true);
+    service.metadata = Some(json!({"displayName": "Managed Database"}));
+
+    assert_golden("service", &ServiceOutput::new(service));
+}
+
+#[test]
+fn plan_output() {
+    let mut plan = Plan::new("plan-id".into(), "small".into(), "Small plan".into());
+    plan.free = Some(false);
+    plan.maintenance_info = Some(Box::new(MaintenanceInfo::new("1.0.0".into())));
+    plan.metadata = Some(json!({
+        "bullets": ["1 GB storage"],
+        "costs": [{"amount": {"usd": 9.0}, "unit": "MONTHLY"}],
+    }));
+
+    let output = PlanOutput::new("service-id".into(), "database".into(), true, plan);
+    assert_golden("plan", &output);
+}
+
+#[test]
+fn parameters_output() {
+    let mut size = Parameter::new("size".into(), true);
+    size._type = Some("integer".into());
+    size.description = Some("Disk size in GB".into());
+    size.default = Some(json!(10));
+    size.minimum = Some(json!(1));
+    size.maximum = Some(json!(100));
+
+    let mut region = Parameter::new("network.region".into(), false);
+    region._type = Some("string".into());
+    region._enum = Some(vec![json!("us-east-1"), json!("eu-west-1")]);
+
+    let output = ParametersOutput::new(
+        "database".into(),
+        "small".into(),
+        "create".into(),
+        vec![size, region],
+    );
+    assert_golden("parameters", &output);
+}
+
+#[test]
+fn conformance_output() {
+    let check = |name: &str, status: &str, duration_ms, message: Option<&str>| ConformanceCheck {
+        name: name.into(),
+        status: status.into(),
+        duration_ms,
+        message: message.map(String::from),
+    };
+
+    let mut output = ConformanceOutput::new(
+        "https://broker.example.com".into(),
+        vec![
+            check("catalog", "passed", 12, None),
+            check(
+                "invalid-version",
+                "failed",
+                8,
+                Some("expected 412 Precondition Failed, got: status code 200"),
+            ),
+            check("bind", "skipped", 0, Some("the plan is not bindable")),
+        ],
+    );
+    output.service_name = Some("database".into());
+    output.plan_name = Some("small".into());
+
+    assert_golden("conformance", &output);
+}
+
+#[test]
+fn service_instance_output() {
+    let mut resource = ServiceInstanceResource::new();
+    resource.service_id = Some("service-id".into());
+    resource.plan_id = Some("plan-id".into());
+    resource.dashboard_url = Some("https://dashboard.example.com/instance-id".into());
+    resource.parameters = Some(json!({"region": "us-east-1"}));
+
+    let output = ServiceInstanceOutput {
+        service_instance_id: Some("instance-id".into()),
+        service_instance_resource: Some(resource),
+        ..ServiceInstanceOutput::new()
+    };
+    assert_golden("service_instance", &output);
+}
+
+#[test]
+fn service_binding_output() {
+    let mut resource = ServiceBindingResource::new();
+    resource.credentials = Some(json!({"username": "user", "password": "secret"}));
+
+    let output = ServiceBindingOutput {
+        service_instance_id: Some("instance-id".into()),
+        service_binding_id: Some("binding-id".into()),
+        service_binding_resource: Some(resource),
+        ..ServiceBindingOutput::new()
+    };
+    assert_golden("service_binding", &output);
+}
+
+#[test]
+fn service_instance_list_output() {
+    let created_at = timestamp(12, 0);
+
+    let output = ServiceInstanceListOutput::new(vec![ServiceInstanceSummary {
+        service_instance_id: "instance-id".into(),
+        service_id: "service-id".into(),
+        service_name: "database".into(),
+        plan_id: "plan-id".into(),
+        plan_name: "small".into(),
+        state: "succeeded".into(),
+        created_at,
+        updated_at: created_at,
+        binding_count: 1,
+    }]);
+    assert_golden("service_instance_list", &output);
+}
+
+#[test]
+fn service_binding_list_output() {
+    let output = ServiceBindingListOutput::new(vec![ServiceBindingSummary {
+        service_binding_id: "binding-id".into(),
+        service_instance_id: "instance-id".into(),
+        service_name: "database".into(),
+        plan_name: "small".into(),
+        state: "in progress".into(),
+        created_at: timestamp(12, 5),
+    }]);
+    assert_golden("service_binding_list", &output);
+}
+
+#[test]
+fn service_instance_description_output() {
+    let mut output = ServiceInstanceDescriptionOutput::new("instance-id".into());
+    output.service_id = Some("service-id".into());
+    output.service_name = Some("database".into());
+    output.service_description = Some("Database".into());
+    output.plan_id = Some("plan-id".into());
+    output.plan_name = Some("small".into());
+    output.plan_description = Some("Small plan".into());
+    output.maintenance_info = Some(MaintenanceInfo::new("1.0.0".into()));
+    output.plan_maintenance_info = Some(MaintenanceInfo::new("1.1.0".into()));
+    output.parameters = Some(json!({"region": "us-east-1"}));
+    output.requested_parameters = Some(json!({"region": "us-east-1"}));
+    output.last_operation = Some(LastOperationResource::new(State::Succeeded));
+    output.created_at = Some(timestamp(12, 0));
+    output.service_bindings = vec![ServiceBindingSummary {
+        service_binding_id: "binding-id".into(),
+        service_instance_id: "instance-id".into(),
+        service_name: "database".into(),
+        plan_name: "small".into(),
+        state: "succeeded".into(),
+        created_at: timestamp(12, 5),
+    }];
+    assert_golden("service_instance_description", &output);
+}
+
+#[test]
+fn last_operation_output() {
+    let mut last_operation = LastOperationResource::new(State::InProgress);
+    last_operation.description = Some("creating database".into());
+    last_operation.instance_usable = Some(false);
+
+    let mut output = LastOperationOutput::new("instance-id".into(), last_operation);
+    output.service_binding_id = Some("binding-id".into());
+    assert_golden("last_operation", &output);
+}
+
+#[test]
+fn error_output() {
+    let mut broker_error = rocl::models::Error::new();
+    broker_error.error = Some("ConcurrencyError".into());
+    broker_error.description = Some("another operation is in progress".into());
+
+    let mut output = ErrorOutput::new(
+        "provision request failed: status code 422".into(),
+        "request-id".into(),
+    );
+    output.status = Some(422);
+    output.broker_error = Some(broker_error);
+    assert_golden("error", &output);
+}
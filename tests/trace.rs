@@ -0,0 +1,118 @@
+//! Tests of traced broker traffic and of the local proxy forwarding it.
+
+use chrono::Utc;
+use rocs::http::NetworkSettings;
+use rocs::proxy::{self, LocalProxy};
+use rocs::trace::{Exchange, Tracer};
+use rocs::{Client, ClientError};
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+fn exchange() -> Exchange {
+    Exchange {
+        started: Utc::now(),
+        elapsed: Duration::from_millis(12),
+        method: "PUT".into(),
+        url: "https://broker/v2/service_instances/1/service_bindings/2".into(),
+        request_headers: vec![
+            ("Authorization".into(), "Basic dXNlcjpzZWNyZXQ=".into()),
+            (
+                "Proxy-Authorization".into(),
+                "Basic cHJveHk6c2VjcmV0".into(),
+            ),
+            ("X-Broker-API-Version".into(), "2.15".into()),
+        ],
+        request_body: r#"{"service_id":"s","plan_id":"p"}"#.into(),
+        status: 201,
+        status_text: "Created".into(),
+        response_headers: vec![("content-type".into(), "application/json".into())],
+        response_body:
+            r#"{"credentials":{"password":"secret"},"endpoints":[{"credentials":"secret"}]}"#.into(),
+    }
+}
+
+#[test]
+fn redaction() {
+    let redacted = exchange().redacted();
+
+    let header = |name: &str| {
+        redacted
+            .request_headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap()
+    };
+    assert_eq!(header("Authorization"), "[REDACTED]");
+    assert_eq!(header("Proxy-Authorization"), "[REDACTED]");
+    assert_eq!(header("X-Broker-API-Version"), "2.15");
+
+    let body: Value = serde_json::from_str(&redacted.response_body).unwrap();
+    assert_eq!(body["credentials"], "[REDACTED]");
+    assert_eq!(body["endpoints"][0]["credentials"], "[REDACTED]");
+    let body: Value = serde_json::from_str(&redacted.request_body).unwrap();
+    assert_eq!(body["plan_id"], "p");
+}
+
+#[test]
+fn trace_file_redaction() {
+    let path = std::env::temp_dir().join(format!("rocs-{}.har", Uuid::new_v4()));
+    let tracer = Tracer::new(0, Some(path.to_str().unwrap().into()));
+    tracer.response(&exchange()).unwrap();
+
+    let har = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(!har.contains("secret"));
+    assert!(!har.contains("dXNlcjpzZWNyZXQ="));
+    let har: Value = serde_json::from_str(&har).unwrap();
+    assert_eq!(har["log"]["entries"][0]["response"]["status"], 201);
+}
+
+#[tokio::test]
+async fn proxy_timeout() {
+    // accepts connections but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((connection, _)) = listener.accept().await {
+            connections.push(connection);
+        }
+    });
+
+    let network = NetworkSettings {
+        timeout: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+    let broker_url = format!("http://{}", address);
+    let proxy_url = proxy::start(LocalProxy {
+        upstream: network
+            .apply(reqwest::Client::builder())
+            .unwrap()
+            .build()
+            .unwrap(),
+        broker_url: broker_url.clone(),
+        tracer: Arc::new(Tracer::new(0, None)),
+        recorder: None,
+        player: None,
+    })
+    .await
+    .unwrap();
+
+    // the proxied client waits longer than the proxy, which must report the timeout
+    let client = Client::builder(&broker_url)
+        .network(NetworkSettings {
+            timeout: Some(Duration::from_secs(10)),
+            ..Default::default()
+        })
+        .local_proxy(&proxy_url)
+        .build()
+        .unwrap();
+    match client.catalog().await {
+        Err(ClientError::Timeout(_)) => {}
+        result => panic!("expected a timeout, got {:?}", result.map(|_| ())),
+    }
+}